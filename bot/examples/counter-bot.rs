@@ -0,0 +1,49 @@
+// You need to make a file called `test_creds.test` in the root of the project for
+// this example to run. The account should be a bot.
+// The file is a JSON file with the following format
+// {
+//     "username":"yourusername",
+//     "password":"yourpassword"
+// }
+
+use socialvoid_bot::middleware::{RateLimit, ReportErrors};
+use socialvoid_bot::{Bot, Context, HandlerResult};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+async fn count(cx: Context<AtomicUsize>) -> HandlerResult {
+    let count = cx.state.fetch_add(1, Ordering::SeqCst) + 1;
+    cx.reply(format!("You're number {}!", count)).await?;
+    Ok(())
+}
+
+async fn hello(cx: Context<AtomicUsize>) -> HandlerResult {
+    cx.reply("Hello! Send /count to count.").await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let creds: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string("test_creds.test").unwrap())
+            .expect("Couldn't read the credentials. Check the JSON format or something");
+
+    let sv = socialvoid::new_with_defaults().await.unwrap();
+    sv.session
+        .authenticate_user(
+            creds["username"].as_str().unwrap().to_string(),
+            creds["password"].as_str().unwrap().to_string(),
+            None,
+        )
+        .await
+        .unwrap();
+
+    Bot::new(sv, AtomicUsize::new(0))
+        .middleware(ReportErrors::default())
+        .middleware(RateLimit::new(5, Duration::from_secs(60)))
+        .command("count", count)
+        .on_mention(hello)
+        .run()
+        .await
+        .unwrap();
+}
@@ -0,0 +1,87 @@
+// You need to make a file called `test_creds.test` in the root of the project for
+// this example to run. The account should be a bot.
+// The file is a JSON file with the following format
+// {
+//     "username":"yourusername",
+//     "password":"yourpassword"
+// }
+
+use socialvoid_bot::conversation::{ConversationKey, JsonFileStore};
+use socialvoid_bot::{Bot, BotError, Context, Conversations, HandlerResult, Step};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The answers given so far
+type Survey = Vec<String>;
+
+const QUESTIONS: [&str; 2] = [
+    "What's your favourite color?",
+    "What's your favourite food?",
+];
+
+struct State {
+    surveys: Arc<Conversations<Survey>>,
+}
+
+async fn start(cx: Context<State>) -> HandlerResult {
+    cx.state.surveys.start(&cx, vec![]).await?;
+    cx.reply(QUESTIONS[0]).await?;
+    Ok(())
+}
+
+async fn cancel(cx: Context<State>) -> HandlerResult {
+    if let Some(key) = ConversationKey::of(&cx.post) {
+        cx.state.surveys.end(&key).await?;
+    }
+    cx.reply("Survey cancelled.").await?;
+    Ok(())
+}
+
+async fn answer(cx: Context<State>, mut answers: Survey) -> Result<Step<Survey>, BotError> {
+    answers.push(cx.post.text.clone().unwrap_or_default());
+    match QUESTIONS.get(answers.len()) {
+        Some(question) => {
+            cx.reply(*question).await?;
+            Ok(Step::Next(answers))
+        }
+        None => {
+            cx.reply(format!("Thanks! You answered: {}", answers.join(", ")))
+                .await?;
+            Ok(Step::End)
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let creds: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string("test_creds.test").unwrap())
+            .expect("Couldn't read the credentials. Check the JSON format or something");
+
+    let sv = socialvoid::new_with_defaults().await.unwrap();
+    sv.session
+        .authenticate_user(
+            creds["username"].as_str().unwrap().to_string(),
+            creds["password"].as_str().unwrap().to_string(),
+            None,
+        )
+        .await
+        .unwrap();
+
+    let surveys = Arc::new(Conversations::new(
+        JsonFileStore::open("surveys.json").unwrap(),
+        Duration::from_secs(600),
+    ));
+    Bot::new(
+        sv,
+        State {
+            surveys: Arc::clone(&surveys),
+        },
+    )
+    .command("survey", start)
+    .command("cancel", cancel)
+    .conversation(surveys, answer)
+    .run()
+    .await
+    .unwrap();
+}
@@ -0,0 +1,64 @@
+/// A `/command` found at the start of a post
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    /// The name of the command, without the `/`
+    pub name: String,
+    /// Everything after the name, trimmed
+    pub text: String,
+}
+
+impl Command {
+    /// The words after the name
+    pub fn args(&self) -> Vec<&str> {
+        self.text.split_whitespace().collect()
+    }
+
+    /// Parses the command at the start of a post of the bot `username`.
+    /// Leading mentions (`@bot /start`) are skipped. A command addressed to another bot
+    /// (`/start@other_bot`) isn't parsed.
+    pub fn parse(text: &str, username: &str) -> Option<Command> {
+        let mut text = text.trim_start();
+        while let Some(rest) = text.strip_prefix('@') {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            text = rest[end..].trim_start();
+        }
+        let text = text.strip_prefix('/')?;
+        let end = text.find(char::is_whitespace).unwrap_or(text.len());
+        let (name, rest) = text.split_at(end);
+        let name = match name.split_once('@') {
+            Some((name, bot)) if bot.eq_ignore_ascii_case(username) => name,
+            Some(_) => return None,
+            None => name,
+        };
+        if name.is_empty() {
+            return None;
+        }
+        Some(Command {
+            name: name.to_string(),
+            text: rest.trim().to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_parse_commands() {
+        let command = Command::parse("/start", "bot").unwrap();
+        assert_eq!(command.name, "start");
+        assert!(command.args().is_empty());
+
+        let command = Command::parse("@bot @ryuk  /roll 2 d6\n", "bot").unwrap();
+        assert_eq!(command.name, "roll");
+        assert_eq!(command.text, "2 d6");
+        assert_eq!(command.args(), vec!["2", "d6"]);
+
+        assert_eq!(Command::parse("/help@Bot", "bot").unwrap().name, "help");
+        assert!(Command::parse("/help@other_bot", "bot").is_none());
+        assert!(Command::parse("hello /help", "bot").is_none());
+        assert!(Command::parse("/ help", "bot").is_none());
+        assert!(Command::parse("@bot", "bot").is_none());
+    }
+}
@@ -0,0 +1,63 @@
+use crate::command::Command;
+use socialvoid::{Client, SocialvoidError};
+use socialvoid_types::{Peer, Post};
+use std::sync::Arc;
+
+/// Why a handler was called
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Update {
+    /// A post starting with a command: on the timeline, mentioning the bot or replying to it
+    Command(Command),
+    /// A post mentioning the bot
+    Mention,
+    /// A reply to a post of the bot
+    Reply,
+}
+
+/// What a handler is called with
+pub struct Context<S> {
+    pub client: Arc<Client>,
+    /// The state shared by every handler of the bot
+    pub state: Arc<S>,
+    /// The peer of the bot
+    pub me: Arc<Peer>,
+    /// The post the handler is called for
+    pub post: Arc<Post>,
+    pub update: Update,
+}
+
+// not derived so `S` doesn't have to be `Clone`
+impl<S> Clone for Context<S> {
+    fn clone(&self) -> Self {
+        Context {
+            client: Arc::clone(&self.client),
+            state: Arc::clone(&self.state),
+            me: Arc::clone(&self.me),
+            post: Arc::clone(&self.post),
+            update: self.update.clone(),
+        }
+    }
+}
+
+impl<S> Context<S> {
+    /// The peer that published the post
+    pub fn author(&self) -> Option<&Peer> {
+        self.post.peer.as_ref()
+    }
+
+    /// The command the handler is called for, if any
+    pub fn command(&self) -> Option<&Command> {
+        match &self.update {
+            Update::Command(command) => Some(command),
+            _ => None,
+        }
+    }
+
+    /// Replies to the post
+    pub async fn reply(&self, text: impl Into<String>) -> Result<Post, SocialvoidError> {
+        self.client
+            .timeline
+            .reply(self.post.id.clone(), text.into(), vec![])
+            .await
+    }
+}
@@ -0,0 +1,537 @@
+//! Dialogues of several steps with a peer, e.g. a survey.
+//! A handler starts a conversation with `Conversations::start`, then every reply (or mention) of
+//! the peer in the same thread is handled by the step handler of the conversation, with its
+//! current state, instead of the reply or mention handler.
+
+use crate::context::{Context, Update};
+use crate::handler::{BotError, Handler, HandlerResult};
+use futures::future::{BoxFuture, FutureExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use socialvoid::environment::{Clock, SystemClock};
+use socialvoid_types::{PeerId, Post, PostId};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Identifies a conversation: a peer in a thread
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConversationKey {
+    /// The original post of the thread
+    pub thread: PostId,
+    pub peer: PeerId,
+}
+
+impl ConversationKey {
+    /// The conversation a post is part of, `None` if its author is unavailable
+    pub fn of(post: &Post) -> Option<Self> {
+        let thread = match &post.original_thread_post {
+            Some(original) => original.id.clone(),
+            None => post.id.clone(),
+        };
+        Some(ConversationKey {
+            thread,
+            peer: post.peer.as_ref()?.id.clone(),
+        })
+    }
+}
+
+/// A conversation as saved in a `ConversationStore`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Conversation<C> {
+    pub state: C,
+    /// The unix timestamp after which the conversation is over
+    pub expires_at: u64,
+}
+
+impl<C> Conversation<C> {
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+}
+
+/// Where the conversations are saved
+pub trait ConversationStore<C>: Send + Sync {
+    fn load<'a>(
+        &'a self,
+        key: &'a ConversationKey,
+    ) -> BoxFuture<'a, Result<Option<Conversation<C>>, BotError>>;
+
+    /// Saves a conversation. The conversations expired at `now`, the time of the clock of the
+    /// `Conversations`, can be removed.
+    fn save<'a>(
+        &'a self,
+        key: &'a ConversationKey,
+        conversation: Conversation<C>,
+        now: u64,
+    ) -> BoxFuture<'a, Result<(), BotError>>;
+
+    fn remove<'a>(&'a self, key: &'a ConversationKey) -> BoxFuture<'a, Result<(), BotError>>;
+}
+
+/// Keeps the conversations in memory, they are lost when the bot stops
+pub struct MemoryStore<C> {
+    conversations: Mutex<HashMap<ConversationKey, Conversation<C>>>,
+}
+
+impl<C> MemoryStore<C> {
+    pub fn new() -> Self {
+        MemoryStore {
+            conversations: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<C> Default for MemoryStore<C> {
+    fn default() -> Self {
+        MemoryStore::new()
+    }
+}
+
+impl<C: Clone + Send> ConversationStore<C> for MemoryStore<C> {
+    fn load<'a>(
+        &'a self,
+        key: &'a ConversationKey,
+    ) -> BoxFuture<'a, Result<Option<Conversation<C>>, BotError>> {
+        let conversation = self.conversations.lock().unwrap().get(key).cloned();
+        async move { Ok(conversation) }.boxed()
+    }
+
+    fn save<'a>(
+        &'a self,
+        key: &'a ConversationKey,
+        conversation: Conversation<C>,
+        now: u64,
+    ) -> BoxFuture<'a, Result<(), BotError>> {
+        let mut conversations = self.conversations.lock().unwrap();
+        conversations.retain(|_, conversation| !conversation.is_expired(now));
+        conversations.insert(key.clone(), conversation);
+        async { Ok(()) }.boxed()
+    }
+
+    fn remove<'a>(&'a self, key: &'a ConversationKey) -> BoxFuture<'a, Result<(), BotError>> {
+        self.conversations.lock().unwrap().remove(key);
+        async { Ok(()) }.boxed()
+    }
+}
+
+/// Saves the conversations to a JSON file so they survive a restart of the bot
+pub struct JsonFileStore<C> {
+    path: PathBuf,
+    conversations: Mutex<HashMap<ConversationKey, Conversation<C>>>,
+}
+
+impl<C: DeserializeOwned> JsonFileStore<C> {
+    /// Loads the conversations saved to `path`, if it exists
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, BotError> {
+        let path = path.into();
+        let conversations: Vec<(ConversationKey, Conversation<C>)> = if path.exists() {
+            serde_json::from_slice(&std::fs::read(&path)?)?
+        } else {
+            vec![]
+        };
+        Ok(JsonFileStore {
+            path,
+            conversations: Mutex::new(conversations.into_iter().collect()),
+        })
+    }
+}
+
+impl<C: Serialize> JsonFileStore<C> {
+    fn write(&self, conversations: &HashMap<ConversationKey, Conversation<C>>) -> HandlerResult {
+        // JSON objects only have string keys
+        let conversations: Vec<_> = conversations.iter().collect();
+        std::fs::write(&self.path, serde_json::to_vec(&conversations)?)?;
+        Ok(())
+    }
+}
+
+impl<C: Serialize + Clone + Send> ConversationStore<C> for JsonFileStore<C> {
+    fn load<'a>(
+        &'a self,
+        key: &'a ConversationKey,
+    ) -> BoxFuture<'a, Result<Option<Conversation<C>>, BotError>> {
+        let conversation = self.conversations.lock().unwrap().get(key).cloned();
+        async move { Ok(conversation) }.boxed()
+    }
+
+    fn save<'a>(
+        &'a self,
+        key: &'a ConversationKey,
+        conversation: Conversation<C>,
+        now: u64,
+    ) -> BoxFuture<'a, Result<(), BotError>> {
+        let mut conversations = self.conversations.lock().unwrap();
+        conversations.retain(|_, conversation| !conversation.is_expired(now));
+        conversations.insert(key.clone(), conversation);
+        let result = self.write(&conversations);
+        async move { result }.boxed()
+    }
+
+    fn remove<'a>(&'a self, key: &'a ConversationKey) -> BoxFuture<'a, Result<(), BotError>> {
+        let mut conversations = self.conversations.lock().unwrap();
+        let result = match conversations.remove(key) {
+            Some(_) => self.write(&conversations),
+            None => Ok(()),
+        };
+        async move { result }.boxed()
+    }
+}
+
+/// What a step handler returns
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step<C> {
+    /// Waits for the next post of the peer with this state
+    Next(C),
+    End,
+}
+
+/// Handles a post of a conversation with its current state.
+/// Implemented by every `async fn(Context<S>, C) -> Result<Step<C>, BotError>`.
+pub trait StepHandler<S, C>: Send + Sync {
+    fn call(&self, cx: Context<S>, state: C) -> BoxFuture<'static, Result<Step<C>, BotError>>;
+}
+
+impl<S, C, F, Fut> StepHandler<S, C> for F
+where
+    F: Fn(Context<S>, C) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Step<C>, BotError>> + Send + 'static,
+{
+    fn call(&self, cx: Context<S>, state: C) -> BoxFuture<'static, Result<Step<C>, BotError>> {
+        self(cx, state).boxed()
+    }
+}
+
+/// The conversations of one kind, e.g. the surveys.
+/// A conversation without a post of the peer for `timeout` is over: its next post is handled
+/// like any other.
+pub struct Conversations<C> {
+    store: Box<dyn ConversationStore<C>>,
+    timeout: Duration,
+    clock: Arc<dyn Clock>,
+    /// Makes the posts of a conversation handled one at a time
+    locks: Mutex<HashMap<ConversationKey, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl<C: Send + 'static> Conversations<C> {
+    pub fn new(store: impl ConversationStore<C> + 'static, timeout: Duration) -> Self {
+        Conversations::with_clock(store, timeout, Arc::new(SystemClock))
+    }
+
+    /// Like `new`, with the conversations expiring according to `clock`
+    pub fn with_clock(
+        store: impl ConversationStore<C> + 'static,
+        timeout: Duration,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Conversations {
+            store: Box::new(store),
+            timeout,
+            clock,
+            locks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Starts (or restarts) the conversation of the post of the context
+    pub async fn start<S>(&self, cx: &Context<S>, state: C) -> HandlerResult {
+        let key = ConversationKey::of(&cx.post).ok_or("the author of the post is unavailable")?;
+        self.save(&key, state).await
+    }
+
+    /// The state of a conversation that isn't over
+    pub async fn get(&self, key: &ConversationKey) -> Result<Option<C>, BotError> {
+        Ok(self.load(key).await?.map(|c| c.state))
+    }
+
+    /// Ends a conversation
+    pub async fn end(&self, key: &ConversationKey) -> HandlerResult {
+        self.store.remove(key).await
+    }
+
+    /// Calls the step handler if the post of the context is part of a conversation.
+    /// Returns false if it isn't. The state isn't changed if the handler fails.
+    pub(crate) async fn step<S>(
+        &self,
+        cx: Context<S>,
+        handler: &dyn StepHandler<S, C>,
+    ) -> Result<bool, BotError> {
+        let key = match ConversationKey::of(&cx.post) {
+            Some(key) => key,
+            None => return Ok(false),
+        };
+        let lock = Arc::clone(self.locks.lock().unwrap().entry(key.clone()).or_default());
+        let result = async {
+            let _guard = lock.lock().await;
+            let conversation = match self.load(&key).await? {
+                Some(conversation) => conversation,
+                None => return Ok(false),
+            };
+            match handler.call(cx, conversation.state).await? {
+                Step::Next(state) => self.save(&key, state).await?,
+                Step::End => self.store.remove(&key).await?,
+            }
+            Ok(true)
+        }
+        .await;
+        drop(lock);
+        self.locks
+            .lock()
+            .unwrap()
+            .retain(|_, lock| Arc::strong_count(lock) > 1);
+        result
+    }
+
+    async fn load(&self, key: &ConversationKey) -> Result<Option<Conversation<C>>, BotError> {
+        match self.store.load(key).await? {
+            Some(conversation) if conversation.is_expired(self.clock.now()) => {
+                self.store.remove(key).await?;
+                Ok(None)
+            }
+            conversation => Ok(conversation),
+        }
+    }
+
+    async fn save(&self, key: &ConversationKey, state: C) -> HandlerResult {
+        let now = self.clock.now();
+        let conversation = Conversation {
+            state,
+            expires_at: now.saturating_add(self.timeout.as_secs()),
+        };
+        self.store.save(key, conversation, now).await
+    }
+}
+
+/// The conversations of one kind with their step handler, as added to a `Bot`
+pub(crate) trait ConversationRoute<S>: Send + Sync {
+    fn step(&self, cx: Context<S>) -> BoxFuture<'static, Result<bool, BotError>>;
+}
+
+pub(crate) struct Route<C, H> {
+    pub(crate) conversations: Arc<Conversations<C>>,
+    pub(crate) handler: Arc<H>,
+}
+
+impl<S, C, H> ConversationRoute<S> for Route<C, H>
+where
+    S: Send + Sync + 'static,
+    C: Send + 'static,
+    H: StepHandler<S, C> + 'static,
+{
+    fn step(&self, cx: Context<S>) -> BoxFuture<'static, Result<bool, BotError>> {
+        let conversations = Arc::clone(&self.conversations);
+        let handler = Arc::clone(&self.handler);
+        async move { conversations.step(cx, handler.as_ref()).await }.boxed()
+    }
+}
+
+/// Gives the mentions and replies to the conversations first, then to the handler
+pub(crate) struct Dispatch<S> {
+    pub(crate) conversations: Vec<Arc<dyn ConversationRoute<S>>>,
+    pub(crate) handler: Option<Arc<dyn Handler<S>>>,
+}
+
+impl<S: Send + Sync + 'static> Handler<S> for Dispatch<S> {
+    fn call(&self, cx: Context<S>) -> BoxFuture<'static, HandlerResult> {
+        let conversations = match cx.update {
+            Update::Mention | Update::Reply => self.conversations.clone(),
+            // a command, e.g. `/cancel`, is always handled by its handler
+            Update::Command(_) => vec![],
+        };
+        let handler = self.handler.clone();
+        async move {
+            for conversation in conversations {
+                if conversation.step(cx.clone()).await? {
+                    return Ok(());
+                }
+            }
+            match handler {
+                Some(handler) => handler.call(cx).await,
+                None => Ok(()),
+            }
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use socialvoid::environment::ManualClock;
+    use socialvoid_types::{Peer, PeerType, PostType};
+
+    fn post(id: &str, author: &str, thread: Option<&str>) -> Post {
+        Post {
+            id: id.into(),
+            post_type: PostType::Post,
+            peer: Some(Peer {
+                id: author.into(),
+                peer_type: PeerType::USER,
+                name: author.to_string(),
+                username: author.to_string(),
+                flags: vec![],
+            }),
+            source: None,
+            text: Some(id.to_string()),
+            attachments: vec![],
+            entities: vec![],
+            mentioned_peers: vec![],
+            reply_to_post: None,
+            quoted_post: None,
+            reposted_post: None,
+            original_thread_post: thread.map(|thread| Box::new(post(thread, "bot", None))),
+            like_count: None,
+            repost_count: None,
+            quote_count: None,
+            reply_count: None,
+            posted_timestamp: 0,
+            flags: vec![],
+        }
+    }
+
+    fn context(post: Post) -> Context<()> {
+        Context {
+            client: Arc::new(socialvoid::new_empty_client()),
+            state: Arc::new(()),
+            me: Arc::new(post.peer.clone().unwrap()),
+            post: Arc::new(post),
+            update: Update::Reply,
+        }
+    }
+
+    /// Counts to 2 then ends
+    async fn count(_: Context<()>, state: u32) -> Result<Step<u32>, BotError> {
+        if state == 2 {
+            return Ok(Step::End);
+        }
+        Ok(Step::Next(state + 1))
+    }
+
+    #[test]
+    fn it_should_key_conversations_on_the_thread_and_the_peer() {
+        let start = ConversationKey::of(&post("a", "light", None)).unwrap();
+        let reply = ConversationKey::of(&post("b", "light", Some("a"))).unwrap();
+        assert_eq!(start, reply);
+        assert_ne!(
+            start,
+            ConversationKey::of(&post("c", "ryuk", Some("a"))).unwrap()
+        );
+        assert_eq!(start.thread, "a");
+    }
+
+    #[tokio::test]
+    async fn it_should_step_through_a_conversation() -> HandlerResult {
+        let conversations = Conversations::new(MemoryStore::new(), Duration::from_secs(60));
+        let handler: &dyn StepHandler<(), u32> = &count;
+        assert!(
+            !conversations
+                .step(context(post("a", "light", None)), handler)
+                .await?
+        );
+
+        conversations
+            .start(&context(post("a", "light", None)), 0)
+            .await?;
+        let key = ConversationKey::of(&post("a", "light", None)).unwrap();
+        // another peer in the thread
+        assert!(
+            !conversations
+                .step(context(post("b", "ryuk", Some("a"))), handler)
+                .await?
+        );
+        assert!(
+            conversations
+                .step(context(post("c", "light", Some("a"))), handler)
+                .await?
+        );
+        assert_eq!(conversations.get(&key).await?, Some(1));
+        assert!(
+            conversations
+                .step(context(post("d", "light", Some("a"))), handler)
+                .await?
+        );
+        assert!(
+            conversations
+                .step(context(post("e", "light", Some("a"))), handler)
+                .await?
+        );
+        assert_eq!(conversations.get(&key).await?, None);
+        assert!(conversations.locks.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_end_expired_conversations() -> HandlerResult {
+        let clock = Arc::new(ManualClock::new(SystemClock.now()));
+        let conversations = Conversations::with_clock(
+            MemoryStore::new(),
+            Duration::from_secs(60),
+            Arc::clone(&clock) as Arc<dyn Clock>,
+        );
+        let key = ConversationKey::of(&post("a", "light", None)).unwrap();
+        conversations
+            .start(&context(post("a", "light", None)), 0)
+            .await?;
+        clock.advance(59);
+        assert_eq!(conversations.get(&key).await?, Some(0));
+        clock.advance(1);
+        assert_eq!(conversations.get(&key).await?, None);
+        assert!(conversations.store.load(&key).await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_the_conversations_live_on_the_clock() -> HandlerResult {
+        // far behind the system time, where every conversation would look expired
+        let clock = Arc::new(ManualClock::new(0));
+        let conversations = Conversations::with_clock(
+            MemoryStore::new(),
+            Duration::from_secs(60),
+            Arc::clone(&clock) as Arc<dyn Clock>,
+        );
+        let (first, second) = (post("a", "light", None), post("b", "ryuk", None));
+        conversations.start(&context(first.clone()), 0).await?;
+        conversations.start(&context(second.clone()), 0).await?;
+        let (first, second) = (
+            ConversationKey::of(&first).unwrap(),
+            ConversationKey::of(&second).unwrap(),
+        );
+        assert!(conversations.store.load(&first).await?.is_some());
+
+        // far ahead of the system time, where no conversation would look expired
+        clock.set(u64::MAX / 2);
+        conversations
+            .start(&context(post("c", "near", None)), 0)
+            .await?;
+        assert!(conversations.store.load(&first).await?.is_none());
+        assert!(conversations.store.load(&second).await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_save_conversations_to_a_file() -> HandlerResult {
+        let path = std::env::temp_dir().join(format!(
+            "socialvoid_bot_conversations_{}.json",
+            std::process::id()
+        ));
+        let key = ConversationKey::of(&post("a", "light", None)).unwrap();
+        let conversation = Conversation {
+            state: "question 2".to_string(),
+            expires_at: u64::MAX,
+        };
+        JsonFileStore::open(&path)?
+            .save(&key, conversation.clone(), 0)
+            .await?;
+
+        let store = JsonFileStore::<String>::open(&path)?;
+        assert_eq!(store.load(&key).await?, Some(conversation));
+        store.remove(&key).await?;
+        assert_eq!(
+            JsonFileStore::<String>::open(&path)?.load(&key).await?,
+            None
+        );
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use crate::context::Context;
+use futures::future::{BoxFuture, FutureExt};
+use std::future::Future;
+use std::sync::Arc;
+
+/// The error of a handler. Any error can be returned with `?`.
+pub type BotError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type HandlerResult = Result<(), BotError>;
+
+/// Handles a command, a mention or a reply.
+/// Implemented by every `async fn(Context<S>) -> HandlerResult`.
+pub trait Handler<S>: Send + Sync {
+    fn call(&self, cx: Context<S>) -> BoxFuture<'static, HandlerResult>;
+}
+
+impl<S, F, Fut> Handler<S> for F
+where
+    F: Fn(Context<S>) -> Fut + Send + Sync,
+    Fut: Future<Output = HandlerResult> + Send + 'static,
+{
+    fn call(&self, cx: Context<S>) -> BoxFuture<'static, HandlerResult> {
+        self(cx).boxed()
+    }
+}
+
+/// Wraps the handlers, e.g. to check who is calling them or to report their errors.
+/// A middleware decides whether the rest of the chain runs by calling `next.run` or not.
+pub trait Middleware<S>: Send + Sync {
+    fn call<'a>(&'a self, cx: Context<S>, next: Next<'a, S>) -> BoxFuture<'a, HandlerResult>;
+}
+
+/// The middlewares left to run, then the handler
+pub struct Next<'a, S> {
+    pub(crate) middlewares: &'a [Arc<dyn Middleware<S>>],
+    pub(crate) handler: &'a dyn Handler<S>,
+}
+
+impl<'a, S> Next<'a, S> {
+    pub async fn run(self, cx: Context<S>) -> HandlerResult {
+        match self.middlewares.split_first() {
+            Some((middleware, middlewares)) => {
+                let next = Next {
+                    middlewares,
+                    handler: self.handler,
+                };
+                middleware.call(cx, next).await
+            }
+            None => self.handler.call(cx).await,
+        }
+    }
+}
@@ -0,0 +1,534 @@
+//! A framework for Socialvoid bots.
+//! A `Bot` watches the timeline and the notifications of its peer and calls a handler for every
+//! command, mention and reply, through the middlewares.
+
+mod command;
+mod context;
+pub mod conversation;
+mod handler;
+pub mod middleware;
+
+pub use command::Command;
+pub use context::{Context, Update};
+pub use conversation::{Conversations, Step, StepHandler};
+pub use handler::{BotError, Handler, HandlerResult, Middleware, Next};
+
+use conversation::{ConversationRoute, Dispatch, Route};
+use futures::stream::StreamExt;
+use socialvoid::notifications::{
+    Notification, NotificationWatcher, NotificationWatcherConfig, WatchEvent,
+};
+use socialvoid::timeline::FeedEvent;
+use socialvoid::{Client, SocialvoidError};
+use socialvoid_types::{Peer, PeerId, Post};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Configures a `Bot`
+#[derive(Debug, Clone)]
+pub struct BotConfig {
+    /// How the notifications are watched. The commands on the timeline are found with the same
+    /// feed polls.
+    pub watcher: NotificationWatcherConfig,
+    /// Also handle the commands posted on the timeline without mentioning the bot
+    pub commands_on_timeline: bool,
+    /// How long the handlers still running are waited for when the bot stops
+    pub shutdown_timeout: Duration,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        BotConfig {
+            watcher: NotificationWatcherConfig::default(),
+            commands_on_timeline: true,
+            shutdown_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Dispatches the commands, mentions and replies to the handlers.
+/// Every handler runs in its own task, after the middlewares in the order they were added.
+/// A post with a command no handler was added for is ignored. The mentions and replies that are
+/// part of a conversation go to its step handler instead of the mention or reply handler.
+pub struct Bot<S> {
+    client: Arc<Client>,
+    state: Arc<S>,
+    config: BotConfig,
+    commands: HashMap<String, Arc<dyn Handler<S>>>,
+    mention_handler: Option<Arc<dyn Handler<S>>>,
+    reply_handler: Option<Arc<dyn Handler<S>>>,
+    conversations: Vec<Arc<dyn ConversationRoute<S>>>,
+    middlewares: Vec<Arc<dyn Middleware<S>>>,
+    error_handler: Arc<dyn Fn(&BotError) + Send + Sync>,
+}
+
+impl<S: Send + Sync + 'static> Bot<S> {
+    /// Creates a bot for the authenticated peer of the client, with the state shared by the
+    /// handlers
+    pub fn new(client: Client, state: S) -> Self {
+        Bot {
+            client: Arc::new(client),
+            state: Arc::new(state),
+            config: BotConfig::default(),
+            commands: HashMap::new(),
+            mention_handler: None,
+            reply_handler: None,
+            conversations: vec![],
+            middlewares: vec![],
+            error_handler: Arc::new(|err| eprintln!("{}", err)),
+        }
+    }
+
+    pub fn config(mut self, config: BotConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Handles `/name`
+    pub fn command(mut self, name: impl Into<String>, handler: impl Handler<S> + 'static) -> Self {
+        self.commands.insert(name.into(), Arc::new(handler));
+        self
+    }
+
+    /// Handles the posts mentioning the bot that don't start with a command
+    pub fn on_mention(mut self, handler: impl Handler<S> + 'static) -> Self {
+        self.mention_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Handles the replies to the posts of the bot that don't start with a command
+    pub fn on_reply(mut self, handler: impl Handler<S> + 'static) -> Self {
+        self.reply_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Handles the posts of the conversations started with `conversations.start`.
+    /// The conversations are tried in the order they were added.
+    pub fn conversation<C, H>(mut self, conversations: Arc<Conversations<C>>, handler: H) -> Self
+    where
+        C: Send + 'static,
+        H: StepHandler<S, C> + 'static,
+    {
+        self.conversations.push(Arc::new(Route {
+            conversations,
+            handler: Arc::new(handler),
+        }));
+        self
+    }
+
+    pub fn middleware(mut self, middleware: impl Middleware<S> + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Called with the errors of the handlers and of the polls. They are printed to stderr by
+    /// default.
+    pub fn on_error(mut self, error_handler: impl Fn(&BotError) + Send + Sync + 'static) -> Self {
+        self.error_handler = Arc::new(error_handler);
+        self
+    }
+
+    /// Runs the bot until Ctrl-C is pressed
+    pub async fn run(self) -> Result<(), SocialvoidError> {
+        self.run_until(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+    }
+
+    /// Runs the bot until `shutdown` completes, then waits for the handlers still running, up to
+    /// `BotConfig::shutdown_timeout`.
+    /// Fails if the peer of the bot can't be retrieved, later errors go to the error handler.
+    pub async fn run_until(
+        self,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<(), SocialvoidError> {
+        let me = Arc::new(self.client.network.get_me().await?);
+        // the timeline is only polled by the notification watcher
+        let events = NotificationWatcher::new(
+            Arc::clone(&self.client.network),
+            Arc::clone(&self.client.timeline),
+            self.config.watcher.clone(),
+        )?
+        .watch_with_feed()
+        .filter_map(|event| async move {
+            match event {
+                Ok(WatchEvent::Notification(notification)) => {
+                    Some(Ok(Event::Notification(notification)))
+                }
+                Ok(WatchEvent::Feed(FeedEvent::NewPost(post))) => Some(Ok(Event::Timeline(post))),
+                Ok(WatchEvent::Feed(_)) => None,
+                Err(err) => Some(Err(err)),
+            }
+        });
+        tokio::pin!(events);
+        tokio::pin!(shutdown);
+
+        let middlewares: Arc<[Arc<dyn Middleware<S>>]> = self.middlewares.clone().into();
+        // every handler task holds a sender, so the receiver is closed once they all finished
+        let (running, mut finished) = mpsc::channel::<()>(1);
+        loop {
+            let event = tokio::select! {
+                _ = &mut shutdown => break,
+                event = events.next() => event,
+            };
+            let event = match event {
+                Some(Ok(event)) => event,
+                Some(Err(err)) => {
+                    (self.error_handler)(&err.into());
+                    continue;
+                }
+                None => break,
+            };
+            let (post, update) = match self.route(&me, event) {
+                Some(route) => route,
+                None => continue,
+            };
+            let handler = match self.dispatch(&update) {
+                Some(handler) => handler,
+                None => continue,
+            };
+            let cx = Context {
+                client: Arc::clone(&self.client),
+                state: Arc::clone(&self.state),
+                me: Arc::clone(&me),
+                post: Arc::new(*post),
+                update,
+            };
+            let middlewares = Arc::clone(&middlewares);
+            let error_handler = Arc::clone(&self.error_handler);
+            let running = running.clone();
+            tokio::spawn(async move {
+                let next = Next {
+                    middlewares: &middlewares,
+                    handler: &handler,
+                };
+                if let Err(err) = next.run(cx).await {
+                    error_handler(&err);
+                }
+                drop(running);
+            });
+        }
+        drop(running);
+        let _ = tokio::time::timeout(self.config.shutdown_timeout, finished.recv()).await;
+        Ok(())
+    }
+
+    /// Finds what an event of the bot `me` is for
+    fn route(&self, me: &Peer, event: Event) -> Option<(Box<Post>, Update)> {
+        let (post, update) = match event {
+            Event::Notification(Notification::Mentioned(post)) => (post, Some(Update::Mention)),
+            Event::Notification(Notification::RepliedTo(post)) => (post, Some(Update::Reply)),
+            // the posts for the bot are handled as notifications
+            Event::Timeline(post)
+                if self.config.commands_on_timeline
+                    && author(&post) != Some(&me.id)
+                    && !is_for(&me.id, &post) =>
+            {
+                (post, None)
+            }
+            _ => return None,
+        };
+        match post
+            .text
+            .as_deref()
+            .and_then(|text| Command::parse(text, &me.username))
+        {
+            Some(command) if self.commands.contains_key(&command.name) => {
+                Some((post, Update::Command(command)))
+            }
+            Some(_) => None,
+            None => update.map(|update| (post, update)),
+        }
+    }
+
+    /// The handler of an update, `None` if nothing handles it
+    fn dispatch(&self, update: &Update) -> Option<Dispatch<S>> {
+        let handler = match update {
+            Update::Command(command) => {
+                return self.commands.get(&command.name).map(|handler| Dispatch {
+                    conversations: vec![],
+                    handler: Some(Arc::clone(handler)),
+                })
+            }
+            Update::Mention => self.mention_handler.clone(),
+            Update::Reply => self.reply_handler.clone(),
+        };
+        if handler.is_none() && self.conversations.is_empty() {
+            return None;
+        }
+        Some(Dispatch {
+            conversations: self.conversations.clone(),
+            handler,
+        })
+    }
+}
+
+/// What the bot watches
+enum Event {
+    Notification(Notification),
+    /// A new post of the timeline
+    Timeline(Box<Post>),
+}
+
+fn author(post: &Post) -> Option<&PeerId> {
+    post.peer.as_ref().map(|peer| &peer.id)
+}
+
+/// Whether a post mentions `me` or replies to, quotes or reposts one of its posts, i.e. whether
+/// it's also a notification
+fn is_for(me: &PeerId, post: &Post) -> bool {
+    let by_me = |post: &Option<Box<Post>>| post.as_deref().and_then(author) == Some(me);
+    post.mentioned_peers.iter().any(|peer| &peer.id == me)
+        || by_me(&post.reply_to_post)
+        || by_me(&post.quoted_post)
+        || by_me(&post.reposted_post)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::{BoxFuture, FutureExt};
+    use socialvoid_types::{Peer, PeerType, PostType};
+    use std::sync::Mutex;
+
+    fn peer(username: &str) -> Peer {
+        Peer {
+            id: username.into(),
+            peer_type: PeerType::USER,
+            name: username.to_string(),
+            username: username.to_string(),
+            flags: vec![],
+        }
+    }
+
+    fn post(text: &str) -> Box<Post> {
+        Box::new(Post {
+            id: text.into(),
+            post_type: PostType::Post,
+            peer: Some(peer("light")),
+            source: None,
+            text: Some(text.to_string()),
+            attachments: vec![],
+            entities: vec![],
+            mentioned_peers: vec![],
+            reply_to_post: None,
+            quoted_post: None,
+            reposted_post: None,
+            original_thread_post: None,
+            like_count: None,
+            repost_count: None,
+            quote_count: None,
+            reply_count: None,
+            posted_timestamp: 0,
+            flags: vec![],
+        })
+    }
+
+    async fn ok(_: Context<()>) -> HandlerResult {
+        Ok(())
+    }
+
+    fn update(bot: &Bot<()>, event: Event) -> Option<Update> {
+        bot.route(&peer("bot"), event)
+            .map(|(_, update)| update)
+            .filter(|update| bot.dispatch(update).is_some())
+    }
+
+    #[test]
+    fn it_should_route_the_updates() {
+        let bot = Bot::new(socialvoid::new_empty_client(), ())
+            .command("start", ok)
+            .on_mention(ok);
+        let start = Update::Command(Command {
+            name: "start".to_string(),
+            text: String::new(),
+        });
+
+        let notification = |notification| update(&bot, Event::Notification(notification));
+        let timeline = |post| update(&bot, Event::Timeline(post));
+        assert_eq!(
+            notification(Notification::Mentioned(post("@bot /start"))),
+            Some(start.clone())
+        );
+        assert_eq!(
+            notification(Notification::Mentioned(post("@bot hi"))),
+            Some(Update::Mention)
+        );
+        // no reply handler
+        assert_eq!(notification(Notification::RepliedTo(post("thanks"))), None);
+        assert_eq!(notification(Notification::Quoted(post("/start"))), None);
+        assert_eq!(timeline(post("/start")), Some(start));
+        assert_eq!(timeline(post("/stop")), None);
+        assert_eq!(timeline(post("hello")), None);
+        // handled once, as a notification
+        let mut mention = post("@bot /start");
+        mention.mentioned_peers = vec![peer("bot")];
+        assert_eq!(timeline(mention), None);
+        let mut own = post("/start");
+        own.peer = Some(peer("bot"));
+        assert_eq!(timeline(own), None);
+
+        let bot = bot.config(BotConfig {
+            commands_on_timeline: false,
+            ..BotConfig::default()
+        });
+        assert_eq!(update(&bot, Event::Timeline(post("/start"))), None);
+    }
+
+    struct Record(&'static str, Arc<Mutex<Vec<&'static str>>>);
+
+    impl Middleware<()> for Record {
+        fn call<'a>(&'a self, cx: Context<()>, next: Next<'a, ()>) -> BoxFuture<'a, HandlerResult> {
+            async move {
+                self.1.lock().unwrap().push(self.0);
+                next.run(cx).await
+            }
+            .boxed()
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_run_the_middlewares_in_order() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let handler_calls = Arc::clone(&calls);
+        let handler = move |_: Context<()>| {
+            handler_calls.lock().unwrap().push("handler");
+            async { Ok(()) }
+        };
+        let middlewares: Vec<Arc<dyn Middleware<()>>> = vec![
+            Arc::new(Record("first", Arc::clone(&calls))),
+            Arc::new(middleware::AllowList::new(vec!["light".into()])),
+            Arc::new(Record("second", Arc::clone(&calls))),
+        ];
+        let cx = |author: &str| {
+            let mut post = post("/start");
+            post.peer = Some(peer(author));
+            Context {
+                client: Arc::new(socialvoid::new_empty_client()),
+                state: Arc::new(()),
+                me: Arc::new(peer("bot")),
+                post: Arc::new(*post),
+                update: Update::Mention,
+            }
+        };
+        let next = || Next {
+            middlewares: &middlewares,
+            handler: &handler,
+        };
+
+        next().run(cx("light")).await.unwrap();
+        assert_eq!(*calls.lock().unwrap(), vec!["first", "second", "handler"]);
+        calls.lock().unwrap().clear();
+        next().run(cx("ryuk")).await.unwrap();
+        assert_eq!(*calls.lock().unwrap(), vec!["first"]);
+    }
+
+    #[tokio::test]
+    async fn it_should_give_replies_to_the_conversations_first() -> HandlerResult {
+        use conversation::MemoryStore;
+
+        let calls = Arc::new(Mutex::new(vec![]));
+        let (step_calls, reply_calls) = (Arc::clone(&calls), Arc::clone(&calls));
+        let conversations = Arc::new(Conversations::new(
+            MemoryStore::new(),
+            Duration::from_secs(60),
+        ));
+        let bot = Bot::new(socialvoid::new_empty_client(), ())
+            .on_reply(move |_: Context<()>| {
+                reply_calls.lock().unwrap().push("reply");
+                async { Ok(()) }
+            })
+            .conversation(Arc::clone(&conversations), move |_: Context<()>, _: ()| {
+                step_calls.lock().unwrap().push("step");
+                async { Ok(Step::End) }
+            });
+        let cx = Context {
+            client: Arc::clone(&bot.client),
+            state: Arc::new(()),
+            me: Arc::new(peer("bot")),
+            post: Arc::new(*post("answer")),
+            update: Update::Reply,
+        };
+
+        conversations.start(&cx, ()).await?;
+        let dispatch = bot.dispatch(&Update::Reply).unwrap();
+        dispatch.call(cx.clone()).await?;
+        dispatch.call(cx).await?;
+        assert_eq!(*calls.lock().unwrap(), vec!["step", "reply"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_poll_the_timeline_once_per_tick() -> Result<(), BotError> {
+        use socialvoid::mock::cassette::Recorder;
+        use socialvoid::mock::MockServer;
+        use socialvoid::timeline::FeedWatcherConfig;
+        use socialvoid_types::PeerRef;
+
+        let server = MockServer::start().await?;
+        let recorder = Recorder::start(server.url(), server.url()).await?;
+        server.add_user("bot", "SuperStrongPassword", "Bot");
+        server.add_user("light", "SuperStrongPassword", "Light");
+        let sign_in = |sv: socialvoid::Client, username: &'static str| async move {
+            sv.session.create().await?;
+            sv.session
+                .authenticate_user(
+                    username.to_string(),
+                    "SuperStrongPassword".to_string(),
+                    None,
+                )
+                .await?;
+            Ok::<_, SocialvoidError>(sv)
+        };
+        let bot = sign_in(recorder.client().await?, "bot").await?;
+        let light = sign_in(server.client().await?, "light").await?;
+        bot.network
+            .follow_peer(PeerRef::Username("light".to_string()))
+            .await?;
+
+        let (started, mut handled) = mpsc::channel(1);
+        let bot = Bot::new(bot, ())
+            .config(BotConfig {
+                watcher: NotificationWatcherConfig {
+                    feed: FeedWatcherConfig {
+                        interval: Duration::from_millis(50),
+                        ..FeedWatcherConfig::default()
+                    },
+                    ..NotificationWatcherConfig::default()
+                },
+                ..BotConfig::default()
+            })
+            .command("start", move |_: Context<()>| {
+                let started = started.clone();
+                async move {
+                    let _ = started.send(()).await;
+                    Ok(())
+                }
+            });
+        let run = tokio::spawn(bot.run_until(async move {
+            handled.recv().await;
+        }));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        light.timeline.compose("/start", vec![]).await?;
+        run.await.unwrap()?;
+
+        let methods: Vec<_> = recorder
+            .cassette()
+            .interactions
+            .into_iter()
+            .map(|interaction| interaction.request.method)
+            .collect();
+        let count = |method: &str| methods.iter().filter(|called| *called == method).count();
+        // one feed request per tick, plus the first one finding the recent posts of the bot
+        assert!(count("network.get_followers") > 1);
+        assert_eq!(
+            count("timeline.retrieve_feed"),
+            count("network.get_followers") + 1
+        );
+        Ok(())
+    }
+}
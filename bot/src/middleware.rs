@@ -0,0 +1,187 @@
+//! The middlewares most bots need
+
+use crate::context::Context;
+use crate::handler::{HandlerResult, Middleware, Next};
+use futures::future::{BoxFuture, FutureExt};
+use socialvoid_types::PeerId;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Only lets the posts of some peers through, e.g. for the admin commands.
+/// The other posts are ignored, or answered with a message if one is set.
+pub struct AllowList {
+    peers: HashSet<PeerId>,
+    commands: Option<HashSet<String>>,
+    message: Option<String>,
+}
+
+impl AllowList {
+    pub fn new(peers: impl IntoIterator<Item = PeerId>) -> Self {
+        AllowList {
+            peers: peers.into_iter().collect(),
+            commands: None,
+            message: None,
+        }
+    }
+
+    /// Only checks these commands, everything else goes through
+    pub fn only_commands<T: Into<String>>(mut self, commands: impl IntoIterator<Item = T>) -> Self {
+        self.commands = Some(commands.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Replies with this message to the posts that aren't allowed
+    pub fn reply_with(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    fn allows<S>(&self, cx: &Context<S>) -> bool {
+        let checked = match (&self.commands, cx.command()) {
+            (None, _) => true,
+            (Some(commands), Some(command)) => commands.contains(&command.name),
+            (Some(_), None) => false,
+        };
+        !checked
+            || cx
+                .author()
+                .is_some_and(|peer| self.peers.contains(&peer.id))
+    }
+}
+
+impl<S: Send + Sync + 'static> Middleware<S> for AllowList {
+    fn call<'a>(&'a self, cx: Context<S>, next: Next<'a, S>) -> BoxFuture<'a, HandlerResult> {
+        async move {
+            if self.allows(&cx) {
+                return next.run(cx).await;
+            }
+            if let Some(message) = &self.message {
+                cx.reply(message.as_str()).await?;
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/// Limits how many posts of a peer are handled in a period of time.
+/// The posts over the limit are ignored, or answered with a message if one is set.
+pub struct RateLimit {
+    max: usize,
+    period: Duration,
+    message: Option<String>,
+    /// When the posts of each peer were handled during the last period
+    handled: Mutex<HashMap<PeerId, VecDeque<Instant>>>,
+}
+
+impl RateLimit {
+    /// Handles at most `max` posts of a peer every `period`
+    pub fn new(max: usize, period: Duration) -> Self {
+        RateLimit {
+            max,
+            period,
+            message: None,
+            handled: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Replies with this message to the posts over the limit
+    pub fn reply_with(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Records a post of `peer` at `now` if it's under the limit
+    fn allows(&self, peer: &PeerId, now: Instant) -> bool {
+        let mut handled = self.handled.lock().unwrap();
+        let period = self.period;
+        handled.retain(|_, times| {
+            while times
+                .front()
+                .is_some_and(|time| now.duration_since(*time) >= period)
+            {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+        let times = handled.entry(peer.clone()).or_default();
+        if times.len() >= self.max {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+}
+
+impl<S: Send + Sync + 'static> Middleware<S> for RateLimit {
+    fn call<'a>(&'a self, cx: Context<S>, next: Next<'a, S>) -> BoxFuture<'a, HandlerResult> {
+        async move {
+            let allowed = match cx.author() {
+                Some(peer) => self.allows(&peer.id, Instant::now()),
+                None => true,
+            };
+            if allowed {
+                return next.run(cx).await;
+            }
+            if let Some(message) = &self.message {
+                cx.reply(message.as_str()).await?;
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/// Replies with a message when a handler fails, so the peer isn't left without an answer.
+/// The error is still returned to the bot.
+pub struct ReportErrors {
+    message: String,
+}
+
+impl ReportErrors {
+    pub fn new(message: impl Into<String>) -> Self {
+        ReportErrors {
+            message: message.into(),
+        }
+    }
+}
+
+impl Default for ReportErrors {
+    fn default() -> Self {
+        ReportErrors::new("Something went wrong, please try again later.")
+    }
+}
+
+impl<S: Send + Sync + 'static> Middleware<S> for ReportErrors {
+    fn call<'a>(&'a self, cx: Context<S>, next: Next<'a, S>) -> BoxFuture<'a, HandlerResult> {
+        async move {
+            let result = next.run(cx.clone()).await;
+            if result.is_err() {
+                // the error of the handler is more useful than the one of the reply
+                let _ = cx.reply(self.message.as_str()).await;
+            }
+            result
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_limit_the_rate_per_peer() {
+        let limit = RateLimit::new(2, Duration::from_secs(10));
+        let (light, ryuk) = (PeerId::from("light"), PeerId::from("ryuk"));
+        let start = Instant::now();
+        assert!(limit.allows(&light, start));
+        assert!(limit.allows(&light, start + Duration::from_secs(1)));
+        assert!(!limit.allows(&light, start + Duration::from_secs(2)));
+        assert!(limit.allows(&ryuk, start + Duration::from_secs(2)));
+        // the first post is out of the period
+        assert!(limit.allows(&light, start + Duration::from_secs(10)));
+        assert!(!limit.allows(&light, start + Duration::from_secs(10)));
+    }
+}
@@ -1,3 +1,5 @@
+use socialvoid::notifications::Notification;
+use socialvoid::timeline::{Thread, ThreadNode};
 use socialvoid_types::*;
 
 use chrono::{DateTime, Utc};
@@ -5,6 +7,8 @@ use std::time::{Duration, UNIX_EPOCH};
 
 pub struct SVPost(Post);
 pub struct SVProfile(Profile);
+pub struct SVThread(Thread);
+pub struct SVNotification(Notification);
 
 impl std::convert::From<Post> for SVPost {
     fn from(post: Post) -> Self {
@@ -18,6 +22,18 @@ impl std::convert::From<Profile> for SVProfile {
     }
 }
 
+impl std::convert::From<Thread> for SVThread {
+    fn from(thread: Thread) -> Self {
+        Self(thread)
+    }
+}
+
+impl std::convert::From<Notification> for SVNotification {
+    fn from(notification: Notification) -> Self {
+        Self(notification)
+    }
+}
+
 impl std::fmt::Display for SVPost {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
@@ -37,21 +53,21 @@ Flags: ",
                 PostType::Reply => format!(
                     "Reply to <{}>",
                     match &self.0.reply_to_post {
-                        Some(reply_to_post) => reply_to_post.id.clone(),
+                        Some(reply_to_post) => reply_to_post.id.to_string(),
                         None => String::new(),
                     }
                 ),
                 PostType::Quote => format!(
                     "Quoted post <{}>",
                     match &self.0.quoted_post.as_ref() {
-                        Some(quoted_post) => quoted_post.id.clone(),
+                        Some(quoted_post) => quoted_post.id.to_string(),
                         None => String::new(),
                     }
                 ),
                 PostType::Repost => format!(
                     "Reposted post <{}>",
                     match &self.0.reposted_post.as_ref() {
-                        Some(reposted_post) => reposted_post.id.clone(),
+                        Some(reposted_post) => reposted_post.id.to_string(),
                         None => String::new(),
                     }
                 ),
@@ -143,3 +159,73 @@ Display Picture: {}",
         )
     }
 }
+
+impl std::fmt::Display for SVThread {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (depth, post) in self.0.ancestors.iter().enumerate() {
+            write_thread_post(f, post, depth)?;
+        }
+        write_thread_node(f, &self.0.root, self.0.ancestors.len())?;
+        if self.0.truncated {
+            write!(f, "[Some replies were left out]")?;
+        }
+        Ok(())
+    }
+}
+
+fn write_thread_node(
+    f: &mut std::fmt::Formatter,
+    node: &ThreadNode,
+    depth: usize,
+) -> std::fmt::Result {
+    write_thread_post(f, &node.post, depth)?;
+    for reply in &node.replies {
+        write_thread_node(f, reply, depth + 1)?;
+    }
+    Ok(())
+}
+
+/// Writes a post on one line, indented by its depth in the thread
+fn write_thread_post(f: &mut std::fmt::Formatter, post: &Post, depth: usize) -> std::fmt::Result {
+    writeln!(
+        f,
+        "{}{}@{}: {} <{}>",
+        "    ".repeat(depth.saturating_sub(1)),
+        if depth > 0 { "└── " } else { "" },
+        post.peer
+            .as_ref()
+            .map(|peer| peer.username.as_str())
+            .unwrap_or("<unavailable>"),
+        post.text
+            .as_deref()
+            .unwrap_or("<no text>")
+            .replace('\n', " "),
+        post.id
+    )
+}
+
+impl std::fmt::Display for SVNotification {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (action, post) = match &self.0 {
+            Notification::Mentioned(post) => ("mentioned you", post),
+            Notification::RepliedTo(post) => ("replied to you", post),
+            Notification::Quoted(post) => ("quoted you", post),
+            Notification::Reposted(post) => ("reposted your post", post),
+            Notification::NewFollower(peer) => return write!(f, "@{} followed you", peer.username),
+        };
+        write!(
+            f,
+            "@{} {}: {} <{}>",
+            post.peer
+                .as_ref()
+                .map(|peer| peer.username.as_str())
+                .unwrap_or("<unavailable>"),
+            action,
+            post.text
+                .as_deref()
+                .unwrap_or("<no text>")
+                .replace('\n', " "),
+            post.id
+        )
+    }
+}
@@ -1,7 +1,5 @@
-use socialvoid::{ClientError, SocialvoidError};
-use socialvoid_rawclient::AuthenticationError;
-use socialvoid_rawclient::ErrorKind;
-use socialvoid_rawclient::ValidationError;
+use socialvoid::error::{AuthenticationError, ErrorKind, ValidationError};
+use socialvoid::SocialvoidError;
 
 pub struct MyFriendlyError(SocialvoidError);
 
@@ -11,12 +9,6 @@ impl std::convert::From<SocialvoidError> for MyFriendlyError {
     }
 }
 
-impl std::convert::From<socialvoid_rawclient::Error> for MyFriendlyError {
-    fn from(err: socialvoid_rawclient::Error) -> Self {
-        Self(err.into())
-    }
-}
-
 impl std::fmt::Display for MyFriendlyError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.0 {
@@ -51,15 +43,23 @@ socialvoid-cli logout",
                         err.description
                     )
                 }
-                _ => {
-                    write!(f, "{:#?}", self.0)
-                }
-            },
-            SocialvoidError::Client(err) => match err {
-                ClientError::SerdeJson(err) => {
-                    write!(f, "Error while parsing JSON.\n{:?}", err)
-                }
+                _ => write_with_sources(f, &self.0),
             },
+            SocialvoidError::Client(_) => write_with_sources(f, &self.0),
         }
     }
 }
+
+/// Writes the error followed by the chain of errors that caused it
+fn write_with_sources(
+    f: &mut std::fmt::Formatter<'_>,
+    err: &dyn std::error::Error,
+) -> std::fmt::Result {
+    write!(f, "{}", err)?;
+    let mut source = err.source();
+    while let Some(err) = source {
+        write!(f, "\nCaused by: {}", err)?;
+        source = err.source();
+    }
+    Ok(())
+}
@@ -1,6 +1,10 @@
+use futures::StreamExt;
+use socialvoid::error::{AuthenticationError, ErrorKind};
+use socialvoid::notifications::{NotificationWatcher, NotificationWatcherConfig};
 use socialvoid::session::RegisterRequest;
-use socialvoid_rawclient::AuthenticationError;
-use socialvoid_rawclient::ErrorKind;
+use socialvoid::timeline::{FeedWatcherConfig, ThreadLimits};
+use socialvoid::SocialvoidError;
+use socialvoid_types::{DocumentId, PeerRef, PostId};
 use structopt::StructOpt;
 
 mod entities;
@@ -17,7 +21,7 @@ async fn main() {
     let args = Cli::from_args();
     let config = load_config();
 
-    let (sv, mut cached) = init_all(&config).await;
+    let (sv, cached) = init_all(&config).await;
 
     if let Some(cmd) = args.commands {
         match cmd {
@@ -40,8 +44,8 @@ async fn main() {
                     .authenticate_user(username.clone(), password.clone(), None)
                     .await
                 {
-                    Err(err) => match err.kind {
-                        ErrorKind::Authentication(AuthenticationError::SessionExpired) => {
+                    Err(err) => match err.kind() {
+                        Some(ErrorKind::Authentication(AuthenticationError::SessionExpired)) => {
                             println!("Session expired. Creating new session and retrying.");
                             match sv.session.create().await {
                                 Ok(_) => {
@@ -181,24 +185,85 @@ async fn main() {
                     Err(err) => println!("{}", MyFriendlyError::from(err)),
                 }
             }
-            SocialVoidCommand::SetProfile { field, value } => match field {
-                ProfileField::Pic => {
-                    if let Some(filepath) = value {
-                        match sv.account.set_profile_picture(filepath).await {
-                            Ok(doc) => {
-                                println!("Profile picture updated successfully.\n{:?}", doc);
-                            }
-                            Err(err) => {
-                                println!(
-                                    "An error occurred while setting the profile picture.\n{}",
-                                    MyFriendlyError::from(err)
-                                );
-                            }
+            SocialVoidCommand::SetProfile { field } => match field {
+                ProfileField::Pic {
+                    file,
+                    document_id,
+                    remove,
+                } => {
+                    if remove {
+                        match sv.account.delete_profile_picture().await {
+                            Ok(_) => println!("Profile picture removed."),
+                            Err(err) => println!(
+                                "An error occurred while removing the profile picture.\n{}",
+                                MyFriendlyError::from(err)
+                            ),
                         }
                     } else {
-                        println!("You need to specify the path to the picture to upload");
+                        let document_id = match (document_id, file) {
+                            (Some(document_id), _) => Ok(document_id),
+                            (None, Some(filepath)) => sv
+                                .session
+                                .upload_file(&filepath)
+                                .await
+                                .map(|document| document.id),
+                            // structopt requires a file unless there is a document ID
+                            (None, None) => unreachable!(),
+                        };
+                        match document_id {
+                            Ok(document_id) => {
+                                match sv.account.set_profile_picture(document_id).await {
+                                    Ok(sizes) => {
+                                        println!("Profile picture updated successfully.");
+                                        for size in sizes {
+                                            println!(
+                                                "{}x{}\t{} ({})",
+                                                size.width,
+                                                size.height,
+                                                size.document.file_name,
+                                                size.document.id
+                                            );
+                                        }
+                                    }
+                                    Err(err) => {
+                                        println!(
+                                            "An error occurred while setting the profile picture.\n{}",
+                                            MyFriendlyError::from(err)
+                                        );
+                                    }
+                                }
+                            }
+                            Err(err) => println!(
+                                "An error occurred while uploading the profile picture.\n{}",
+                                MyFriendlyError::from(err)
+                            ),
+                        }
                     }
                 }
+                ProfileField::Name {
+                    first_name,
+                    last_name,
+                } => report_profile_update(
+                    sv.account.update_profile_name(first_name, last_name).await,
+                ),
+                ProfileField::Bio { biography, clear } => {
+                    report_profile_update(match biography.filter(|_| !clear) {
+                        Some(biography) => sv.account.update_profile_biography(biography).await,
+                        None => sv.account.clear_profile_biography().await,
+                    })
+                }
+                ProfileField::Location { location, clear } => {
+                    report_profile_update(match location.filter(|_| !clear) {
+                        Some(location) => sv.account.update_profile_location(location).await,
+                        None => sv.account.clear_profile_location().await,
+                    })
+                }
+                ProfileField::Url { url, clear } => {
+                    report_profile_update(match url.filter(|_| !clear) {
+                        Some(url) => sv.account.update_profile_url(url).await,
+                        None => sv.account.clear_profile_url().await,
+                    })
+                }
             },
             SocialVoidCommand::Profile { peer } => match sv.network.get_profile(peer).await {
                 Ok(profile) => println!("{}", SVProfile::from(profile)),
@@ -207,6 +272,24 @@ async fn main() {
                     MyFriendlyError::from(err)
                 ),
             },
+            SocialVoidCommand::ProfilePicture { peer, size, output } => {
+                let output = output.unwrap_or_else(|| format!("profile-picture-{}.jpg", size));
+                match sv
+                    .network
+                    .download_display_picture(peer, size, &output)
+                    .await
+                {
+                    Ok(Some(picture)) => println!(
+                        "Saved the {}x{} profile picture to '{}'",
+                        picture.width, picture.height, output
+                    ),
+                    Ok(None) => println!("No profile picture is set."),
+                    Err(err) => println!(
+                        "An error occurred while downloading the profile picture.\n{}",
+                        MyFriendlyError::from(err)
+                    ),
+                }
+            }
             SocialVoidCommand::Follow { peer } => {
                 match sv.network.follow_peer(peer.clone()).await {
                     Ok(relationship) => {
@@ -263,6 +346,57 @@ async fn main() {
                 Ok(_ok) => println!("Done"),
                 Err(err) => println!("{}", MyFriendlyError::from(err)),
             },
+            SocialVoidCommand::Thread {
+                post_id,
+                depth,
+                max_replies,
+            } => {
+                let limits = ThreadLimits {
+                    max_depth: depth,
+                    max_replies,
+                };
+                match sv.timeline.get_thread_with_limits(post_id, limits).await {
+                    Ok(thread) => print!("{}", SVThread::from(thread)),
+                    Err(err) => println!("{}", MyFriendlyError::from(err)),
+                }
+            }
+            SocialVoidCommand::Notifications { interval } => {
+                let config = NotificationWatcherConfig {
+                    feed: FeedWatcherConfig {
+                        interval: std::time::Duration::from_secs(interval),
+                        state_file: Some(
+                            format!("{}/notifications_feed.json", config.base_path).into(),
+                        ),
+                        ..FeedWatcherConfig::default()
+                    },
+                    state_file: Some(format!("{}/notifications.json", config.base_path).into()),
+                    ..NotificationWatcherConfig::default()
+                };
+                match NotificationWatcher::new(
+                    std::sync::Arc::clone(&sv.network),
+                    std::sync::Arc::clone(&sv.timeline),
+                    config,
+                ) {
+                    Ok(watcher) => {
+                        println!("Waiting for notifications. Press Ctrl-C to stop.");
+                        let notifications = watcher.watch();
+                        tokio::pin!(notifications);
+                        loop {
+                            tokio::select! {
+                                _ = tokio::signal::ctrl_c() => break,
+                                notification = notifications.next() => match notification {
+                                    Some(Ok(notification)) => {
+                                        println!("{}", SVNotification::from(notification))
+                                    }
+                                    Some(Err(err)) => println!("{}", MyFriendlyError::from(err)),
+                                    None => break,
+                                },
+                            }
+                        }
+                    }
+                    Err(err) => println!("{}", MyFriendlyError::from(err)),
+                }
+            }
             SocialVoidCommand::Sync {} => {}
         }
     }
@@ -275,6 +409,16 @@ async fn main() {
     save_config(&config).expect("Couldn't save the config");
 }
 
+fn report_profile_update(result: Result<bool, SocialvoidError>) {
+    match result {
+        Ok(_) => println!("Profile updated."),
+        Err(err) => println!(
+            "An error occurred while updating the profile.\n{}",
+            MyFriendlyError::from(err)
+        ),
+    }
+}
+
 #[derive(Debug, StructOpt)]
 struct Cli {
     #[structopt(subcommand)]
@@ -288,6 +432,7 @@ enum SocialVoidCommand {
     },
     Logout,
     Register,
+    #[allow(dead_code)] // WIP
     Config {
         #[structopt(subcommand)]
         field: ConfigField,
@@ -295,48 +440,111 @@ enum SocialVoidCommand {
     },
     GetMe,
     Followers {
-        peer: Option<String>,
+        peer: Option<PeerRef>,
         page: Option<u32>,
     },
     Following {
-        peer: Option<String>,
+        peer: Option<PeerRef>,
         page: Option<u32>,
     },
     Follow {
-        peer: String,
+        peer: PeerRef,
     },
     Unfollow {
-        peer: String,
+        peer: PeerRef,
     },
     SetProfile {
         #[structopt(subcommand)]
         field: ProfileField,
-        value: Option<String>,
     },
     Profile {
-        peer: Option<String>,
+        peer: Option<PeerRef>,
+    },
+    /// Download the profile picture of a peer
+    ProfilePicture {
+        peer: Option<PeerRef>,
+        /// The preferred width and height of the picture
+        #[structopt(long, default_value = "256")]
+        size: u32,
+        /// The file to save the picture to
+        #[structopt(short, long)]
+        output: Option<String>,
     },
     Feed {
         page: Option<u32>,
     },
     GetPost {
-        post_id: String,
+        post_id: PostId,
     },
     Like {
-        post_id: String,
+        post_id: PostId,
     },
     Unlike {
-        post_id: String,
+        post_id: PostId,
     },
     DeletePost {
-        post_id: String,
+        post_id: PostId,
+    },
+    /// Show the conversation around a post as a tree
+    Thread {
+        post_id: PostId,
+        /// How many levels of replies to show
+        #[structopt(long, default_value = "8")]
+        depth: usize,
+        /// The maximum number of replies to show
+        #[structopt(long, default_value = "200")]
+        max_replies: usize,
+    },
+    /// Show the mentions, replies, quotes, reposts and new followers as they happen
+    Notifications {
+        /// Seconds between two checks
+        #[structopt(long, default_value = "30")]
+        interval: u64,
     },
     Sync {},
 }
 
 #[derive(Debug, StructOpt)]
 enum ProfileField {
-    Pic, //TODO: remove this and make a separate command for profile pic
+    /// Set or remove the profile picture
+    Pic {
+        /// Path to the picture to upload
+        #[structopt(required_unless_one = &["document-id", "remove"])]
+        file: Option<String>,
+        /// ID of an already uploaded document to use instead of uploading a file
+        #[structopt(long, conflicts_with = "file")]
+        document_id: Option<DocumentId>,
+        /// Remove the current profile picture
+        #[structopt(long, conflicts_with_all = &["file", "document-id"])]
+        remove: bool,
+    },
+    /// Set the first and last name
+    Name {
+        first_name: String,
+        /// The last name is removed if it isn't given
+        last_name: Option<String>,
+    },
+    /// Set or clear the biography
+    Bio {
+        #[structopt(required_unless = "clear")]
+        biography: Option<String>,
+        #[structopt(long, conflicts_with = "biography")]
+        clear: bool,
+    },
+    /// Set or clear the location
+    Location {
+        #[structopt(required_unless = "clear")]
+        location: Option<String>,
+        #[structopt(long, conflicts_with = "location")]
+        clear: bool,
+    },
+    /// Set or clear the URL
+    Url {
+        #[structopt(required_unless = "clear")]
+        url: Option<String>,
+        #[structopt(long, conflicts_with = "url")]
+        clear: bool,
+    },
 }
 
 #[derive(Debug, StructOpt)]
@@ -17,7 +17,7 @@ async fn main() {
     let username = creds["username"].as_str().unwrap().to_string();
     let password = creds["password"].as_str().unwrap().to_string();
     sv.session
-        .authenticate_user(username, password, None)
+        .authenticate_user(username.clone(), password, None)
         .await
         .unwrap();
 
@@ -0,0 +1,107 @@
+//! Parameters and result types of the `account` methods
+
+use crate::method::Method;
+use serde::Serialize;
+use socialvoid_types::{DisplayPictureSize, DocumentId};
+
+/// `account.set_profile_picture`: Sets the profile picture to an uploaded document
+#[derive(Serialize, Debug)]
+pub struct SetProfilePicture {
+    /// ID of the document
+    pub document: DocumentId,
+}
+
+impl Method for SetProfilePicture {
+    const NAME: &'static str = "account.set_profile_picture";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Vec<DisplayPictureSize>;
+}
+
+/// `account.delete_profile_picture`: Removes the profile picture
+#[derive(Serialize, Debug)]
+pub struct DeleteProfilePicture;
+
+impl Method for DeleteProfilePicture {
+    const NAME: &'static str = "account.delete_profile_picture";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = bool;
+}
+
+/// `account.update_profile_name`: Updates the first and last name of the profile
+#[derive(Serialize, Debug)]
+pub struct UpdateProfileName {
+    pub first_name: String,
+    pub last_name: Option<String>,
+}
+
+impl Method for UpdateProfileName {
+    const NAME: &'static str = "account.update_profile_name";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = bool;
+}
+
+/// `account.update_profile_biography`: Updates the biography of the profile
+#[derive(Serialize, Debug)]
+pub struct UpdateProfileBiography {
+    pub biography: String,
+}
+
+impl Method for UpdateProfileBiography {
+    const NAME: &'static str = "account.update_profile_biography";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = bool;
+}
+
+/// `account.clear_profile_biography`: Removes the biography of the profile
+#[derive(Serialize, Debug)]
+pub struct ClearProfileBiography;
+
+impl Method for ClearProfileBiography {
+    const NAME: &'static str = "account.clear_profile_biography";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = bool;
+}
+
+/// `account.update_profile_location`: Updates the location of the profile
+#[derive(Serialize, Debug)]
+pub struct UpdateProfileLocation {
+    pub location: String,
+}
+
+impl Method for UpdateProfileLocation {
+    const NAME: &'static str = "account.update_profile_location";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = bool;
+}
+
+/// `account.clear_profile_location`: Removes the location of the profile
+#[derive(Serialize, Debug)]
+pub struct ClearProfileLocation;
+
+impl Method for ClearProfileLocation {
+    const NAME: &'static str = "account.clear_profile_location";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = bool;
+}
+
+/// `account.update_profile_url`: Updates the URL of the profile
+#[derive(Serialize, Debug)]
+pub struct UpdateProfileUrl {
+    pub url: String,
+}
+
+impl Method for UpdateProfileUrl {
+    const NAME: &'static str = "account.update_profile_url";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = bool;
+}
+
+/// `account.clear_profile_url`: Removes the URL of the profile
+#[derive(Serialize, Debug)]
+pub struct ClearProfileUrl;
+
+impl Method for ClearProfileUrl {
+    const NAME: &'static str = "account.clear_profile_url";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = bool;
+}
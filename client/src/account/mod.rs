@@ -1,28 +1,184 @@
+pub mod methods;
+#[cfg(feature = "image")]
+mod picture;
+
+#[cfg(feature = "image")]
+pub use picture::{prepare_profile_picture, PROFILE_PICTURE_SIZE};
+
+use crate::error::SocialvoidError;
 use crate::SVSessionMethods;
-use serde_json::json;
-use socialvoid_rawclient::Error;
+use methods::*;
+use socialvoid_types::DisplayPictureSize;
+use socialvoid_types::DocumentId;
 use std::sync::Arc;
 
 pub struct SVAccountMethods {
-    client: Arc<socialvoid_rawclient::Client>,
     session: Arc<SVSessionMethods>,
 }
 
 impl SVAccountMethods {
-    pub fn new(client: Arc<socialvoid_rawclient::Client>, session: Arc<SVSessionMethods>) -> Self {
-        Self { client, session }
-    }
-
-    pub async fn set_profile_picture(&self, document_id: String) -> Result<bool, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "account.set_profile_picture",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "document": document_id,
-                }),
-            )
+    pub fn new(session: Arc<SVSessionMethods>) -> Self {
+        Self { session }
+    }
+
+    /// Sets the profile picture to a document already uploaded to the CDN.
+    /// Returns the sizes of the new display picture.
+    pub async fn set_profile_picture(
+        &self,
+        document_id: DocumentId,
+    ) -> Result<Vec<DisplayPictureSize>, SocialvoidError> {
+        self.session
+            .invoke(SetProfilePicture {
+                document: document_id,
+            })
+            .await
+    }
+
+    /// Removes the profile picture
+    pub async fn delete_profile_picture(&self) -> Result<bool, SocialvoidError> {
+        self.session.invoke(DeleteProfilePicture).await
+    }
+
+    /// Updates the first and last name of the profile.
+    /// The last name is removed if `last_name` is `None`.
+    pub async fn update_profile_name(
+        &self,
+        first_name: String,
+        last_name: Option<String>,
+    ) -> Result<bool, SocialvoidError> {
+        self.session
+            .invoke(UpdateProfileName {
+                first_name,
+                last_name,
+            })
+            .await
+    }
+
+    /// Updates the biography of the profile
+    pub async fn update_profile_biography(
+        &self,
+        biography: String,
+    ) -> Result<bool, SocialvoidError> {
+        self.session
+            .invoke(UpdateProfileBiography { biography })
+            .await
+    }
+
+    /// Removes the biography of the profile
+    pub async fn clear_profile_biography(&self) -> Result<bool, SocialvoidError> {
+        self.session.invoke(ClearProfileBiography).await
+    }
+
+    /// Updates the location of the profile
+    pub async fn update_profile_location(&self, location: String) -> Result<bool, SocialvoidError> {
+        self.session
+            .invoke(UpdateProfileLocation { location })
             .await
     }
+
+    /// Removes the location of the profile
+    pub async fn clear_profile_location(&self) -> Result<bool, SocialvoidError> {
+        self.session.invoke(ClearProfileLocation).await
+    }
+
+    /// Updates the URL of the profile
+    pub async fn update_profile_url(&self, url: String) -> Result<bool, SocialvoidError> {
+        self.session.invoke(UpdateProfileUrl { url }).await
+    }
+
+    /// Removes the URL of the profile
+    pub async fn clear_profile_url(&self) -> Result<bool, SocialvoidError> {
+        self.session.invoke(ClearProfileUrl).await
+    }
+
+    /// Crops the image at `file_path` to a square, resizes it to `PROFILE_PICTURE_SIZE`, uploads it
+    /// to the CDN and sets it as the profile picture.
+    #[cfg(feature = "image")]
+    pub async fn set_profile_picture_from_file(
+        &self,
+        file_path: &str,
+    ) -> Result<Vec<DisplayPictureSize>, SocialvoidError> {
+        use rand::distributions::Alphanumeric;
+        use rand::{thread_rng, Rng};
+
+        // decoding and resizing would block the other tasks of the runtime
+        let path = file_path.to_string();
+        let picture = tokio::task::spawn_blocking(move || {
+            prepare_profile_picture(&path, PROFILE_PICTURE_SIZE)
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+        let suffix: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(10)
+            .map(char::from)
+            .collect();
+        let upload_path = std::env::temp_dir().join(format!("profile-picture-{}.jpg", suffix));
+        let upload_path = upload_path.to_string_lossy().to_string();
+        tokio::fs::write(&upload_path, picture).await?;
+        let document = self.session.upload_file(&upload_path).await;
+        let _ = tokio::fs::remove_file(&upload_path).await;
+
+        self.set_profile_picture(document?.id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::SocialvoidError;
+    use crate::mock::MockServer;
+
+    #[tokio::test]
+    async fn it_should_update_and_clear_the_profile() -> Result<(), SocialvoidError> {
+        let server = MockServer::start().await?;
+        server.add_user("light", "SuperStrongPassword", "Light");
+        let sv = server.client().await?;
+        sv.session.create().await?;
+        sv.session
+            .authenticate_user("light".to_string(), "SuperStrongPassword".to_string(), None)
+            .await?;
+
+        assert!(
+            sv.account
+                .update_profile_name("Light".to_string(), Some("Yagami".to_string()))
+                .await?
+        );
+        assert!(
+            sv.account
+                .update_profile_biography("Justice will prevail".to_string())
+                .await?
+        );
+        assert!(
+            sv.account
+                .update_profile_location("Tokyo".to_string())
+                .await?
+        );
+        assert!(
+            sv.account
+                .update_profile_url("https://example.com".to_string())
+                .await?
+        );
+        let profile = sv.network.get_profile(None).await?;
+        assert_eq!(profile.name, "Light Yagami");
+        assert_eq!(profile.last_name.as_deref(), Some("Yagami"));
+        assert_eq!(profile.biography.as_deref(), Some("Justice will prevail"));
+        assert_eq!(profile.location.as_deref(), Some("Tokyo"));
+        assert_eq!(profile.url.as_deref(), Some("https://example.com"));
+
+        assert!(
+            sv.account
+                .update_profile_name("Kira".to_string(), None)
+                .await?
+        );
+        assert!(sv.account.clear_profile_biography().await?);
+        assert!(sv.account.clear_profile_location().await?);
+        assert!(sv.account.clear_profile_url().await?);
+        let profile = sv.network.get_profile(None).await?;
+        assert_eq!(profile.name, "Kira");
+        assert_eq!(profile.last_name, None);
+        assert_eq!(profile.biography, None);
+        assert_eq!(profile.location, None);
+        assert_eq!(profile.url, None);
+        Ok(())
+    }
 }
@@ -0,0 +1,71 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageError, ImageOutputFormat};
+use std::io::Cursor;
+
+/// Width and height of the pictures produced by `set_profile_picture_from_file`
+pub const PROFILE_PICTURE_SIZE: u32 = 640;
+
+const JPEG_QUALITY: u8 = 90;
+
+/// Decodes the image at `file_path`, crops it to a centered square, resizes it to `size`x`size`
+/// (smaller images are not scaled up) and re-encodes it as a JPEG suitable as a profile picture.
+pub fn prepare_profile_picture(file_path: &str, size: u32) -> Result<Vec<u8>, std::io::Error> {
+    let image = image::io::Reader::open(file_path)?
+        .with_guessed_format()?
+        .decode()
+        .map_err(into_io_error)?;
+    encode_profile_picture(&image, size)
+}
+
+fn encode_profile_picture(image: &DynamicImage, size: u32) -> Result<Vec<u8>, std::io::Error> {
+    let side = image.width().min(image.height());
+    let x = (image.width() - side) / 2;
+    let y = (image.height() - side) / 2;
+    let mut square = image.crop_imm(x, y, side, side);
+    if side > size {
+        square = square.resize_exact(size, size, FilterType::Lanczos3);
+    }
+
+    let mut bytes = Cursor::new(vec![]);
+    DynamicImage::ImageRgb8(square.to_rgb8())
+        .write_to(&mut bytes, ImageOutputFormat::Jpeg(JPEG_QUALITY))
+        .map_err(into_io_error)?;
+    Ok(bytes.into_inner())
+}
+
+fn into_io_error(error: ImageError) -> std::io::Error {
+    match error {
+        ImageError::IoError(error) => error,
+        error => std::io::Error::new(std::io::ErrorKind::InvalidData, error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, RgbaImage};
+
+    #[test]
+    fn it_should_crop_and_resize_to_a_square_jpeg() -> Result<(), std::io::Error> {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(1200, 800));
+        let bytes = encode_profile_picture(&image, 640)?;
+
+        let decoded = image::load_from_memory(&bytes).map_err(into_io_error)?;
+        assert_eq!(
+            image::guess_format(&bytes).map_err(into_io_error)?,
+            image::ImageFormat::Jpeg
+        );
+        assert_eq!(decoded.dimensions(), (640, 640));
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_not_upscale_small_images() -> Result<(), std::io::Error> {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(100, 300));
+        let bytes = encode_profile_picture(&image, 640)?;
+
+        let decoded = image::load_from_memory(&bytes).map_err(into_io_error)?;
+        assert_eq!(decoded.dimensions(), (100, 100));
+        Ok(())
+    }
+}
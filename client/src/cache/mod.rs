@@ -0,0 +1,320 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const INDEX_FILE: &str = "index.json";
+
+/// An on-disk cache for documents downloaded from the CDN.
+/// Contents are stored by their SHA-256 hash and looked up by document ID. When the total size of
+/// the cache goes above `max_size` bytes, the least recently used documents are evicted.
+/// Every read is verified against the stored hash and corrupt entries are dropped.
+/// The recency of the reads is saved with the next change, by `flush` or when the cache is
+/// dropped.
+pub struct DocumentCache {
+    dir: PathBuf,
+    max_size: u64,
+    index: Mutex<CacheIndex>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct CacheIndex {
+    /// Incremented on every access and used to order the entries by recency
+    clock: u64,
+    entries: HashMap<String, CacheEntry>,
+    /// Whether the index changed since it was saved
+    #[serde(skip)]
+    dirty: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    hash: String,
+    size: u64,
+    last_used: u64,
+}
+
+impl DocumentCache {
+    /// Opens (or creates) a cache in the directory `dir` that holds at most `max_size` bytes
+    pub fn open<P: AsRef<Path>>(dir: P, max_size: u64) -> Result<DocumentCache, io::Error> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        let index = match std::fs::read(dir.join(INDEX_FILE)) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+            Err(_) => CacheIndex::default(),
+        };
+        let cache = DocumentCache {
+            dir,
+            max_size,
+            index: Mutex::new(index),
+        };
+        cache.evict(&mut cache.index.lock().unwrap())?;
+        Ok(cache)
+    }
+
+    /// Returns the contents of the document if it is cached and intact
+    pub fn get(&self, document_id: &str) -> Option<Vec<u8>> {
+        let mut index = self.index.lock().unwrap();
+        let (hash, size) = index
+            .entries
+            .get(document_id)
+            .map(|entry| (entry.hash.clone(), entry.size))?;
+        let intact = match std::fs::read(self.dir.join(&hash)) {
+            Ok(bytes) if bytes.len() as u64 == size && sha256::digest_bytes(&bytes) == hash => {
+                Some(bytes)
+            }
+            _ => None,
+        };
+        match intact {
+            Some(bytes) => {
+                index.clock += 1;
+                let clock = index.clock;
+                if let Some(entry) = index.entries.get_mut(document_id) {
+                    entry.last_used = clock;
+                }
+                index.dirty = true;
+                Some(bytes)
+            }
+            None => {
+                index.entries.remove(document_id);
+                self.remove_unreferenced_blob(&index, &hash);
+                let _ = self.save_index(&mut index);
+                None
+            }
+        }
+    }
+
+    /// Adds a document to the cache, evicting the least recently used documents if needed.
+    /// Documents larger than the cache itself aren't stored.
+    pub fn put(&self, document_id: &str, content: &[u8]) -> Result<(), io::Error> {
+        let size = content.len() as u64;
+        if size > self.max_size {
+            return Ok(());
+        }
+        let hash = sha256::digest_bytes(content);
+        let blob_path = self.dir.join(&hash);
+        // locked until the entry is indexed, so the blob isn't evicted in between
+        let mut index = self.index.lock().unwrap();
+        if !blob_path.exists() {
+            let tmp_path = self.dir.join(format!("{}.tmp", hash));
+            std::fs::write(&tmp_path, content)?;
+            std::fs::rename(&tmp_path, &blob_path)?;
+        }
+        index.clock += 1;
+        let entry = CacheEntry {
+            hash,
+            size,
+            last_used: index.clock,
+        };
+        if let Some(old) = index.entries.insert(document_id.to_string(), entry) {
+            self.remove_unreferenced_blob(&index, &old.hash);
+        }
+        self.evict(&mut index)
+    }
+
+    /// Removes a document from the cache
+    pub fn remove(&self, document_id: &str) -> Result<(), io::Error> {
+        let mut index = self.index.lock().unwrap();
+        if let Some(entry) = index.entries.remove(document_id) {
+            self.remove_unreferenced_blob(&index, &entry.hash);
+        }
+        self.save_index(&mut index)
+    }
+
+    /// Removes every document from the cache
+    pub fn clear(&self) -> Result<(), io::Error> {
+        let mut index = self.index.lock().unwrap();
+        for (_, entry) in index.entries.drain() {
+            let _ = std::fs::remove_file(self.dir.join(entry.hash));
+        }
+        self.save_index(&mut index)
+    }
+
+    /// Saves the recency of the reads since the last change
+    pub fn flush(&self) -> Result<(), io::Error> {
+        let mut index = self.index.lock().unwrap();
+        if index.dirty {
+            self.save_index(&mut index)?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if the document is in the cache (without verifying its contents)
+    pub fn contains(&self, document_id: &str) -> bool {
+        self.index.lock().unwrap().entries.contains_key(document_id)
+    }
+
+    /// The total size in bytes of the documents stored in the cache
+    pub fn size(&self) -> u64 {
+        total_size(&self.index.lock().unwrap())
+    }
+
+    /// Evicts the least recently used documents until the cache fits in `max_size`
+    fn evict(&self, index: &mut CacheIndex) -> Result<(), io::Error> {
+        while total_size(index) > self.max_size {
+            let oldest = index
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(id, _)| id.clone());
+            match oldest.and_then(|id| index.entries.remove(&id)) {
+                Some(entry) => self.remove_unreferenced_blob(index, &entry.hash),
+                None => break,
+            }
+        }
+        self.save_index(index)
+    }
+
+    /// Deletes the blob with the given hash if no entry refers to it anymore
+    fn remove_unreferenced_blob(&self, index: &CacheIndex, hash: &str) {
+        if !index.entries.values().any(|entry| entry.hash == hash) {
+            let _ = std::fs::remove_file(self.dir.join(hash));
+        }
+    }
+
+    fn save_index(&self, index: &mut CacheIndex) -> Result<(), io::Error> {
+        std::fs::write(self.dir.join(INDEX_FILE), serde_json::to_vec(index)?)?;
+        index.dirty = false;
+        Ok(())
+    }
+}
+
+impl Drop for DocumentCache {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Size of the unique blobs referenced by the index
+fn total_size(index: &CacheIndex) -> u64 {
+    let mut sizes = HashMap::new();
+    for entry in index.entries.values() {
+        sizes.insert(&entry.hash, entry.size);
+    }
+    sizes.values().sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "socialvoid-cache-{}-{}.test",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn it_should_return_cached_documents() -> Result<(), io::Error> {
+        let dir = cache_dir("get");
+        let cache = DocumentCache::open(&dir, 1024)?;
+        cache.put("doc1", b"hello")?;
+        assert_eq!(cache.get("doc1"), Some(b"hello".to_vec()));
+        assert_eq!(cache.get("doc2"), None);
+
+        // the index survives reopening the cache
+        let cache = DocumentCache::open(&dir, 1024)?;
+        assert_eq!(cache.get("doc1"), Some(b"hello".to_vec()));
+        std::fs::remove_dir_all(dir)
+    }
+
+    #[test]
+    fn it_should_evict_the_least_recently_used_documents() -> Result<(), io::Error> {
+        let dir = cache_dir("evict");
+        let cache = DocumentCache::open(&dir, 10)?;
+        cache.put("doc1", b"aaaa")?;
+        cache.put("doc2", b"bbbb")?;
+        assert!(cache.get("doc1").is_some());
+        cache.put("doc3", b"cccc")?;
+
+        assert!(cache.contains("doc1"));
+        assert!(!cache.contains("doc2"));
+        assert!(cache.contains("doc3"));
+        assert_eq!(cache.size(), 8);
+
+        cache.put("too-large", &[0; 11])?;
+        assert!(!cache.contains("too-large"));
+        std::fs::remove_dir_all(dir)
+    }
+
+    #[test]
+    fn it_should_store_identical_contents_once() -> Result<(), io::Error> {
+        let dir = cache_dir("dedup");
+        let cache = DocumentCache::open(&dir, 10)?;
+        cache.put("doc1", b"same")?;
+        cache.put("doc2", b"same")?;
+        assert_eq!(cache.size(), 4);
+
+        cache.remove("doc1")?;
+        assert_eq!(cache.get("doc2"), Some(b"same".to_vec()));
+        std::fs::remove_dir_all(dir)
+    }
+
+    #[test]
+    fn it_should_save_the_recency_of_the_reads_lazily() -> Result<(), io::Error> {
+        let dir = cache_dir("lazy");
+        let cache = DocumentCache::open(&dir, 10)?;
+        cache.put("doc1", b"aaaa")?;
+        cache.put("doc2", b"bbbb")?;
+        let saved = std::fs::read(dir.join(INDEX_FILE))?;
+        assert!(cache.get("doc1").is_some());
+        assert_eq!(std::fs::read(dir.join(INDEX_FILE))?, saved);
+
+        // saved when the cache is dropped
+        drop(cache);
+        let cache = DocumentCache::open(&dir, 10)?;
+        cache.put("doc3", b"cccc")?;
+        assert!(cache.contains("doc1"));
+        assert!(!cache.contains("doc2"));
+        std::fs::remove_dir_all(dir)
+    }
+
+    #[test]
+    fn it_should_keep_the_blobs_of_the_documents_put_concurrently() -> Result<(), io::Error> {
+        let dir = cache_dir("concurrent");
+        // one blob fits, so every put evicts the blob another thread may be putting
+        let cache = std::sync::Arc::new(DocumentCache::open(&dir, 4)?);
+        let threads: Vec<_> = (0..8)
+            .map(|thread| {
+                let cache = std::sync::Arc::clone(&cache);
+                std::thread::spawn(move || {
+                    let mut missing = 0;
+                    for i in 0..200 {
+                        let id = format!("doc{}-{}", thread, i);
+                        cache.put(&id, &[(i % 2) as u8; 4]).unwrap();
+                        let index = cache.index.lock().unwrap();
+                        if let Some(entry) = index.entries.get(&id) {
+                            if !cache.dir.join(&entry.hash).exists() {
+                                missing += 1;
+                            }
+                        }
+                    }
+                    missing
+                })
+            })
+            .collect();
+        let missing: usize = threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .sum();
+        assert_eq!(missing, 0, "indexed documents without a blob");
+        std::fs::remove_dir_all(dir)
+    }
+
+    #[test]
+    fn it_should_drop_corrupt_documents() -> Result<(), io::Error> {
+        let dir = cache_dir("corrupt");
+        let cache = DocumentCache::open(&dir, 1024)?;
+        cache.put("doc1", b"hello")?;
+        std::fs::write(dir.join(sha256::digest_bytes(b"hello")), b"jello")?;
+
+        assert_eq!(cache.get("doc1"), None);
+        assert!(!cache.contains("doc1"));
+        std::fs::remove_dir_all(dir)
+    }
+}
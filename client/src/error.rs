@@ -1,3 +1,14 @@
+pub use socialvoid_rawclient::AuthenticationError;
+pub use socialvoid_rawclient::ErrorKind;
+pub use socialvoid_rawclient::ErrorNamespace;
+pub use socialvoid_rawclient::NetworkError;
+pub use socialvoid_rawclient::RpcErrorCode;
+pub use socialvoid_rawclient::ServerError;
+pub use socialvoid_rawclient::ValidationError;
+
+/// The error returned by every method of the client.
+/// Errors either come from the server/transport (`RawClient`) or are raised by the client itself
+/// (`Client`).
 #[derive(Debug)]
 pub enum SocialvoidError {
     RawClient(socialvoid_rawclient::Error),
@@ -9,6 +20,110 @@ pub enum SocialvoidError {
 pub enum ClientError {
     /// Errors thrown by serde json
     SerdeJson(serde_json::Error),
+    /// The terms of service need to be accepted using `session.accept_terms_of_service` before registering
+    TermsOfServiceNotAgreed,
+    /// `session.create` needs to be called before making requests that need a session
+    SessionNotEstablished,
+    /// The session needs to be authenticated (e.g. with `session.authenticate_user`) before
+    /// calling the method
+    NotAuthenticated,
+    /// A paginated method was called with a cursor but the server answered with a bare list, so
+    /// it doesn't paginate with cursors. The methods taking a `page` number can be used instead.
+    CursorsNotSupported,
+}
+
+impl SocialvoidError {
+    /// Returns the kind of the error if it came from the server or the transport
+    pub fn kind(&self) -> Option<&ErrorKind> {
+        match self {
+            SocialvoidError::RawClient(err) => Some(&err.kind),
+            SocialvoidError::Client(_) => None,
+        }
+    }
+
+    /// True if the request failed because the session isn't (or is no longer) authenticated or
+    /// doesn't exist. Logging in again (or creating a new session) may help.
+    pub fn is_auth_error(&self) -> bool {
+        match self {
+            SocialvoidError::RawClient(err) => matches!(
+                err.kind,
+                ErrorKind::Authentication(_)
+                    | ErrorKind::Unrecognized {
+                        namespace: ErrorNamespace::Authentication,
+                        ..
+                    }
+            ),
+            SocialvoidError::Client(err) => matches!(
+                err,
+                ClientError::SessionNotEstablished | ClientError::NotAuthenticated
+            ),
+        }
+    }
+
+    /// True if the peer, post or document the request refers to doesn't exist (anymore)
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self.kind(),
+            Some(ErrorKind::Network(
+                NetworkError::PeerNotFound
+                    | NetworkError::PostNotFound
+                    | NetworkError::PostDeleted
+                    | NetworkError::DocumentNotFound
+            ))
+        )
+    }
+
+    /// True if the error is likely temporary and the same request may succeed if sent again
+    pub fn is_retryable(&self) -> bool {
+        match self.kind() {
+            Some(ErrorKind::RequestError(err)) => {
+                err.is_timeout()
+                    || err.is_connect()
+                    || err.status().is_some_and(|status| status.is_server_error())
+            }
+            Some(ErrorKind::IO(err)) => matches!(
+                err.kind(),
+                std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+            ),
+            Some(ErrorKind::Server(ServerError::InternalServerError)) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the name of the parameter that failed validation, if this is a validation error
+    pub fn validation_field(&self) -> Option<&'static str> {
+        let err = match self.kind() {
+            Some(ErrorKind::Validation(err)) => err,
+            _ => return None,
+        };
+        let field = match err {
+            ValidationError::InvalidUsername | ValidationError::UsernameAlreadyExists => "username",
+            ValidationError::InvalidPassword => "password",
+            ValidationError::InvalidFirstName => "first_name",
+            ValidationError::InvalidLastName => "last_name",
+            ValidationError::InvalidBiography => "biography",
+            ValidationError::InvalidPeerInput => "peer",
+            ValidationError::InvalidPostText => "text",
+            ValidationError::InvalidClientPublicHash => "public_hash",
+            ValidationError::InvalidClientPrivateHash => "private_hash",
+            ValidationError::InvalidPlatform => "platform",
+            ValidationError::InvalidVersion => "version",
+            ValidationError::InvalidClientName => "name",
+            ValidationError::InvalidSessionIdentification => "session_identification",
+            ValidationError::InvalidFileForProfilePicture | ValidationError::FileTooLarge => {
+                "document"
+            }
+            ValidationError::InvalidHelpDocumentId => "id",
+            ValidationError::AgreementRequired => "terms_of_service_agree",
+            ValidationError::InvalidCursorValue => "cursor",
+            ValidationError::InvalidGeoLocation => "location",
+            ValidationError::InvalidUrlValue => "url",
+        };
+        Some(field)
+    }
 }
 
 impl From<socialvoid_rawclient::Error> for SocialvoidError {
@@ -22,3 +137,91 @@ impl From<serde_json::Error> for SocialvoidError {
         SocialvoidError::Client(ClientError::SerdeJson(err))
     }
 }
+
+impl From<std::io::Error> for SocialvoidError {
+    fn from(err: std::io::Error) -> Self {
+        SocialvoidError::RawClient(err.into())
+    }
+}
+
+impl From<ClientError> for SocialvoidError {
+    fn from(err: ClientError) -> Self {
+        SocialvoidError::Client(err)
+    }
+}
+
+impl std::fmt::Display for SocialvoidError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SocialvoidError::RawClient(err) => write!(f, "{}", err),
+            SocialvoidError::Client(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for SocialvoidError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SocialvoidError::RawClient(err) => err.source(),
+            SocialvoidError::Client(err) => err.source(),
+        }
+    }
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::SerdeJson(_) => write!(f, "couldn't parse the JSON"),
+            ClientError::TermsOfServiceNotAgreed => write!(
+                f,
+                "the terms of service must be accepted before registering"
+            ),
+            ClientError::SessionNotEstablished => write!(f, "no session has been established"),
+            ClientError::NotAuthenticated => write!(f, "the session isn't authenticated"),
+            ClientError::CursorsNotSupported => {
+                write!(f, "the server doesn't paginate with cursors")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::SerdeJson(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server_error(code: i32) -> SocialvoidError {
+        socialvoid_rawclient::Error {
+            kind: ErrorKind::from(code),
+            code,
+            description: String::new(),
+            data: None,
+        }
+        .into()
+    }
+
+    #[test]
+    fn it_should_classify_errors() {
+        assert!(server_error(8708).is_auth_error());
+        assert!(server_error(8900).is_auth_error());
+        assert!(SocialvoidError::from(ClientError::SessionNotEstablished).is_auth_error());
+        assert!(!server_error(8448).is_auth_error());
+
+        assert!(server_error(12544).is_not_found());
+        assert!(!server_error(12550).is_not_found());
+
+        assert!(server_error(16384).is_retryable());
+        assert!(!server_error(8448).is_retryable());
+
+        assert_eq!(server_error(8450).validation_field(), Some("first_name"));
+        assert_eq!(server_error(12544).validation_field(), None);
+    }
+}
@@ -0,0 +1,45 @@
+//! Parameters and result types of the `help` methods
+
+use crate::method::Method;
+use serde::Serialize;
+use socialvoid_types::{HelpDocument, ServerInformation};
+
+/// `help.get_community_guidelines`: Returns the community guidelines
+#[derive(Serialize, Debug)]
+pub struct GetCommunityGuidelines;
+
+impl Method for GetCommunityGuidelines {
+    const NAME: &'static str = "help.get_community_guidelines";
+    const REQUIRES_SESSION: bool = false;
+    type Response = HelpDocument;
+}
+
+/// `help.get_privacy_policy`: Returns the privacy policy
+#[derive(Serialize, Debug)]
+pub struct GetPrivacyPolicy;
+
+impl Method for GetPrivacyPolicy {
+    const NAME: &'static str = "help.get_privacy_policy";
+    const REQUIRES_SESSION: bool = false;
+    type Response = HelpDocument;
+}
+
+/// `help.get_server_information`: Returns information about the server
+#[derive(Serialize, Debug)]
+pub struct GetServerInformation;
+
+impl Method for GetServerInformation {
+    const NAME: &'static str = "help.get_server_information";
+    const REQUIRES_SESSION: bool = false;
+    type Response = ServerInformation;
+}
+
+/// `help.get_terms_of_service`: Returns the terms of service
+#[derive(Serialize, Debug)]
+pub struct GetTermsOfService;
+
+impl Method for GetTermsOfService {
+    const NAME: &'static str = "help.get_terms_of_service";
+    const REQUIRES_SESSION: bool = false;
+    type Response = HelpDocument;
+}
@@ -1,5 +1,8 @@
-use serde_json::json;
-use socialvoid_rawclient::Error;
+pub mod methods;
+
+use crate::error::SocialvoidError;
+use crate::method::{self, Method};
+use methods::*;
 use socialvoid_types::{HelpDocument, ServerInformation};
 use std::sync::Arc;
 
@@ -12,57 +15,59 @@ impl SVHelpMethods {
         SVHelpMethods { client }
     }
 
-    pub async fn get_community_guidelines(&self) -> Result<HelpDocument, Error> {
-        self.client
-            .send_request("help.get_community_guidelines", json!(null))
-            .await
+    /// Calls a method that doesn't need a session
+    async fn invoke<M: Method>(&self, method: M) -> Result<M::Response, SocialvoidError> {
+        method::send(&self.client, None, &method).await
+    }
+
+    pub async fn get_community_guidelines(&self) -> Result<HelpDocument, SocialvoidError> {
+        self.invoke(GetCommunityGuidelines).await
     }
 
-    pub async fn get_privacy_policy(&self) -> Result<HelpDocument, Error> {
-        self.client
-            .send_request("help.get_privacy_policy", json!(null))
-            .await
+    pub async fn get_privacy_policy(&self) -> Result<HelpDocument, SocialvoidError> {
+        self.invoke(GetPrivacyPolicy).await
     }
 
-    pub async fn get_server_information(&self) -> Result<ServerInformation, Error> {
-        self.client
-            .send_request("help.get_server_information", json!(null))
-            .await
+    pub async fn get_server_information(&self) -> Result<ServerInformation, SocialvoidError> {
+        self.invoke(GetServerInformation).await
     }
 
-    pub async fn get_terms_of_service(&self) -> Result<HelpDocument, Error> {
-        self.client
-            .send_request("help.get_terms_of_service", json!(null))
-            .await
+    pub async fn get_terms_of_service(&self) -> Result<HelpDocument, SocialvoidError> {
+        self.invoke(GetTermsOfService).await
     }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::MockServer;
     #[tokio::test]
     pub async fn save_all_documents() {
         use std::fs::File;
-        let client = socialvoid_rawclient::new();
+        let server = MockServer::start().await.unwrap();
+        let client = socialvoid_rawclient::with_host(server.url());
         let help = SVHelpMethods::new(Arc::new(client));
+        let dir = std::env::temp_dir().join(format!("socialvoid-help-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
         serde_json::to_writer(
-            &File::create("community_guidelines.json.test").unwrap(),
+            &File::create(dir.join("community_guidelines.json.test")).unwrap(),
             &help.get_community_guidelines().await.unwrap(),
         )
         .unwrap();
         serde_json::to_writer(
-            &File::create("privacy_policy.json.test").unwrap(),
+            &File::create(dir.join("privacy_policy.json.test")).unwrap(),
             &help.get_privacy_policy().await.unwrap(),
         )
         .unwrap();
         serde_json::to_writer(
-            &File::create("server_information.json.test").unwrap(),
+            &File::create(dir.join("server_information.json.test")).unwrap(),
             &help.get_server_information().await.unwrap(),
         )
         .unwrap();
         serde_json::to_writer(
-            &File::create("terms_of_service.json.test").unwrap(),
+            &File::create(dir.join("terms_of_service.json.test")).unwrap(),
             &help.get_terms_of_service().await.unwrap(),
         )
         .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -1,14 +1,24 @@
 pub mod account;
+pub mod cache;
 pub mod error;
 pub mod help;
+pub mod method;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod network;
+pub mod notifications;
+mod pagination;
 pub mod session;
 pub mod timeline;
 
+pub use socialvoid_rawclient::environment;
+
 use account::SVAccountMethods;
+use environment::Environment;
 pub use error::ClientError;
 pub use error::SocialvoidError;
 use help::SVHelpMethods;
+use method::Method;
 use network::SVNetworkMethods;
 use session::ClientInfo;
 use session::SVSessionMethods;
@@ -36,6 +46,7 @@ pub async fn new_with_defaults() -> Result<Client, SocialvoidError> {
         Arc::clone(&rpc_client),
         Arc::clone(&cdn_client),
         Arc::clone(&session_holder),
+        &Environment::default(),
     );
 
     session.create().await?;
@@ -77,6 +88,7 @@ pub fn new_with_host(rpc_url: Option<String>) -> Client {
         Arc::clone(&rpc_client),
         Arc::clone(&cdn_client),
         Arc::clone(&session_holder),
+        &Environment::default(),
     );
     Client {
         session,
@@ -95,12 +107,24 @@ pub async fn new(
     session: SessionHolder,
     rpc_url: Option<String>,
     cdn_url: Option<String>,
+) -> Result<Client, SocialvoidError> {
+    new_with_environment(session, rpc_url, cdn_url, Environment::default()).await
+}
+
+/// Like `new`, with the clock and the random number generator of `environment`.
+/// Use `ClientInfo::generate_with` to generate the client info of the session from it too.
+pub async fn new_with_environment(
+    session: SessionHolder,
+    rpc_url: Option<String>,
+    cdn_url: Option<String>,
+    environment: Environment,
 ) -> Result<Client, SocialvoidError> {
     let rpc_client = if let Some(rpc_url) = rpc_url {
-        Arc::new(socialvoid_rawclient::with_host(&rpc_url))
+        socialvoid_rawclient::with_host(&rpc_url)
     } else {
-        Arc::new(socialvoid_rawclient::new())
+        socialvoid_rawclient::new()
     };
+    let rpc_client = Arc::new(rpc_client.with_id_rng(Box::new(environment.fork_rng())));
     let cdn_client = if let Some(cdn_url) = cdn_url {
         Arc::new(socialvoid_rawclient::CdnClient::with_cdn_url(cdn_url))
     } else {
@@ -111,6 +135,7 @@ pub async fn new(
         Arc::clone(&rpc_client),
         Arc::clone(&cdn_client),
         Arc::clone(&session_holder),
+        &environment,
     );
     Ok(Client {
         session,
@@ -132,6 +157,7 @@ pub fn new_empty_client() -> Client {
         Arc::clone(&rpc_client),
         Arc::new(socialvoid_rawclient::CdnClient::new()),
         Arc::clone(&session_holder),
+        &Environment::default(),
     );
     Client {
         cdn_client: Arc::new(socialvoid_rawclient::CdnClient::new()),
@@ -143,41 +169,43 @@ pub fn new_empty_client() -> Client {
     }
 }
 
-pub fn init_methods(
-    client: Arc<socialvoid_rawclient::Client>,
-    cdn_client: Arc<socialvoid_rawclient::CdnClient>,
-    session_holder: Arc<Mutex<SessionHolder>>,
-) -> (
+/// The method namespaces as returned by `init_methods`
+pub type Methods = (
     Arc<SVSessionMethods>,
     Arc<SVNetworkMethods>,
     Arc<SVAccountMethods>,
     Arc<SVTimelineMethods>,
     Arc<SVHelpMethods>,
-) {
-    let session = Arc::new(SVSessionMethods::new(
+);
+
+pub fn init_methods(
+    client: Arc<socialvoid_rawclient::Client>,
+    cdn_client: Arc<socialvoid_rawclient::CdnClient>,
+    session_holder: Arc<Mutex<SessionHolder>>,
+    environment: &Environment,
+) -> Methods {
+    let session = Arc::new(SVSessionMethods::with_clock(
         Arc::clone(&client),
         Arc::clone(&cdn_client),
         Arc::clone(&session_holder),
+        Arc::clone(&environment.clock),
     ));
     (
         Arc::clone(&session),
-        Arc::new(SVNetworkMethods::new(
-            Arc::clone(&client),
-            Arc::clone(&session),
-        )),
-        Arc::new(SVAccountMethods::new(
-            Arc::clone(&client),
-            Arc::clone(&session),
-        )),
-        Arc::new(SVTimelineMethods::new(
-            Arc::clone(&client),
-            Arc::clone(&session),
-        )),
+        Arc::new(SVNetworkMethods::new(Arc::clone(&session))),
+        Arc::new(SVAccountMethods::new(Arc::clone(&session))),
+        Arc::new(SVTimelineMethods::new(Arc::clone(&session))),
         Arc::new(SVHelpMethods::new(Arc::clone(&client))),
     )
 }
 
 impl Client {
+    /// Calls any method of the API, e.g. one that isn't wrapped by the namespaces yet.
+    /// The session identification is added if the method needs one.
+    pub async fn invoke<M: Method>(&self, method: M) -> Result<M::Response, SocialvoidError> {
+        self.session.invoke(method).await
+    }
+
     /// Set the CDN server URL from the ServerInfomation
     pub async fn reset_cdn_url(&mut self) -> Result<(), SocialvoidError> {
         self.cdn_client =
@@ -189,50 +217,42 @@ impl Client {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::MockServer;
     use rand::distributions::Alphanumeric;
     use rand::{thread_rng, Rng};
 
-    const CREDS_FILE_1: &str = "test_creds.test";
+    const USERNAME: &str = "light";
+    const PASSWORD: &str = "SuperStrongPassword";
 
     #[tokio::test]
     async fn it_should_log_in_and_get_the_correct_peer() -> Result<(), SocialvoidError> {
-        // let sessions_file = "sessions.test";
+        let server = MockServer::start().await?;
+        server.add_user(USERNAME, PASSWORD, "Light");
 
-        let creds: serde_json::Value =
-            serde_json::from_str(&std::fs::read_to_string(CREDS_FILE_1).unwrap())?;
-
-        let sv = new_with_defaults().await?;
+        let sv = server.client().await?;
+        sv.session.create().await?;
         sv.session
-            .authenticate_user(
-                creds["username"].as_str().unwrap().to_string(),
-                creds["password"].as_str().unwrap().to_string(),
-                None,
-            )
+            .authenticate_user(USERNAME.to_string(), PASSWORD.to_string(), None)
             .await?;
 
         let peer = sv.network.get_me().await?;
 
         println!("{:?}", peer);
         sv.session.logout().await?;
-        assert_eq!(
-            peer.username,
-            creds["username"].as_str().unwrap().to_string()
-        );
+        assert_eq!(peer.username, USERNAME);
 
         Ok(())
     }
 
     #[tokio::test]
     async fn it_should_create_post_and_delete_it() -> Result<(), SocialvoidError> {
-        let creds: serde_json::Value =
-            serde_json::from_str(&std::fs::read_to_string(CREDS_FILE_1).unwrap())?;
-        let sv = new_with_defaults().await?;
+        let server = MockServer::start().await?;
+        server.add_user(USERNAME, PASSWORD, "Light");
+
+        let sv = server.client().await?;
+        sv.session.create().await?;
         sv.session
-            .authenticate_user(
-                creds["username"].as_str().unwrap().to_string(),
-                creds["password"].as_str().unwrap().to_string(),
-                None,
-            )
+            .authenticate_user(USERNAME.to_string(), PASSWORD.to_string(), None)
             .await?;
 
         let post_text = thread_rng()
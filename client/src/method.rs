@@ -0,0 +1,123 @@
+use crate::error::{ClientError, SocialvoidError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use socialvoid_types::{Page, SessionIdentification};
+
+/// A method of the Socialvoid API.
+/// The type implementing this trait holds the parameters of the method (except the
+/// `session_identification` which is added by the client) and is serialized as the `params`
+/// of the request.
+///
+/// Methods that aren't wrapped by this crate can be called with `Client::invoke` by implementing
+/// this trait:
+/// ```no_run
+/// # use serde::Serialize;
+/// # use socialvoid::method::Method;
+/// #[derive(Serialize)]
+/// struct GetProfile {
+///     peer: Option<String>,
+/// }
+///
+/// impl Method for GetProfile {
+///     const NAME: &'static str = "network.get_profile";
+///     type Response = socialvoid_types::Profile;
+/// }
+/// # async fn run(sv: socialvoid::Client) -> Result<(), socialvoid::SocialvoidError> {
+/// let profile = sv.invoke(GetProfile { peer: None }).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait Method: Serialize {
+    /// The name of the method, e.g. `network.get_me`
+    const NAME: &'static str;
+    /// Whether the method is called on a session, i.e. needs a `session_identification`
+    const REQUIRES_SESSION: bool = true;
+    /// Whether the method fails with `ClientError::NotAuthenticated` without being sent if the
+    /// session isn't known to be authenticated. `false` by default: the server refuses the
+    /// methods needing an authenticated session anyway, and the local state of a session can be
+    /// stale, e.g. for a session loaded from a file until `session.get` refreshes it.
+    const REQUIRES_AUTHENTICATION: bool = false;
+    /// The type of the result of the method
+    type Response: DeserializeOwned + std::fmt::Debug;
+}
+
+/// The response of a paginated method called with a `cursor`. A server that doesn't paginate with
+/// cursors ignores it and answers with the bare list of the first page, which isn't a snapshot
+/// and doesn't tell if there are more results.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum PageResponse<T> {
+    Page(Page<T>),
+    List(Vec<T>),
+}
+
+impl<T> PageResponse<T> {
+    /// The page, or `ClientError::CursorsNotSupported` if the server answered with a bare list
+    pub fn into_page(self) -> Result<Page<T>, SocialvoidError> {
+        match self {
+            PageResponse::Page(page) => Ok(page),
+            PageResponse::List(_) => Err(ClientError::CursorsNotSupported.into()),
+        }
+    }
+}
+
+/// Sends the method, adding the session identification to the parameters if it is given
+pub(crate) async fn send<M: Method>(
+    client: &socialvoid_rawclient::Client,
+    session_identification: Option<SessionIdentification>,
+    method: &M,
+) -> Result<M::Response, SocialvoidError> {
+    let params = params(session_identification, method)?;
+    Ok(client.send_request(M::NAME, params).await?)
+}
+
+/// Serializes the parameters of the method
+fn params<M: Method>(
+    session_identification: Option<SessionIdentification>,
+    method: &M,
+) -> Result<serde_json::Value, serde_json::Error> {
+    let mut params = serde_json::to_value(method)?;
+    if let Some(session_identification) = session_identification {
+        if params.is_null() {
+            params = serde_json::Value::Object(serde_json::Map::new());
+        }
+        if let Some(params) = params.as_object_mut() {
+            params.insert(
+                "session_identification".to_string(),
+                serde_json::to_value(session_identification)?,
+            );
+        }
+    }
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::help::methods::GetTermsOfService;
+    use crate::network::methods::{GetMe, GetProfile};
+    use serde_json::json;
+
+    fn session_identification() -> SessionIdentification {
+        SessionIdentification {
+            session_id: "session".into(),
+            client_public_hash: "public".to_string(),
+            challenge_answer: "answer".to_string(),
+        }
+    }
+
+    #[test]
+    fn it_should_add_the_session_identification_to_the_params() -> Result<(), serde_json::Error> {
+        let identification = serde_json::to_value(session_identification())?;
+        assert_eq!(
+            params(Some(session_identification()), &GetMe)?,
+            json!({ "session_identification": identification })
+        );
+        assert_eq!(
+            params(Some(session_identification()), &GetProfile { peer: None })?,
+            json!({ "session_identification": identification, "peer": null })
+        );
+        assert_eq!(params(None, &GetTermsOfService)?, json!(null));
+        Ok(())
+    }
+}
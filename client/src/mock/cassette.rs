@@ -0,0 +1,541 @@
+//! Recording the traffic of the clients into cassettes, and replaying them.
+//!
+//! A `Recorder` is a proxy in front of a real JSON-RPC server and CDN which records every
+//! request and response. The cassette can then be saved and served by a `Player`, so a test
+//! talking to a real network can be replayed without it:
+//! ```no_run
+//! # use socialvoid::mock::cassette::{Cassette, Player, Recorder};
+//! # async fn run() -> Result<(), socialvoid::SocialvoidError> {
+//! let recorder = Recorder::start(
+//!     "http://socialvoid.qlg1.com:5601/",
+//!     "http://socialvoid.qlg1.com:5602/",
+//! )
+//! .await?;
+//! let sv = recorder.client().await?;
+//! sv.session.create().await?;
+//! recorder.cassette().save("session.cassette.json")?;
+//!
+//! let player = Player::start(Cassette::load("session.cassette.json")?).await?;
+//! let sv = player.client().await?;
+//! sv.session.create().await?;
+//! # Ok(())
+//! # }
+//! ```
+//! Requests are matched on their method and normalized params: the JSON-RPC ids are ignored
+//! and the secrets (passwords, hashes and challenge answers) are redacted before recording.
+//! The secrets of the responses are redacted too, the challenges being replaced with one the
+//! clients can still answer.
+
+use crate::error::SocialvoidError;
+use crate::session::{ClientInfo, SessionHolder};
+use crate::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use socialvoid_rawclient::RpcErrorCode;
+use socialvoid_server::http::{self, Field, Request, Response};
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+
+/// The keys whose values are replaced with `REDACTED`, wherever they are in the params
+pub const REDACTED_KEYS: &[&str] = &[
+    "password",
+    "otp",
+    "private_hash",
+    "public_hash",
+    "client_public_hash",
+    "challenge_answer",
+];
+
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Replaces the challenges of the responses: a base32 secret, so the replaying clients can still
+/// answer it
+pub const REDACTED_CHALLENGE: &str = "REDACTEDREDACTED";
+
+/// The recorded interactions, in the order they happened
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// A request, as it is matched
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    /// The JSON-RPC method, or `cdn.<action>` for the CDN
+    pub method: String,
+    /// The redacted params. The uploaded files are described by their name and size.
+    pub params: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Body,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Body {
+    Json(Value),
+    /// Any other content, base32 encoded
+    Bytes(String),
+}
+
+/// The headers worth replaying
+const RECORDED_HEADERS: &[&str] = &["content-type", "content-range"];
+
+impl Cassette {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Cassette> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+}
+
+/// Replaces the values of the `REDACTED_KEYS` in the value
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if REDACTED_KEYS.contains(&key.as_str()) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Like `redact`, also replacing the values of the `challenge` keys with `REDACTED_CHALLENGE`
+pub fn redact_response(value: &mut Value) {
+    redact(value);
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if key == "challenge" {
+                    *value = Value::String(REDACTED_CHALLENGE.to_string());
+                } else {
+                    redact_response(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_response),
+        _ => {}
+    }
+}
+
+/// Describes a request the way it is recorded and matched.
+/// `None` if it isn't a valid JSON-RPC or CDN request.
+fn describe(request: &Request) -> Option<RecordedRequest> {
+    let mut recorded = match request.header("content-type") {
+        Some(content_type) if content_type.starts_with("multipart/form-data") => {
+            let fields = http::parse_multipart(content_type, &request.body)?;
+            let action = String::from_utf8_lossy(&fields.get("action")?.data).into_owned();
+            let mut params: serde_json::Map<String, Value> = fields
+                .iter()
+                .filter(|(name, _)| name.as_str() != "action")
+                .map(|(name, field)| (name.clone(), describe_field(field)))
+                .collect();
+            if let Some(range) = request.header("range") {
+                params.insert("range".to_string(), Value::String(range.to_string()));
+            }
+            RecordedRequest {
+                method: format!("cdn.{}", action),
+                params: Value::Object(params),
+            }
+        }
+        _ => {
+            let body: Value = serde_json::from_slice(&request.body).ok()?;
+            RecordedRequest {
+                method: body.get("method")?.as_str()?.to_string(),
+                params: body.get("params").cloned().unwrap_or(Value::Null),
+            }
+        }
+    };
+    redact(&mut recorded.params);
+    Some(recorded)
+}
+
+fn describe_field(field: &Field) -> Value {
+    match &field.file_name {
+        Some(file_name) => json!({ "file_name": file_name, "size": field.data.len() }),
+        None => Value::String(String::from_utf8_lossy(&field.data).into_owned()),
+    }
+}
+
+fn record(response: &Response) -> RecordedResponse {
+    let headers = response
+        .headers
+        .iter()
+        .filter(|(name, _)| RECORDED_HEADERS.contains(&name.to_lowercase().as_str()))
+        .cloned()
+        .collect();
+    let is_json = response.headers.iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case("content-type") && value.starts_with("application/json")
+    });
+    let body = match serde_json::from_slice(&response.body) {
+        Ok(mut body) if is_json => {
+            redact_response(&mut body);
+            Body::Json(body)
+        }
+        _ => Body::Bytes(base32::encode(
+            base32::Alphabet::RFC4648 { padding: true },
+            &response.body,
+        )),
+    };
+    RecordedResponse {
+        status: response.status,
+        headers,
+        body,
+    }
+}
+
+/// The response for a request, answering a JSON-RPC request with its own id
+fn replay(recorded: &RecordedResponse, request: &Request) -> Response {
+    let body = match &recorded.body {
+        Body::Json(body) => {
+            let mut body = body.clone();
+            let id = serde_json::from_slice::<Value>(&request.body)
+                .ok()
+                .and_then(|request| request.get("id").cloned());
+            if let (Some(body), Some(id)) = (body.as_object_mut(), id) {
+                if body.contains_key("jsonrpc") {
+                    body.insert("id".to_string(), id);
+                }
+            }
+            serde_json::to_vec(&body).expect("a JSON value is serializable")
+        }
+        Body::Bytes(bytes) => {
+            base32::decode(base32::Alphabet::RFC4648 { padding: true }, bytes).unwrap_or_default()
+        }
+    };
+    Response {
+        status: recorded.status,
+        headers: recorded.headers.clone(),
+        body,
+        hang_up: false,
+    }
+}
+
+/// A proxy recording the traffic to a JSON-RPC server and a CDN, stopped when dropped
+pub struct Recorder {
+    url: String,
+    cassette: Arc<Mutex<Cassette>>,
+    task: JoinHandle<Result<(), http::Error>>,
+}
+
+impl Recorder {
+    /// Starts recording, forwarding the JSON-RPC requests to `rpc_url` and the CDN requests to
+    /// `cdn_url`
+    pub async fn start(rpc_url: &str, cdn_url: &str) -> io::Result<Recorder> {
+        let (listener, url) = http::bind().await?;
+        let cassette = Arc::new(Mutex::new(Cassette::default()));
+        let upstream = Arc::new(Upstream {
+            client: reqwest::Client::new(),
+            rpc_url: rpc_url.to_string(),
+            cdn_url: cdn_url.to_string(),
+        });
+        let task = tokio::spawn(http::serve(listener, usize::MAX, {
+            let cassette = Arc::clone(&cassette);
+            move |request| {
+                let (upstream, cassette) = (Arc::clone(&upstream), Arc::clone(&cassette));
+                async move {
+                    let response = upstream.forward(&request).await;
+                    if let Some(recorded) = describe(&request) {
+                        cassette.lock().unwrap().interactions.push(Interaction {
+                            request: recorded,
+                            response: record(&response),
+                        });
+                    }
+                    response
+                }
+            }
+        }));
+        Ok(Recorder {
+            url,
+            cassette,
+            task,
+        })
+    }
+
+    /// The URL of the proxy, for both the JSON-RPC and the CDN requests
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The interactions recorded so far
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+
+    /// A client with a new client info and no session, talking through this proxy
+    pub async fn client(&self) -> Result<Client, SocialvoidError> {
+        client(&self.url).await
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct Upstream {
+    client: reqwest::Client,
+    rpc_url: String,
+    cdn_url: String,
+}
+
+impl Upstream {
+    /// Sends the request to the CDN if it's `multipart/form-data`, to the JSON-RPC server otherwise
+    async fn forward(&self, request: &Request) -> Response {
+        let content_type = request.header("content-type").unwrap_or("application/json");
+        let url = if content_type.starts_with("multipart/form-data") {
+            &self.cdn_url
+        } else {
+            &self.rpc_url
+        };
+        let mut forwarded = self
+            .client
+            .post(url)
+            .header("Content-Type", content_type)
+            .body(request.body.clone());
+        if let Some(range) = request.header("range") {
+            forwarded = forwarded.header("Range", range);
+        }
+        match self.send(forwarded).await {
+            Ok(response) => response,
+            Err(error) => Response::bytes(502, error.to_string().into_bytes()),
+        }
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> reqwest::Result<Response> {
+        let response = request.send().await?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| RECORDED_HEADERS.contains(&name.as_str()))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = response.bytes().await?.to_vec();
+        Ok(Response {
+            status,
+            headers,
+            body,
+            hang_up: false,
+        })
+    }
+}
+
+/// A server answering with the responses of a cassette, stopped when dropped.
+///
+/// Each request is answered with the first interaction recorded for it that wasn't replayed yet,
+/// or with the last one if they all were, e.g. when a client polls more than when recording.
+pub struct Player {
+    url: String,
+    interactions: Arc<Mutex<Vec<(Interaction, bool)>>>,
+    task: JoinHandle<Result<(), http::Error>>,
+}
+
+impl Player {
+    pub async fn start(cassette: Cassette) -> io::Result<Player> {
+        let (listener, url) = http::bind().await?;
+        let interactions: Arc<Mutex<Vec<_>>> = Arc::new(Mutex::new(
+            cassette
+                .interactions
+                .into_iter()
+                .map(|interaction| (interaction, false))
+                .collect(),
+        ));
+        let task = tokio::spawn(http::serve(listener, usize::MAX, {
+            let interactions = Arc::clone(&interactions);
+            move |request| {
+                let response = play(&mut interactions.lock().unwrap(), &request);
+                async move { response }
+            }
+        }));
+        Ok(Player {
+            url,
+            interactions,
+            task,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Whether every recorded interaction was replayed
+    pub fn is_done(&self) -> bool {
+        self.interactions
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|(_, played)| *played)
+    }
+
+    /// A client with a new client info and no session, talking to this player
+    pub async fn client(&self) -> Result<Client, SocialvoidError> {
+        client(&self.url).await
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn play(interactions: &mut [(Interaction, bool)], request: &Request) -> Response {
+    let recorded = describe(request);
+    let mut matching = interactions
+        .iter_mut()
+        .filter(|(interaction, _)| Some(&interaction.request) == recorded.as_ref())
+        .peekable();
+    let mut last = None;
+    while let Some((interaction, played)) = matching.next() {
+        if !*played || matching.peek().is_none() {
+            *played = true;
+            last = Some(interaction);
+            break;
+        }
+    }
+    match last {
+        Some(interaction) => replay(&interaction.response, request),
+        None => {
+            let message = match recorded {
+                Some(recorded) => format!(
+                    "no interaction was recorded for {} {}",
+                    recorded.method, recorded.params
+                ),
+                None => "the request isn't a JSON-RPC or a CDN request".to_string(),
+            };
+            let code = RpcErrorCode::InternalError.code();
+            let is_cdn = request
+                .header("content-type")
+                .is_some_and(|content_type| content_type.starts_with("multipart/form-data"));
+            let mut response = Response::json(&if is_cdn {
+                json!({ "success": false, "error_code": code, "message": message })
+            } else {
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": Value::Null,
+                    "error": { "code": code, "message": message },
+                })
+            });
+            response.status = 404;
+            response
+        }
+    }
+}
+
+async fn client(url: &str) -> Result<Client, SocialvoidError> {
+    crate::new(
+        SessionHolder::new(Arc::new(ClientInfo::generate())),
+        Some(url.to_string()),
+        Some(url.to_string()),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockServer;
+
+    const PASSWORD: &str = "SuperStrongPassword";
+
+    /// Signs in, posts and uploads a file
+    async fn session(sv: &Client, file: &str) -> Result<Vec<String>, SocialvoidError> {
+        sv.session.create().await?;
+        sv.session
+            .authenticate_user("light".to_string(), PASSWORD.to_string(), None)
+            .await?;
+        let me = sv.network.get_me().await?;
+        let post = sv.timeline.compose("I am justice", vec![]).await?;
+        let document = sv.session.upload_file(file).await?;
+        let content = sv.session.download_file(document.id.clone()).await?;
+        Ok(vec![
+            me.username,
+            post.id.into_string(),
+            document.id.into_string(),
+            String::from_utf8_lossy(&content).into_owned(),
+        ])
+    }
+
+    #[test]
+    fn it_should_redact_the_secrets() {
+        let mut params = json!({
+            "username": "light",
+            "password": PASSWORD,
+            "session_identification": { "session_id": "1", "challenge_answer": "42" },
+        });
+        redact(&mut params);
+        assert_eq!(params["username"], "light");
+        assert_eq!(params["password"], REDACTED);
+        assert_eq!(params["session_identification"]["session_id"], "1");
+        assert_eq!(
+            params["session_identification"]["challenge_answer"],
+            REDACTED
+        );
+    }
+
+    #[test]
+    fn it_should_redact_the_secrets_of_the_responses() {
+        let mut body = json!({
+            "result": { "id": "1", "challenge": "JBSWY3DPEHPK3PXP", "public_hash": "ab" },
+        });
+        redact_response(&mut body);
+        assert_eq!(body["result"]["id"], "1");
+        assert_eq!(body["result"]["challenge"], REDACTED_CHALLENGE);
+        assert_eq!(body["result"]["public_hash"], REDACTED);
+    }
+
+    #[tokio::test]
+    async fn it_should_replay_a_recorded_session() -> Result<(), SocialvoidError> {
+        let file = std::env::temp_dir().join(format!("cassette-{}.txt", std::process::id()));
+        let file = file.to_str().unwrap();
+        std::fs::write(file, "the content")?;
+        let cassette_path = format!("{}.cassette.json", file);
+
+        let server = MockServer::start().await?;
+        server.add_user("light", PASSWORD, "Light");
+        let recorder = Recorder::start(server.url(), server.url()).await?;
+        let sv = recorder.client().await?;
+        let recorded = session(&sv, file).await?;
+        recorder.cassette().save(&cassette_path)?;
+        drop(server);
+
+        let saved = std::fs::read_to_string(&cassette_path)?;
+        assert!(!saved.contains(PASSWORD));
+        let holder = SessionHolder::deserialize(sv.session.serialize());
+        let challenge = &holder.established.as_ref().unwrap().challenge;
+        assert!(!saved.contains(challenge.as_str()));
+        assert!(saved.contains(REDACTED_CHALLENGE));
+        assert!(!saved.contains(&holder.client_info.private_hash));
+        let player = Player::start(Cassette::load(&cassette_path)?).await?;
+        let replayed = session(&player.client().await?, file).await?;
+        std::fs::remove_file(file)?;
+        std::fs::remove_file(&cassette_path)?;
+
+        assert_eq!(replayed, recorded);
+        assert_eq!(recorded[3], "the content");
+        assert!(player.is_done());
+        Ok(())
+    }
+}
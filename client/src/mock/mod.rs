@@ -0,0 +1,315 @@
+//! An in-process Socialvoid server for testing without a network.
+//!
+//! The server is a `socialvoid_server` network kept in memory, answering both the JSON-RPC
+//! requests and the CDN requests on the same URL:
+//! ```no_run
+//! # async fn run() -> Result<(), socialvoid::SocialvoidError> {
+//! let server = socialvoid::mock::MockServer::start().await?;
+//! server.add_user("light", "SuperStrongPassword", "Light");
+//!
+//! let sv = server.client().await?;
+//! sv.session.create().await?;
+//! sv.session
+//!     .authenticate_user("light".to_string(), "SuperStrongPassword".to_string(), None)
+//!     .await?;
+//! assert_eq!(sv.network.get_me().await?.username, "light");
+//! # Ok(())
+//! # }
+//! ```
+//! It's available to the tests of this crate, and to other crates with the `mock` feature.
+//! The `cassette` module records the traffic with a real server to replay it the same way.
+
+pub mod cassette;
+
+use crate::environment::Environment;
+use crate::error::SocialvoidError;
+use crate::session::ClientInfo;
+use crate::session::SessionHolder;
+use crate::Client;
+use socialvoid_server::storage::MemoryStorage;
+use socialvoid_server::{http, Config, Network, Shared};
+use socialvoid_types::Peer;
+use std::io;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+
+/// The limits of a `MockServer`
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    /// The number of results in a page of the paginated methods
+    pub page_size: u32,
+    /// The number of seconds a session lasts
+    pub session_ttl: u64,
+    /// The size of the largest document that can be uploaded, in bytes
+    pub upload_max_file_size: u32,
+    /// The clock of the sessions and the generator of the IDs and challenges
+    pub environment: Environment,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        MockConfig {
+            page_size: 20,
+            session_ttl: 600,
+            upload_max_file_size: 8 * 1024 * 1024,
+            environment: Environment::default(),
+        }
+    }
+}
+
+/// A Socialvoid server listening on a local port, stopped when dropped
+pub struct MockServer {
+    url: String,
+    network: Shared<MemoryStorage>,
+    task: JoinHandle<Result<(), http::Error>>,
+}
+
+impl MockServer {
+    /// Starts a server with the default config
+    pub async fn start() -> io::Result<MockServer> {
+        MockServer::start_with(MockConfig::default()).await
+    }
+
+    pub async fn start_with(config: MockConfig) -> io::Result<MockServer> {
+        let (listener, url) = http::bind().await?;
+        let network_config = Config {
+            network_name: "Socialvoid Mock".to_string(),
+            cdn_server: url.clone(),
+            page_size: config.page_size,
+            unauthorized_session_ttl: config.session_ttl as u32,
+            authorized_session_ttl: config.session_ttl as u32,
+            upload_max_file_size: config.upload_max_file_size,
+        };
+        let network =
+            Network::with_environment(MemoryStorage::new(), network_config, config.environment);
+        let network = Arc::new(Mutex::new(network));
+        let task = tokio::spawn(socialvoid_server::serve(listener, Arc::clone(&network)));
+        Ok(MockServer { url, network, task })
+    }
+
+    /// The URL of both the JSON-RPC and the CDN endpoints
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Registers a user, as `session.register` would
+    pub fn add_user(&self, username: &str, password: &str, first_name: &str) -> Peer {
+        self.network
+            .lock()
+            .unwrap()
+            .register(username, password, first_name, None)
+            .unwrap_or_else(|fault| panic!("Couldn't add {}: {}", username, fault.message))
+    }
+
+    /// A client with a new client info and no session, talking to this server
+    pub async fn client(&self) -> Result<Client, SocialvoidError> {
+        self.client_with(Environment::default()).await
+    }
+
+    /// Like `client`, with the client info and the client generated from `environment`
+    pub async fn client_with(&self, environment: Environment) -> Result<Client, SocialvoidError> {
+        crate::new_with_environment(
+            SessionHolder::new(Arc::new(ClientInfo::generate_with(&environment))),
+            Some(self.url.clone()),
+            Some(self.url.clone()),
+            environment,
+        )
+        .await
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::{Clock, ManualClock, SystemClock};
+    use futures::TryStreamExt;
+    use socialvoid_rawclient::{AuthenticationError, ErrorKind, ValidationError};
+    use socialvoid_types::{PeerRef, RelationshipType};
+
+    async fn sign_in(server: &MockServer, username: &str) -> Result<Client, SocialvoidError> {
+        let sv = server.client().await?;
+        sv.session.create().await?;
+        sv.session
+            .authenticate_user(
+                username.to_string(),
+                "SuperStrongPassword".to_string(),
+                None,
+            )
+            .await?;
+        Ok(sv)
+    }
+
+    #[tokio::test]
+    async fn it_should_show_the_posts_of_the_followed_peers() -> Result<(), SocialvoidError> {
+        let server = MockServer::start_with(MockConfig {
+            page_size: 2,
+            ..MockConfig::default()
+        })
+        .await?;
+        server.add_user("light", "SuperStrongPassword", "Light");
+        server.add_user("ryuk", "SuperStrongPassword", "Ryuk");
+        let (light, ryuk) = (
+            sign_in(&server, "light").await?,
+            sign_in(&server, "ryuk").await?,
+        );
+
+        let relationship = ryuk
+            .network
+            .follow_peer(PeerRef::Username("light".to_string()))
+            .await?;
+        assert!(matches!(relationship, RelationshipType::Following));
+        for text in &["one", "two", "three @ryuk"] {
+            light.timeline.compose(text, vec![]).await?;
+        }
+
+        let feed: Vec<_> = ryuk.timeline.feed().try_collect().await?;
+        let texts: Vec<_> = feed
+            .iter()
+            .filter_map(|post| post.text.as_deref())
+            .collect();
+        assert_eq!(texts, ["three @ryuk", "two", "one"]);
+        assert_eq!(feed[0].mentioned_peers[0].username, "ryuk");
+        let followers = light.network.get_followers(None, None).await?;
+        assert_eq!(followers[0].username, "ryuk");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_page_with_cursors_while_posts_are_published() -> Result<(), SocialvoidError>
+    {
+        let server = MockServer::start_with(MockConfig {
+            page_size: 2,
+            ..MockConfig::default()
+        })
+        .await?;
+        server.add_user("light", "SuperStrongPassword", "Light");
+        let light = sign_in(&server, "light").await?;
+        for text in &["1", "2", "3", "4", "5"] {
+            light.timeline.compose(text, vec![]).await?;
+        }
+
+        let mut texts = vec![];
+        let mut cursor = None;
+        loop {
+            let page = light.timeline.retrieve_feed_page(cursor).await?;
+            texts.extend(page.results.into_iter().filter_map(|post| post.text));
+            // the newer posts aren't in the next pages, so no post is repeated
+            light.timeline.compose("newer", vec![]).await?;
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(texts, ["5", "4", "3", "2", "1"]);
+
+        let invalid = light
+            .timeline
+            .retrieve_feed_page(Some("page 2".into()))
+            .await;
+        match invalid {
+            Err(SocialvoidError::RawClient(error)) => assert!(matches!(
+                error.kind,
+                ErrorKind::Validation(ValidationError::InvalidCursorValue)
+            )),
+            other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_walk_the_pages_smaller_than_the_max_limit() -> Result<(), SocialvoidError> {
+        let server = MockServer::start_with(MockConfig {
+            page_size: 2,
+            ..MockConfig::default()
+        })
+        .await?;
+        // advertises pages of up to 100 results, while they have 2
+        let (listener, url) = http::bind().await?;
+        let upstream = server.url().to_string();
+        let proxy = tokio::spawn(http::serve(listener, usize::MAX, move |request| {
+            let upstream = upstream.clone();
+            async move {
+                let response = reqwest::Client::new()
+                    .post(&upstream)
+                    .header("Content-Type", "application/json")
+                    .body(request.body)
+                    .send()
+                    .await
+                    .unwrap();
+                let mut body: serde_json::Value = response.json().await.unwrap();
+                if let Some(result) = body["result"].as_object_mut() {
+                    for (name, value) in result.iter_mut() {
+                        if name.ends_with("_max_limit") {
+                            *value = 100.into();
+                        }
+                    }
+                }
+                http::Response::json(&body)
+            }
+        }));
+        server.add_user("light", "SuperStrongPassword", "Light");
+        let light = crate::new_with_environment(
+            SessionHolder::new(Arc::new(ClientInfo::generate())),
+            Some(url.clone()),
+            Some(url),
+            Environment::default(),
+        )
+        .await?;
+        light.session.create().await?;
+        light
+            .session
+            .authenticate_user("light".to_string(), "SuperStrongPassword".to_string(), None)
+            .await?;
+        assert_eq!(
+            light
+                .session
+                .server_information()
+                .await?
+                .retrieve_feed_max_limit,
+            100
+        );
+        for text in &["1", "2", "3", "4", "5"] {
+            light.timeline.compose(text, vec![]).await?;
+        }
+
+        let feed: Vec<_> = light.timeline.feed().try_collect().await?;
+        let texts: Vec<_> = feed
+            .iter()
+            .filter_map(|post| post.text.as_deref())
+            .collect();
+        assert_eq!(texts, ["5", "4", "3", "2", "1"]);
+        proxy.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_expire_the_sessions_with_the_clock_of_its_environment(
+    ) -> Result<(), SocialvoidError> {
+        let clock = Arc::new(ManualClock::new(SystemClock.now()));
+        let server = MockServer::start_with(MockConfig {
+            session_ttl: 60,
+            environment: Environment::new(Arc::clone(&clock), rand::rngs::OsRng),
+            ..MockConfig::default()
+        })
+        .await?;
+        let sv = server.client().await?;
+        sv.session.create().await?;
+        sv.session.get().await?;
+
+        clock.advance(60);
+        match sv.session.get().await {
+            Err(SocialvoidError::RawClient(error)) => assert!(matches!(
+                error.kind,
+                ErrorKind::Authentication(AuthenticationError::SessionExpired)
+            )),
+            other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+        }
+        Ok(())
+    }
+}
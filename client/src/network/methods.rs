@@ -0,0 +1,117 @@
+//! Parameters and result types of the `network` methods
+
+use crate::method::{Method, PageResponse};
+use serde::Serialize;
+use socialvoid_types::{Cursor, Peer, PeerRef, Profile, RelationshipType};
+
+/// `network.get_me`: Returns the peer of the authenticated user
+#[derive(Serialize, Debug)]
+pub struct GetMe;
+
+impl Method for GetMe {
+    const NAME: &'static str = "network.get_me";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Peer;
+}
+
+/// `network.get_profile`: Returns the profile of a peer, or of the authenticated peer if `peer` is `None`
+#[derive(Serialize, Debug)]
+pub struct GetProfile {
+    pub peer: Option<PeerRef>,
+}
+
+impl Method for GetProfile {
+    const NAME: &'static str = "network.get_profile";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Profile;
+}
+
+/// `network.resolve_peer`: Resolves a peer from its ID or username
+#[derive(Serialize, Debug)]
+pub struct ResolvePeer {
+    pub peer: PeerRef,
+}
+
+impl Method for ResolvePeer {
+    const NAME: &'static str = "network.resolve_peer";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Peer;
+}
+
+/// `network.follow_peer`: Follows a peer
+#[derive(Serialize, Debug)]
+pub struct FollowPeer {
+    pub peer: PeerRef,
+}
+
+impl Method for FollowPeer {
+    const NAME: &'static str = "network.follow_peer";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = RelationshipType;
+}
+
+/// `network.unfollow_peer`: Unfollows a peer
+#[derive(Serialize, Debug)]
+pub struct UnfollowPeer {
+    pub peer: PeerRef,
+}
+
+impl Method for UnfollowPeer {
+    const NAME: &'static str = "network.unfollow_peer";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = RelationshipType;
+}
+
+/// `network.get_followers`: Returns a page of the followers of a peer
+#[derive(Serialize, Debug)]
+pub struct GetFollowers {
+    pub peer: Option<PeerRef>,
+    pub page: Option<u32>,
+}
+
+impl Method for GetFollowers {
+    const NAME: &'static str = "network.get_followers";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Vec<Peer>;
+}
+
+/// `network.get_following`: Returns a page of the peers a peer is following
+#[derive(Serialize, Debug)]
+pub struct GetFollowing {
+    pub peer: Option<PeerRef>,
+    pub page: Option<u32>,
+}
+
+impl Method for GetFollowing {
+    const NAME: &'static str = "network.get_following";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Vec<Peer>;
+}
+
+/// `network.get_followers`: Returns the followers of a peer starting at a cursor
+#[derive(Serialize, Debug)]
+pub struct GetFollowersPage {
+    pub peer: Option<PeerRef>,
+    /// `None` for the first page
+    pub cursor: Option<Cursor>,
+}
+
+impl Method for GetFollowersPage {
+    const NAME: &'static str = "network.get_followers";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = PageResponse<Peer>;
+}
+
+/// `network.get_following`: Returns the peers a peer is following starting at a cursor
+#[derive(Serialize, Debug)]
+pub struct GetFollowingPage {
+    pub peer: Option<PeerRef>,
+    /// `None` for the first page
+    pub cursor: Option<Cursor>,
+}
+
+impl Method for GetFollowingPage {
+    const NAME: &'static str = "network.get_following";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = PageResponse<Peer>;
+}
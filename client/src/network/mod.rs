@@ -1,158 +1,180 @@
+pub mod methods;
+
+use crate::error::SocialvoidError;
+use crate::pagination::paginate;
 use crate::SVSessionMethods;
-use serde_json::json;
-use socialvoid_rawclient as rawclient;
-use socialvoid_rawclient::Error;
+use futures::Stream;
+use methods::*;
+use socialvoid_types::DisplayPictureSize;
 use socialvoid_types::Peer;
+use socialvoid_types::PeerRef;
 use socialvoid_types::Profile;
 use socialvoid_types::RelationshipType;
+use socialvoid_types::{Cursor, Page};
 use std::sync::Arc;
 
 pub struct SVNetworkMethods {
-    client: Arc<rawclient::Client>,
     session: Arc<SVSessionMethods>,
 }
 
 impl SVNetworkMethods {
-    pub fn new(client: Arc<rawclient::Client>, session: Arc<SVSessionMethods>) -> Self {
-        Self { client, session }
+    pub fn new(session: Arc<SVSessionMethods>) -> Self {
+        Self { session }
     }
 
     /// GetMe
     /// Returns the peer object of the authenticated peer
-    pub async fn get_me(&self) -> Result<Peer, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "network.get_me",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?
-                }),
-            )
-            .await
+    pub async fn get_me(&self) -> Result<Peer, SocialvoidError> {
+        self.session.invoke(GetMe).await
     }
 
     /// GetProfile
-    /// `peer` can be 'None' for own profile, otherwise,
-    /// 'peer' can be Some(p) where p can be the id or username(with leading @) of the peer.
-    pub async fn get_profile(&self, peer: Option<String>) -> Result<Profile, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "network.get_profile",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "peer": peer,
-                }),
-            )
-            .await
+    /// `peer` can be 'None' for own profile
+    pub async fn get_profile(&self, peer: Option<PeerRef>) -> Result<Profile, SocialvoidError> {
+        self.session.invoke(GetProfile { peer }).await
+    }
+
+    /// Downloads the display picture of a peer (or of the authenticated peer if `peer` is `None`)
+    /// that best fits a `size`x`size` box into `file_path`.
+    /// Returns the downloaded size, or `None` if the peer has no display picture.
+    pub async fn download_display_picture(
+        &self,
+        peer: Option<PeerRef>,
+        size: u32,
+        file_path: &str,
+    ) -> Result<Option<DisplayPictureSize>, SocialvoidError> {
+        let profile = self.get_profile(peer).await?;
+        let picture = match profile.best_display_picture(size) {
+            Some(picture) => picture.clone(),
+            None => return Ok(None),
+        };
+        self.session
+            .download_file_to(picture.document.id.clone(), file_path)
+            .await?;
+        Ok(Some(picture))
     }
 
     /// ResolvePeer
-    pub async fn resolve_peer(&self, peer: String) -> Result<Peer, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "network.resolve_peer",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "peer": peer,
-                }),
-            )
-            .await
+    pub async fn resolve_peer(&self, peer: PeerRef) -> Result<Peer, SocialvoidError> {
+        self.session.invoke(ResolvePeer { peer }).await
     }
 
     /// UnfollowPeer
-    pub async fn unfollow_peer(&self, peer: String) -> Result<RelationshipType, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "network.unfollow_peer",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "peer": peer,
-                }),
-            )
-            .await
+    pub async fn unfollow_peer(&self, peer: PeerRef) -> Result<RelationshipType, SocialvoidError> {
+        self.session.invoke(UnfollowPeer { peer }).await
     }
 
     /// FollowPeer
-    pub async fn follow_peer(&self, peer: String) -> Result<RelationshipType, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "network.follow_peer",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "peer": peer,
-                }),
-            )
-            .await
+    pub async fn follow_peer(&self, peer: PeerRef) -> Result<RelationshipType, SocialvoidError> {
+        self.session.invoke(FollowPeer { peer }).await
     }
 
     /// Get the number of followers of a specific peer or get the number of followers of the authenticated peer if the peer is `None`
     /// `page` can be none to return the first page of the result.
     pub async fn get_followers(
         &self,
-        peer: Option<String>,
+        peer: Option<PeerRef>,
         page: Option<u32>,
-    ) -> Result<Vec<Peer>, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "network.get_followers",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "peer":peer,
-                    "page": page,
-                }),
-            )
-            .await
+    ) -> Result<Vec<Peer>, SocialvoidError> {
+        self.session.invoke(GetFollowers { peer, page }).await
     }
 
     /// Get the number of peers following a specific peer or get the number of peers following the authenticated peer if the peer is `None`
     /// `page` can be none to return the first page of the result.
     pub async fn get_following(
         &self,
-        peer: Option<String>,
+        peer: Option<PeerRef>,
         page: Option<u32>,
-    ) -> Result<Vec<Peer>, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "network.get_following",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "peer":peer,
-                    "page": page,
-                }),
-            )
-            .await
+    ) -> Result<Vec<Peer>, SocialvoidError> {
+        self.session.invoke(GetFollowing { peer, page }).await
+    }
+
+    /// Like `get_followers` but requests the page starting at `cursor` (`None` for the first page).
+    /// The returned page carries the cursor of the next page. Fails with
+    /// `ClientError::CursorsNotSupported` if the server doesn't paginate with cursors.
+    pub async fn get_followers_page(
+        &self,
+        peer: Option<PeerRef>,
+        cursor: Option<Cursor>,
+    ) -> Result<Page<Peer>, SocialvoidError> {
+        self.session
+            .invoke(GetFollowersPage { peer, cursor })
+            .await?
+            .into_page()
+    }
+
+    /// Like `get_following` but requests the page starting at `cursor` (`None` for the first page).
+    /// The returned page carries the cursor of the next page. Fails with
+    /// `ClientError::CursorsNotSupported` if the server doesn't paginate with cursors.
+    pub async fn get_following_page(
+        &self,
+        peer: Option<PeerRef>,
+        cursor: Option<Cursor>,
+    ) -> Result<Page<Peer>, SocialvoidError> {
+        self.session
+            .invoke(GetFollowingPage { peer, cursor })
+            .await?
+            .into_page()
+    }
+
+    /// Streams every follower of a peer (or of the authenticated peer if `peer` is `None`),
+    /// requesting the pages of `get_followers` as needed
+    pub fn followers(
+        &self,
+        peer: Option<PeerRef>,
+    ) -> impl Stream<Item = Result<Peer, SocialvoidError>> + '_ {
+        paginate(self.session.prefetch_pages(), move |page| {
+            self.get_followers(peer.clone(), Some(page))
+        })
+    }
+
+    /// Streams every peer a peer (or the authenticated peer if `peer` is `None`) is following,
+    /// requesting the pages of `get_following` as needed
+    pub fn following(
+        &self,
+        peer: Option<PeerRef>,
+    ) -> impl Stream<Item = Result<Peer, SocialvoidError>> + '_ {
+        paginate(self.session.prefetch_pages(), move |page| {
+            self.get_following(peer.clone(), Some(page))
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::{AuthenticationError, ClientError, ErrorKind};
+    use crate::mock::MockServer;
     use crate::session::{ClientInfo, SVSessionMethods, SessionHolder};
-    use rawclient::{AuthenticationError, ErrorKind};
     use std::sync::{Arc, Mutex};
     #[tokio::test]
     async fn it_should_return_a_notauthenticated_error() {
-        let client = Arc::new(socialvoid_rawclient::new());
+        let server = MockServer::start().await.unwrap();
+        let client = Arc::new(socialvoid_rawclient::with_host(server.url()));
         let session = Arc::new(SVSessionMethods::new(
             Arc::clone(&client),
-            Arc::new(socialvoid_rawclient::CdnClient::new()),
+            Arc::new(socialvoid_rawclient::CdnClient::with_cdn_url(
+                server.url().to_string(),
+            )),
             Arc::new(Mutex::new(SessionHolder::new(Arc::new(
                 ClientInfo::generate(),
             )))),
         ));
         session.create().await.expect("Couldn't create a session.");
 
-        let network = SVNetworkMethods::new(Arc::clone(&client), Arc::clone(&session));
+        let network = SVNetworkMethods::new(Arc::clone(&session));
         match network.get_me().await {
             Ok(_) => panic!("Session found for some reason.?"),
-            Err(error) => match error.kind {
-                ErrorKind::Authentication(error) => match error {
+            Err(SocialvoidError::Client(ClientError::NotAuthenticated)) => {}
+            Err(error) => panic!("Unexpected error: {:#?}", error),
+        }
+
+        // the server refuses it too
+        let session_identification = session.session_identification().unwrap();
+        match crate::method::send(&client, Some(session_identification), &methods::GetMe).await {
+            Ok(_) => panic!("Session found for some reason.?"),
+            Err(error) => match error.kind() {
+                Some(ErrorKind::Authentication(error)) => match error {
                     AuthenticationError::NotAuthenticated => {}
                     authkind => panic!("Unexpected authentication error: {:#?}", authkind),
                 },
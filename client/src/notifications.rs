@@ -0,0 +1,510 @@
+//! Notifications built from the timeline, the replies to the recent posts of the authenticated
+//! peer and its followers, as the API has no notifications endpoint.
+
+use crate::error::SocialvoidError;
+use crate::network::SVNetworkMethods;
+use crate::timeline::{self, FeedEvent, FeedWatcher, FeedWatcherConfig, SVTimelineMethods};
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use socialvoid_types::{Peer, PeerId, Post, PostId, PostType};
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Something that happened to the authenticated peer
+#[derive(Debug, Clone)]
+pub enum Notification {
+    /// A post mentioning the authenticated peer
+    Mentioned(Box<Post>),
+    /// A reply to a post of the authenticated peer
+    RepliedTo(Box<Post>),
+    /// A post quoting a post of the authenticated peer
+    Quoted(Box<Post>),
+    /// A repost of a post of the authenticated peer
+    Reposted(Box<Post>),
+    /// A peer that started following the authenticated peer
+    NewFollower(Peer),
+}
+
+/// What `NotificationWatcher::watch_with_feed` yields
+#[derive(Debug, Clone)]
+pub enum WatchEvent {
+    Notification(Notification),
+    /// A change of the timeline, found by the feed poll the notifications are found with
+    Feed(FeedEvent),
+}
+
+/// Configures a `NotificationWatcher`
+#[derive(Debug, Clone)]
+pub struct NotificationWatcherConfig {
+    /// The feed watcher the notifications are found with. Its interval and backoff are also used
+    /// for the other requests.
+    pub feed: FeedWatcherConfig,
+    /// How many of the latest posts of the authenticated peer are checked for replies
+    pub recent_posts: usize,
+    /// How many pages of followers are compared to find new followers
+    pub follower_pages: u32,
+    /// The file the recent posts with their replies and the followers are saved to, so a
+    /// restarted watcher doesn't yield the same replies and followers again. Nothing is saved if
+    /// `None`. The state of the feed is saved to `feed.state_file`.
+    pub state_file: Option<PathBuf>,
+}
+
+impl Default for NotificationWatcherConfig {
+    fn default() -> Self {
+        NotificationWatcherConfig {
+            feed: FeedWatcherConfig::default(),
+            recent_posts: 10,
+            follower_pages: 1,
+            state_file: None,
+        }
+    }
+}
+
+/// Polls the timeline, the replies to the recent posts of the authenticated peer and its
+/// followers and streams the notifications.
+/// Like the `FeedWatcher`, the first poll only records what already happened.
+pub struct NotificationWatcher {
+    network: Arc<SVNetworkMethods>,
+    timeline: Arc<SVTimelineMethods>,
+    feed: FeedWatcher,
+    config: NotificationWatcherConfig,
+    me: Option<PeerId>,
+    state: NotificationState,
+}
+
+/// What the watcher knows besides the timeline, saved to `NotificationWatcherConfig::state_file`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct NotificationState {
+    /// The latest posts of the authenticated peer, newest first
+    own_posts: VecDeque<OwnPost>,
+    /// `None` until the first poll
+    followers: Option<HashSet<PeerId>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OwnPost {
+    id: PostId,
+    /// Whether the replies were already requested
+    checked: bool,
+    /// The replies notifications were yielded for (or that were seen by the first request).
+    /// Only the replies of the last request are kept, they are forgotten with the post.
+    replies: HashSet<PostId>,
+}
+
+impl NotificationWatcher {
+    /// Creates a watcher, loading the states saved by an earlier watcher if there are some
+    pub fn new(
+        network: Arc<SVNetworkMethods>,
+        timeline: Arc<SVTimelineMethods>,
+        config: NotificationWatcherConfig,
+    ) -> Result<Self, SocialvoidError> {
+        let state = match &config.state_file {
+            Some(path) if path.exists() => serde_json::from_slice(&std::fs::read(path)?)?,
+            _ => NotificationState::default(),
+        };
+        Ok(NotificationWatcher {
+            feed: FeedWatcher::new(Arc::clone(&timeline), config.feed.clone())?,
+            network,
+            timeline,
+            config,
+            me: None,
+            state,
+        })
+    }
+
+    /// Polls forever, starting right away.
+    /// Errors are yielded as they happen and the watcher keeps polling with a backoff.
+    pub fn watch(self) -> impl Stream<Item = Result<Notification, SocialvoidError>> {
+        self.watch_with_feed().filter_map(|event| async move {
+            match event {
+                Ok(WatchEvent::Notification(notification)) => Some(Ok(notification)),
+                Ok(WatchEvent::Feed(_)) => None,
+                Err(err) => Some(Err(err)),
+            }
+        })
+    }
+
+    /// Like `watch`, also yielding the changes of the timeline, e.g. to watch the timeline
+    /// without polling it a second time
+    pub fn watch_with_feed(self) -> impl Stream<Item = Result<WatchEvent, SocialvoidError>> {
+        stream::unfold(
+            (self, None),
+            |(mut watcher, failures): (NotificationWatcher, Option<u32>)| async move {
+                if let Some(failures) = failures {
+                    tokio::time::sleep(timeline::delay(&watcher.config.feed, failures)).await;
+                }
+                let (events, failures) = match watcher.poll_with_feed().await {
+                    Ok(events) => (events.into_iter().map(Ok).collect(), 0),
+                    Err(err) => (vec![Err(err)], failures.unwrap_or(0) + 1),
+                };
+                Some((stream::iter(events), (watcher, Some(failures))))
+            },
+        )
+        .flatten()
+    }
+
+    /// Requests everything once and returns the new notifications
+    pub async fn poll(&mut self) -> Result<Vec<Notification>, SocialvoidError> {
+        let events = self.poll_with_feed().await?;
+        Ok(events
+            .into_iter()
+            .filter_map(|event| match event {
+                WatchEvent::Notification(notification) => Some(notification),
+                WatchEvent::Feed(_) => None,
+            })
+            .collect())
+    }
+
+    /// Like `poll`, also returning the changes of the timeline, before the notifications
+    pub async fn poll_with_feed(&mut self) -> Result<Vec<WatchEvent>, SocialvoidError> {
+        let me = match &self.me {
+            Some(me) => me.clone(),
+            None => {
+                let me = self.network.get_me().await?.id;
+                // the feed watcher doesn't yield the posts that are already on the timeline
+                for post in self.timeline.retrieve_feed(None).await? {
+                    self.track(&me, &post, false);
+                }
+                self.me = Some(me.clone());
+                me
+            }
+        };
+
+        let mut feed = vec![];
+        let mut notifications = vec![];
+        for event in self.feed.poll().await? {
+            feed.push(WatchEvent::Feed(event.clone()));
+            if let FeedEvent::NewPost(post) = event {
+                self.track(&me, &post, true);
+                // the replies to the recent posts are also found with `get_replies`
+                let replied = post.reply_to_post.as_ref().and_then(|replied| {
+                    self.state
+                        .own_posts
+                        .iter_mut()
+                        .find(|own| own.id == replied.id)
+                });
+                if let Some(replied) = replied {
+                    if !replied.replies.insert(post.id.clone()) {
+                        continue;
+                    }
+                }
+                notifications.extend(notification_for(&me, *post));
+            }
+        }
+
+        for own in self.state.own_posts.iter_mut() {
+            let mut replies = HashSet::new();
+            for reply in self.timeline.get_replies(own.id.clone(), None).await? {
+                let is_new = !own.replies.contains(&reply.id);
+                replies.insert(reply.id.clone());
+                if is_new && own.checked && author(&reply) != Some(&me) {
+                    notifications.push(Notification::RepliedTo(Box::new(reply)));
+                }
+            }
+            own.replies = replies;
+            own.checked = true;
+        }
+
+        let mut followers = vec![];
+        for page in 1..=self.config.follower_pages {
+            let peers = self.network.get_followers(None, Some(page)).await?;
+            if peers.is_empty() {
+                break;
+            }
+            followers.extend(peers);
+        }
+        notifications.extend(
+            new_followers(&mut self.state.followers, followers)
+                .into_iter()
+                .map(Notification::NewFollower),
+        );
+
+        if let Some(path) = &self.config.state_file {
+            std::fs::write(path, serde_json::to_vec(&self.state)?)?;
+        }
+        feed.extend(notifications.into_iter().map(WatchEvent::Notification));
+        Ok(feed)
+    }
+
+    /// Remembers a post of the authenticated peer to check its replies.
+    /// The replies of a post that was just published are all new.
+    fn track(&mut self, me: &PeerId, post: &Post, published: bool) {
+        if author(post) != Some(me) || matches!(post.post_type, PostType::Repost) {
+            return;
+        }
+        let own_posts = &mut self.state.own_posts;
+        if own_posts.iter().any(|own| own.id == post.id) {
+            return;
+        }
+        own_posts.push_front(OwnPost {
+            id: post.id.clone(),
+            checked: published,
+            replies: HashSet::new(),
+        });
+        own_posts.truncate(self.config.recent_posts);
+    }
+}
+
+fn author(post: &Post) -> Option<&PeerId> {
+    post.peer.as_ref().map(|peer| &peer.id)
+}
+
+/// Returns the notification a post of the timeline is for, if any.
+/// A post that is both a reply and a mention is only notified as a reply.
+fn notification_for(me: &PeerId, post: Post) -> Option<Notification> {
+    let by_me = |post: &Option<Box<Post>>| post.as_deref().and_then(author) == Some(me);
+    if author(&post) == Some(me) {
+        return None;
+    }
+    let post = Box::new(post);
+    match post.post_type {
+        PostType::Reply if by_me(&post.reply_to_post) => Some(Notification::RepliedTo(post)),
+        PostType::Quote if by_me(&post.quoted_post) => Some(Notification::Quoted(post)),
+        PostType::Repost if by_me(&post.reposted_post) => Some(Notification::Reposted(post)),
+        _ if post.mentioned_peers.iter().any(|peer| &peer.id == me) => {
+            Some(Notification::Mentioned(post))
+        }
+        _ => None,
+    }
+}
+
+/// Records the followers and returns the ones that weren't known.
+/// The first call only records them.
+fn new_followers(known: &mut Option<HashSet<PeerId>>, followers: Vec<Peer>) -> Vec<Peer> {
+    match known {
+        Some(known) => followers
+            .into_iter()
+            .filter(|peer| known.insert(peer.id.clone()))
+            .collect(),
+        None => {
+            *known = Some(followers.into_iter().map(|peer| peer.id).collect());
+            vec![]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockServer;
+    use crate::Client;
+    use socialvoid_types::{PeerRef, PeerType};
+
+    fn peer(id: &str) -> Peer {
+        Peer {
+            id: id.into(),
+            peer_type: PeerType::USER,
+            name: id.to_string(),
+            username: id.to_string(),
+            flags: vec![],
+        }
+    }
+
+    fn post(id: &str, author: &str, post_type: PostType) -> Post {
+        Post {
+            id: id.into(),
+            post_type,
+            peer: Some(peer(author)),
+            source: None,
+            text: None,
+            attachments: vec![],
+            entities: vec![],
+            mentioned_peers: vec![],
+            reply_to_post: None,
+            quoted_post: None,
+            reposted_post: None,
+            original_thread_post: None,
+            like_count: None,
+            repost_count: None,
+            quote_count: None,
+            reply_count: None,
+            posted_timestamp: 0,
+            flags: vec![],
+        }
+    }
+
+    #[test]
+    fn it_should_find_the_notification_of_a_post() {
+        let me = PeerId::from("me");
+        let mine = Box::new(post("mine", "me", PostType::Post));
+
+        let mut reply = post("reply", "l", PostType::Reply);
+        reply.reply_to_post = Some(mine.clone());
+        reply.mentioned_peers = vec![peer("me")];
+        assert!(matches!(
+            notification_for(&me, reply),
+            Some(Notification::RepliedTo(_))
+        ));
+
+        let mut quote = post("quote", "l", PostType::Quote);
+        quote.quoted_post = Some(mine.clone());
+        assert!(matches!(
+            notification_for(&me, quote),
+            Some(Notification::Quoted(_))
+        ));
+
+        let mut repost = post("repost", "l", PostType::Repost);
+        repost.reposted_post = Some(mine);
+        assert!(matches!(
+            notification_for(&me, repost),
+            Some(Notification::Reposted(_))
+        ));
+
+        let mut mention = post("mention", "l", PostType::Post);
+        mention.mentioned_peers = vec![peer("ryuk"), peer("me")];
+        assert!(matches!(
+            notification_for(&me, mention),
+            Some(Notification::Mentioned(_))
+        ));
+
+        assert!(notification_for(&me, post("other", "l", PostType::Post)).is_none());
+        let mut own_mention = post("own", "me", PostType::Post);
+        own_mention.mentioned_peers = vec![peer("me")];
+        assert!(notification_for(&me, own_mention).is_none());
+    }
+
+    #[test]
+    fn it_should_find_new_followers() {
+        let mut known = None;
+        assert!(new_followers(&mut known, vec![peer("a"), peer("b")]).is_empty());
+
+        let new = new_followers(&mut known, vec![peer("c"), peer("a"), peer("b")]);
+        let new: Vec<_> = new.iter().map(|peer| peer.id.as_str()).collect();
+        assert_eq!(new, vec!["c"]);
+
+        // a follower that went out of the compared pages isn't new when it comes back
+        assert!(new_followers(&mut known, vec![peer("d")]).len() == 1);
+        assert!(new_followers(&mut known, vec![peer("a"), peer("d")]).is_empty());
+    }
+
+    async fn sign_in(server: &MockServer, username: &str) -> Result<Client, SocialvoidError> {
+        server.add_user(username, "SuperStrongPassword", username);
+        let sv = server.client().await?;
+        sv.session.create().await?;
+        sv.session
+            .authenticate_user(
+                username.to_string(),
+                "SuperStrongPassword".to_string(),
+                None,
+            )
+            .await?;
+        Ok(sv)
+    }
+
+    #[tokio::test]
+    async fn it_should_not_notify_the_peer_of_its_own_posts() -> Result<(), SocialvoidError> {
+        let server = MockServer::start().await?;
+        let (light, ryuk) = (
+            sign_in(&server, "light").await?,
+            sign_in(&server, "ryuk").await?,
+        );
+        light
+            .network
+            .follow_peer(PeerRef::Username("ryuk".to_string()))
+            .await?;
+        let mut watcher = NotificationWatcher::new(
+            Arc::clone(&light.network),
+            Arc::clone(&light.timeline),
+            NotificationWatcherConfig::default(),
+        )?;
+        assert!(watcher.poll().await?.is_empty());
+
+        let note = light
+            .timeline
+            .compose("@light note to self", vec![])
+            .await?;
+        light
+            .timeline
+            .reply(note.id.clone(), "@light and another".to_string(), vec![])
+            .await?;
+        assert!(watcher.poll().await?.is_empty());
+
+        ryuk.timeline.compose("@light hi", vec![]).await?;
+        ryuk.timeline
+            .reply(note.id, "apples?".to_string(), vec![])
+            .await?;
+        let notifications = watcher.poll().await?;
+        assert_eq!(notifications.len(), 2, "{:#?}", notifications);
+        // the posts of the same second are in the order of their IDs
+        assert!(notifications.iter().any(
+            |notification| matches!(notification, Notification::Mentioned(post) if post.text.as_deref() == Some("@light hi"))
+        ));
+        assert!(notifications.iter().any(
+            |notification| matches!(notification, Notification::RepliedTo(post) if post.text.as_deref() == Some("apples?"))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_not_notify_again_after_a_restart() -> Result<(), SocialvoidError> {
+        let server = MockServer::start().await?;
+        let (light, ryuk, misa) = (
+            sign_in(&server, "light").await?,
+            sign_in(&server, "ryuk").await?,
+            sign_in(&server, "misa").await?,
+        );
+        let dir = std::env::temp_dir();
+        let config = NotificationWatcherConfig {
+            feed: FeedWatcherConfig {
+                state_file: Some(dir.join(format!("socialvoid-feed-{}.json", std::process::id()))),
+                ..FeedWatcherConfig::default()
+            },
+            state_file: Some(dir.join(format!(
+                "socialvoid-notifications-{}.json",
+                std::process::id()
+            ))),
+            ..NotificationWatcherConfig::default()
+        };
+        let watcher = || {
+            NotificationWatcher::new(
+                Arc::clone(&light.network),
+                Arc::clone(&light.timeline),
+                config.clone(),
+            )
+        };
+        let kinds = |notifications: Vec<Notification>| -> Vec<String> {
+            notifications
+                .into_iter()
+                .map(|notification| match notification {
+                    Notification::RepliedTo(post) => format!("reply {}", post.text.unwrap()),
+                    Notification::NewFollower(peer) => format!("follower {}", peer.username),
+                    notification => panic!("Unexpected notification: {:#?}", notification),
+                })
+                .collect()
+        };
+
+        let note = light.timeline.compose("note", vec![]).await?;
+        let mut first = watcher()?;
+        assert!(first.poll().await?.is_empty());
+        ryuk.timeline
+            .reply(note.id.clone(), "apples?".to_string(), vec![])
+            .await?;
+        ryuk.network
+            .follow_peer(PeerRef::Username("light".to_string()))
+            .await?;
+        assert_eq!(
+            kinds(first.poll().await?),
+            ["reply apples?", "follower ryuk"]
+        );
+        drop(first);
+
+        // while the watcher is stopped
+        misa.timeline
+            .reply(note.id, "hi light".to_string(), vec![])
+            .await?;
+        misa.network
+            .follow_peer(PeerRef::Username("light".to_string()))
+            .await?;
+        let mut restarted = watcher()?;
+        assert_eq!(
+            kinds(restarted.poll().await?),
+            ["reply hi light", "follower misa"]
+        );
+        assert!(restarted.poll().await?.is_empty());
+
+        std::fs::remove_file(config.feed.state_file.unwrap())?;
+        std::fs::remove_file(config.state_file.unwrap())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+//! Streams walking through the pages of the paginated methods
+
+use crate::error::SocialvoidError;
+use futures::future::{self, Future};
+use futures::stream::{self, Stream, StreamExt};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Streams the items of every page, starting from the first one, until an empty page.
+/// A page with fewer items than the `retrieve_*_max_limit` of the server isn't the last one, as
+/// the limit is only the most a page can have. Up to `prefetch` pages are requested
+/// concurrently, so a few pages past the last one may be requested. The stream ends after the
+/// first error.
+pub(crate) fn paginate<'a, T, F, Fut>(
+    prefetch: usize,
+    fetch: F,
+) -> impl Stream<Item = Result<T, SocialvoidError>> + 'a
+where
+    T: 'a,
+    F: FnMut(u32) -> Fut + 'a,
+    Fut: Future<Output = Result<Vec<T>, SocialvoidError>> + 'a,
+{
+    pages(prefetch, fetch).flat_map(|page| match page {
+        Ok(items) => stream::iter(items.into_iter().map(Ok)).left_stream(),
+        Err(err) => stream::once(future::ready(Err(err))).right_stream(),
+    })
+}
+
+/// Streams the pages until the first empty one or the first error
+fn pages<T, F, Fut>(
+    prefetch: usize,
+    fetch: F,
+) -> impl Stream<Item = Result<Vec<T>, SocialvoidError>>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, SocialvoidError>>,
+{
+    // Set once the last page was received so no further pages are requested. Pages that were
+    // already requested by the prefetch are dropped by the `scan`.
+    let done = Arc::new(AtomicBool::new(false));
+    let last_page_received = Arc::clone(&done);
+    stream::iter(1..)
+        .take_while(move |_| future::ready(!last_page_received.load(Ordering::SeqCst)))
+        .map(fetch)
+        .buffered(prefetch.max(1))
+        .scan(false, move |last, page| {
+            if *last {
+                return future::ready(None);
+            }
+            *last = match &page {
+                Ok(items) => items.is_empty(),
+                Err(_) => true,
+            };
+            done.store(*last, Ordering::SeqCst);
+            future::ready(Some(page))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ClientError;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    /// Serves `total` numbered items in pages of `page_size`
+    async fn fetch_page(
+        page: u32,
+        page_size: u32,
+        total: u32,
+    ) -> Result<Vec<u32>, SocialvoidError> {
+        let start = (page - 1) * page_size;
+        Ok((start..total.min(start + page_size)).collect())
+    }
+
+    #[tokio::test]
+    async fn it_should_walk_every_page() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        let items: Vec<u32> = paginate(1, move |page| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            fetch_page(page, 10, 25)
+        })
+        .map(Result::unwrap)
+        .collect()
+        .await;
+        assert_eq!(items, (0..25).collect::<Vec<_>>());
+        // and the empty page after the last one
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn it_should_stop_on_an_empty_page() {
+        let items: Vec<u32> = paginate(1, |page| fetch_page(page, 10, 20))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(items.len(), 20);
+    }
+
+    #[tokio::test]
+    async fn it_should_stop_after_an_error() {
+        let items: Vec<Result<u32, SocialvoidError>> = paginate(1, |page| async move {
+            if page == 2 {
+                Err(ClientError::SessionNotEstablished.into())
+            } else {
+                fetch_page(page, 2, 10).await
+            }
+        })
+        .collect()
+        .await;
+        assert_eq!(items.len(), 3);
+        assert!(items[..2].iter().all(Result::is_ok));
+        assert!(items[2].is_err());
+    }
+
+    #[tokio::test]
+    async fn it_should_not_stop_on_a_short_page() {
+        // e.g. a server leaving out the posts of the blocked peers
+        let sizes = [3, 1, 2, 0, 4];
+        let items: Vec<u32> = paginate(1, |page| async move {
+            let start = sizes[..page as usize - 1].iter().sum::<u32>();
+            Ok((start..start + sizes[page as usize - 1]).collect())
+        })
+        .map(Result::unwrap)
+        .collect()
+        .await;
+        assert_eq!(items, (0..6).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn it_should_limit_the_pages_fetched_concurrently() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let (current, max) = (Arc::clone(&in_flight), Arc::clone(&max_in_flight));
+        let items: Vec<u32> = paginate(3, move |page| {
+            let (current, max) = (Arc::clone(&current), Arc::clone(&max));
+            async move {
+                let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                max.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                current.fetch_sub(1, Ordering::SeqCst);
+                fetch_page(page, 5, 42).await
+            }
+        })
+        .map(Result::unwrap)
+        .collect()
+        .await;
+        assert_eq!(items, (0..42).collect::<Vec<_>>());
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::environment::Environment;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 use serde::{Deserialize, Serialize};
@@ -15,8 +16,17 @@ pub struct ClientInfo {
 impl ClientInfo {
     ///Generates client information
     pub fn generate() -> ClientInfo {
-        let public_hash = generate_random_hash();
-        let private_hash = generate_random_hash();
+        ClientInfo::generate_from(&mut thread_rng())
+    }
+
+    /// Generates client information with the random number generator of the environment
+    pub fn generate_with(environment: &Environment) -> ClientInfo {
+        ClientInfo::generate_from(&mut *environment.rng.lock().unwrap())
+    }
+
+    fn generate_from<R: Rng + ?Sized>(rng: &mut R) -> ClientInfo {
+        let public_hash = generate_random_hash(rng);
+        let private_hash = generate_random_hash(rng);
         let platform = env::consts::OS.to_string(); //String::from("Linux"); //TODO: auto detect platform?
         let name = String::from("Social Void Rust");
         let version = String::from("0.0.1"); //maybe have a better way to set this?
@@ -30,7 +40,7 @@ impl ClientInfo {
     }
 
     pub fn save(&self, fpath: &str) -> Result<(), std::io::Error> {
-        serde_json::to_writer(&std::fs::File::create(&fpath)?, self)?;
+        serde_json::to_writer(&std::fs::File::create(fpath)?, self)?;
         Ok(())
     }
 
@@ -41,13 +51,11 @@ impl ClientInfo {
     }
 }
 
-fn generate_random_hash() -> String {
+fn generate_random_hash<R: Rng + ?Sized>(rng: &mut R) -> String {
     //TODO: make it secure, more random idk
     sha256::digest::<String>(
-        thread_rng()
-            .sample_iter(&Alphanumeric)
-            .take(30)
-            .map(char::from)
+        (0..30)
+            .map(|_| char::from(rng.sample(Alphanumeric)))
             .collect(),
     )
 }
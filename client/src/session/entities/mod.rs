@@ -5,4 +5,3 @@ pub use session::RegisterRequest;
 pub use session::Session;
 pub use session::SessionEstablished;
 pub use session::SessionHolder;
-pub use session::SessionRegisterInput;
@@ -1,11 +1,11 @@
 use super::ClientInfo;
 use serde::{Deserialize, Serialize};
-use socialvoid_types::SessionIdentification;
+use socialvoid_types::{HelpDocumentId, SessionId};
 use std::sync::Arc;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Session {
-    pub id: String,
+    pub id: SessionId,
     pub flags: Vec<String>,
     pub authenticated: bool,
     pub created: i32,
@@ -14,7 +14,7 @@ pub struct Session {
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SessionEstablished {
-    pub id: String,
+    pub id: SessionId,
     pub challenge: String,
 }
 
@@ -23,7 +23,7 @@ pub struct SessionHolder {
     pub established: Option<SessionEstablished>,
     pub authenticated: bool,
     pub client_info: Arc<ClientInfo>,
-    pub tos_read: Option<String>, //Holds the terms of service ID
+    pub tos_read: Option<HelpDocumentId>, //Holds the terms of service ID
 }
 
 impl SessionHolder {
@@ -51,14 +51,3 @@ pub struct RegisterRequest {
     pub first_name: String,
     pub last_name: Option<String>,
 }
-
-#[derive(Serialize, Debug)]
-pub struct SessionRegisterInput {
-    pub session_identification: SessionIdentification,
-    pub terms_of_service_id: String,
-    pub terms_of_service_agree: bool,
-    pub username: String,
-    pub password: String,
-    pub first_name: String,
-    pub last_name: Option<String>,
-}
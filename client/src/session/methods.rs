@@ -0,0 +1,67 @@
+//! Parameters and result types of the `session` methods
+
+use super::{ClientInfo, Session, SessionEstablished};
+use crate::method::Method;
+use serde::Serialize;
+use socialvoid_types::{HelpDocumentId, Peer};
+
+/// `session.create`: Creates a session for the client
+#[derive(Serialize, Debug)]
+pub struct Create {
+    #[serde(flatten)]
+    pub client_info: ClientInfo,
+}
+
+impl Method for Create {
+    const NAME: &'static str = "session.create";
+    const REQUIRES_SESSION: bool = false;
+    type Response = SessionEstablished;
+}
+
+/// `session.get`: Returns the session
+#[derive(Serialize, Debug)]
+pub struct Get;
+
+impl Method for Get {
+    const NAME: &'static str = "session.get";
+    type Response = Session;
+}
+
+/// `session.authenticate_user`: Authenticates a user via a username & password and optionally an OTP
+#[derive(Serialize, Debug)]
+pub struct AuthenticateUser {
+    pub username: String,
+    pub password: String,
+    pub otp: Option<String>,
+}
+
+impl Method for AuthenticateUser {
+    const NAME: &'static str = "session.authenticate_user";
+    type Response = bool;
+}
+
+/// `session.logout`: Logs out without destroying the session
+#[derive(Serialize, Debug)]
+pub struct Logout;
+
+impl Method for Logout {
+    const NAME: &'static str = "session.logout";
+    type Response = bool;
+}
+
+/// `session.register`: Registers a new user to the network
+#[derive(Serialize, Debug)]
+pub struct Register {
+    /// ID of the terms of service that were agreed to
+    pub terms_of_service_id: HelpDocumentId,
+    pub terms_of_service_agree: bool,
+    pub username: String,
+    pub password: String,
+    pub first_name: String,
+    pub last_name: Option<String>,
+}
+
+impl Method for Register {
+    const NAME: &'static str = "session.register";
+    type Response = Peer;
+}
@@ -1,21 +1,26 @@
 mod entities;
-mod session_challenge;
+pub mod methods;
 
+use crate::cache::DocumentCache;
+use crate::environment::{Clock, SystemClock};
+use crate::error::ClientError;
+use crate::error::SocialvoidError;
+use crate::method::{self, Method};
 pub use entities::ClientInfo;
 pub use entities::RegisterRequest;
 pub use entities::Session;
 pub use entities::SessionEstablished;
 pub use entities::SessionHolder;
-use entities::SessionRegisterInput;
-use session_challenge::answer_challenge;
-use socialvoid_rawclient::ClientError;
-use socialvoid_rawclient::Error;
+use methods::*;
+use socialvoid_rawclient::challenge::answer_challenge_at;
 use socialvoid_types::Document;
+use socialvoid_types::DocumentId;
 pub use socialvoid_types::HelpDocument;
 use socialvoid_types::Peer;
+use socialvoid_types::ServerInformation;
 use socialvoid_types::SessionIdentification;
 
-use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -23,6 +28,10 @@ pub struct SVSessionMethods {
     client: Arc<socialvoid_rawclient::Client>,
     cdn_client: Arc<socialvoid_rawclient::CdnClient>,
     session: Arc<Mutex<SessionHolder>>,
+    clock: Arc<dyn Clock>,
+    document_cache: Mutex<Option<Arc<DocumentCache>>>,
+    server_information: Mutex<Option<Arc<ServerInformation>>>,
+    prefetch_pages: AtomicUsize,
 }
 
 impl SVSessionMethods {
@@ -30,12 +39,63 @@ impl SVSessionMethods {
         client: Arc<socialvoid_rawclient::Client>,
         cdn_client: Arc<socialvoid_rawclient::CdnClient>,
         session: Arc<Mutex<SessionHolder>>,
+    ) -> Self {
+        Self::with_clock(client, cdn_client, session, Arc::new(SystemClock))
+    }
+
+    /// Like `new`, answering the session challenges at the time of `clock`
+    pub fn with_clock(
+        client: Arc<socialvoid_rawclient::Client>,
+        cdn_client: Arc<socialvoid_rawclient::CdnClient>,
+        session: Arc<Mutex<SessionHolder>>,
+        clock: Arc<dyn Clock>,
     ) -> Self {
         Self {
             client,
             cdn_client,
             session,
+            clock,
+            document_cache: Mutex::new(None),
+            server_information: Mutex::new(None),
+            prefetch_pages: AtomicUsize::new(1),
+        }
+    }
+
+    /// Sets the cache used by `download_file`. `None` disables caching.
+    pub fn set_document_cache(&self, cache: Option<Arc<DocumentCache>>) {
+        *self.document_cache.lock().unwrap() = cache;
+    }
+
+    /// Sets how many pages the pagination streams (e.g. `network.followers`) request
+    /// concurrently. The default is 1, i.e. a page is only requested once the previous one
+    /// has been received.
+    pub fn set_prefetch_pages(&self, pages: usize) {
+        self.prefetch_pages.store(pages.max(1), Ordering::Relaxed);
+    }
+
+    pub(crate) fn prefetch_pages(&self) -> usize {
+        self.prefetch_pages.load(Ordering::Relaxed)
+    }
+
+    /// Returns the server information, requesting it only the first time
+    pub async fn server_information(&self) -> Result<Arc<ServerInformation>, SocialvoidError> {
+        if let Some(server_information) = self.server_information.lock().unwrap().clone() {
+            return Ok(server_information);
         }
+        let server_information = Arc::new(
+            self.invoke(crate::help::methods::GetServerInformation)
+                .await?,
+        );
+        *self.server_information.lock().unwrap() = Some(Arc::clone(&server_information));
+        Ok(server_information)
+    }
+
+    /// Returns the number of items per page of a paginated method, as given by `limit`
+    pub(crate) async fn page_size(
+        &self,
+        limit: fn(&ServerInformation) -> u32,
+    ) -> Result<u32, SocialvoidError> {
+        Ok(limit(&*self.server_information().await?))
     }
 
     pub fn serialize(&self) -> Vec<u8> {
@@ -46,29 +106,20 @@ impl SVSessionMethods {
     /// Creates a session and sets a session established object which contains a challenge.
     /// A session object is not yet returned - the challenge needs to be solved and sent inside a session identification
     /// object using the `get_session` method to get the Session object.
-    pub async fn create(&self) -> Result<(), Error> {
+    pub async fn create(&self) -> Result<(), SocialvoidError> {
+        let client_info = (*self.session.lock().unwrap().client_info).clone();
+        let established = self.invoke(Create { client_info }).await?;
         let mut session = self.session.lock().unwrap();
-        let client_info = &*session.client_info.clone();
-        session.established = Some(
-            self.client
-                .send_request("session.create", serde_json::value::to_value(client_info)?)
-                .await?,
-        );
+        session.established = Some(established);
+        session.authenticated = false;
         Ok(())
     }
 
     /// `session.get`
     /// Returns a `Session`
-    pub async fn get(&self) -> Result<Session, Error> {
-        let session_identification = self.session_identification()?;
-        let mut session = self.session.lock().unwrap();
-        let sesh: Session = self.client
-            .send_request(
-                "session.get",
-                json!({"session_identification": serde_json::value::to_value(session_identification)?}),
-            )
-            .await?;
-        session.authenticated = sesh.authenticated;
+    pub async fn get(&self) -> Result<Session, SocialvoidError> {
+        let sesh = self.invoke(Get).await?;
+        self.session.lock().unwrap().authenticated = sesh.authenticated;
         Ok(sesh)
     }
 
@@ -79,19 +130,13 @@ impl SVSessionMethods {
         username: String,
         password: String,
         otp: Option<String>,
-    ) -> Result<bool, Error> {
-        let session_identification = self.session_identification()?;
+    ) -> Result<bool, SocialvoidError> {
         let response = self
-            .client
-            .send_request(
-                "session.authenticate_user",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "username": username,
-                    "password": password,
-                    "otp": otp
-                }),
-            )
+            .invoke(AuthenticateUser {
+                username,
+                password,
+                otp,
+            })
             .await?;
         self.session.lock().unwrap().authenticated = true;
         Ok(response)
@@ -99,35 +144,23 @@ impl SVSessionMethods {
 
     /// `session.logout`
     /// Log out without destroying the session - changes the session expiration date too
-    pub async fn logout(&self) -> Result<bool, Error> {
-        let session_identification = self.session_identification()?;
-        let response = self
-            .client
-            .send_request(
-                "session.logout",
-                json!({
-                    "session_identification":serde_json::value::to_value(session_identification)?
-                }),
-            )
-            .await?;
+    pub async fn logout(&self) -> Result<bool, SocialvoidError> {
+        let response = self.invoke(Logout).await?;
         self.session.lock().unwrap().authenticated = false;
         Ok(response)
     }
 
     /// session.register
     /// Registers a new user to the network
-    pub async fn register(&self, request: RegisterRequest) -> Result<Peer, Error> {
-        let session_identification = self.session_identification()?;
-
-        let request = SessionRegisterInput {
-            session_identification,
+    pub async fn register(&self, request: RegisterRequest) -> Result<Peer, SocialvoidError> {
+        let request = Register {
             terms_of_service_id: self
                 .session
                 .lock()
                 .unwrap()
                 .tos_read
                 .take()
-                .ok_or_else(|| Error::new_client_error(ClientError::TermsOfServiceNotAgreed))?,
+                .ok_or(ClientError::TermsOfServiceNotAgreed)?,
             terms_of_service_agree: true,
             username: request.username,
             password: request.password,
@@ -135,25 +168,73 @@ impl SVSessionMethods {
             last_name: request.last_name,
         };
 
-        self.client
-            .send_request("session.register", serde_json::to_value(request)?)
-            .await
+        self.invoke(request).await
+    }
+
+    /// Calls a method, adding the session identification if the method needs one.
+    /// Methods with `REQUIRES_AUTHENTICATION` fail without being sent if the session isn't known
+    /// to be authenticated. The session is known not to be if the server says so.
+    pub async fn invoke<M: Method>(&self, method: M) -> Result<M::Response, SocialvoidError> {
+        if M::REQUIRES_AUTHENTICATION && !self.authenticated() {
+            return Err(ClientError::NotAuthenticated.into());
+        }
+        let session_identification = if M::REQUIRES_SESSION {
+            Some(self.session_identification()?)
+        } else {
+            None
+        };
+        let result = method::send(&self.client, session_identification, &method).await;
+        if let Err(error) = &result {
+            if error.is_auth_error() {
+                self.session.lock().unwrap().authenticated = false;
+            }
+        }
+        result
     }
 
     /// Upload a file to the CDN
-    pub async fn upload_file(&self, file: &str) -> Result<Document, Error> {
+    pub async fn upload_file(&self, file: &str) -> Result<Document, SocialvoidError> {
         let session_identification = self.session_identification()?;
-        self.cdn_client
+        Ok(self
+            .cdn_client
             .upload(session_identification, file.to_string())
-            .await
+            .await?)
     }
 
     /// Download a file from the CDN
-    pub async fn download_file(&self, document_id: String) -> Result<Vec<u8>, Error> {
+    /// If a document cache is set, the file is served from (and saved to) the cache.
+    pub async fn download_file(&self, document_id: DocumentId) -> Result<Vec<u8>, SocialvoidError> {
+        let cache = self.document_cache.lock().unwrap().clone();
+        if let Some(content) = cache
+            .as_ref()
+            .and_then(|cache| cache.get(document_id.as_str()))
+        {
+            return Ok(content);
+        }
+        let session_identification = self.session_identification()?;
+        let content = self
+            .cdn_client
+            .download(session_identification, document_id.clone())
+            .await?;
+        if let Some(cache) = cache {
+            // failing to cache shouldn't fail the download
+            let _ = cache.put(document_id.as_str(), &content);
+        }
+        Ok(content)
+    }
+
+    /// Download a file from the CDN into `file_path`, resuming a previously interrupted download
+    /// if the CDN supports it
+    pub async fn download_file_to(
+        &self,
+        document_id: DocumentId,
+        file_path: &str,
+    ) -> Result<(), SocialvoidError> {
         let session_identification = self.session_identification()?;
-        self.cdn_client
-            .download(session_identification, document_id)
-            .await
+        Ok(self
+            .cdn_client
+            .download_to_file(session_identification, document_id, file_path.to_string())
+            .await?)
     }
 
     /// Accepts the terms of service
@@ -163,17 +244,12 @@ impl SVSessionMethods {
         self.session.lock().unwrap().tos_read = Some(tos.id);
     }
 
-    pub fn session_identification(&self) -> Result<SessionIdentification, Error> {
+    pub fn session_identification(&self) -> Result<SessionIdentification, SocialvoidError> {
         let session = self.session.lock().unwrap();
         if session.established.is_none() {
-            return Err(Error::new_client_error(ClientError::SessionNotEstablished));
+            return Err(ClientError::SessionNotEstablished.into());
         }
-        let session_id = session
-            .established
-            .as_ref()
-            .map(|s| &s.id)
-            .unwrap()
-            .to_string();
+        let session_id = session.established.as_ref().map(|s| &s.id).unwrap().clone();
         let challenge = session
             .established
             .as_ref()
@@ -184,7 +260,11 @@ impl SVSessionMethods {
         Ok(SessionIdentification {
             session_id,
             client_public_hash,
-            challenge_answer: answer_challenge(session.client_info.private_hash.clone(), challenge),
+            challenge_answer: answer_challenge_at(
+                session.client_info.private_hash.clone(),
+                challenge,
+                self.clock.now(),
+            ),
         })
     }
 
@@ -196,15 +276,18 @@ impl SVSessionMethods {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::MockServer;
     use entities::RegisterRequest;
     use rand::distributions::Alphanumeric;
     use rand::{thread_rng, Rng};
-    use socialvoid_rawclient::{ClientError, Error, ErrorKind};
     #[tokio::test]
-    async fn it_should_establish_a_session_and_get_it() -> Result<(), Error> {
+    async fn it_should_establish_a_session_and_get_it() -> Result<(), SocialvoidError> {
+        let server = MockServer::start().await?;
         let session = SVSessionMethods::new(
-            Arc::new(socialvoid_rawclient::new()),
-            Arc::new(socialvoid_rawclient::CdnClient::new()),
+            Arc::new(socialvoid_rawclient::with_host(server.url())),
+            Arc::new(socialvoid_rawclient::CdnClient::with_cdn_url(
+                server.url().to_string(),
+            )),
             Arc::new(Mutex::new(SessionHolder::new(Arc::new(
                 ClientInfo::generate(),
             )))),
@@ -217,30 +300,29 @@ mod tests {
         Ok(())
     }
     #[tokio::test]
-    async fn it_should_establish_a_session_and_upload_and_download_a_file() -> Result<(), Error> {
-        let creds: serde_json::Value =
-            serde_json::from_str(&std::fs::read_to_string("../client/test_creds.test").unwrap())?;
+    async fn it_should_establish_a_session_and_upload_and_download_a_file(
+    ) -> Result<(), SocialvoidError> {
+        let server = MockServer::start().await?;
+        server.add_user("light", "SuperStrongPassword", "Light");
         let session = SVSessionMethods::new(
-            Arc::new(socialvoid_rawclient::new()),
-            Arc::new(socialvoid_rawclient::CdnClient::new()),
+            Arc::new(socialvoid_rawclient::with_host(server.url())),
+            Arc::new(socialvoid_rawclient::CdnClient::with_cdn_url(
+                server.url().to_string(),
+            )),
             Arc::new(Mutex::new(SessionHolder::new(Arc::new(
                 ClientInfo::generate(),
             )))),
         );
         session.create().await?;
 
-        assert_eq!(
+        assert!(
             session
-                .authenticate_user(
-                    creds["username"].as_str().unwrap().to_string(),
-                    creds["password"].as_str().unwrap().to_string(),
-                    None
-                )
-                .await?,
-            true
+                .authenticate_user("light".to_string(), "SuperStrongPassword".to_string(), None)
+                .await?
         );
 
-        let file_name = "test1.test";
+        let path = std::env::temp_dir().join(format!("test1-{}.test", std::process::id()));
+        let file_name = path.to_str().unwrap();
         let file_content: String = thread_rng()
             .sample_iter(&Alphanumeric)
             .take(30)
@@ -248,9 +330,10 @@ mod tests {
             .collect();
         std::fs::write(file_name, file_content.clone()).unwrap();
 
-        let document = session.upload_file(file_name).await?;
-        println!("Document: {:#?}", document);
+        let document = session.upload_file(file_name).await;
         std::fs::remove_file(file_name).unwrap();
+        let document = document?;
+        println!("Document: {:#?}", document);
 
         let id = document.id;
         let downloaded_file = session.download_file(id).await?;
@@ -262,10 +345,15 @@ mod tests {
     #[test]
     fn it_should_write_and_read_client_info_from_file() -> Result<(), std::io::Error> {
         let client_info_generated = ClientInfo::generate();
-        let filename = "test_session_file.socialvoid.session";
+        let path = std::env::temp_dir().join(format!(
+            "test_session_file-{}.socialvoid.session",
+            std::process::id()
+        ));
+        let filename = path.to_str().unwrap();
         client_info_generated.save(filename)?;
 
         let client_info_read = ClientInfo::load_from_file(filename)?;
+        std::fs::remove_file(filename)?;
 
         assert_eq!(client_info_generated, client_info_read);
 
@@ -273,10 +361,13 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn it_should_throw_a_terms_of_service_not_agreed_error() -> Result<(), Error> {
+    async fn it_should_throw_a_terms_of_service_not_agreed_error() -> Result<(), SocialvoidError> {
+        let server = MockServer::start().await?;
         let session = SVSessionMethods::new(
-            Arc::new(socialvoid_rawclient::new()),
-            Arc::new(socialvoid_rawclient::CdnClient::new()),
+            Arc::new(socialvoid_rawclient::with_host(server.url())),
+            Arc::new(socialvoid_rawclient::CdnClient::with_cdn_url(
+                server.url().to_string(),
+            )),
             Arc::new(Mutex::new(SessionHolder::new(Arc::new(
                 ClientInfo::generate(),
             )))),
@@ -292,13 +383,7 @@ mod tests {
             })
             .await;
         match response {
-            Err(e) => match e.kind {
-                ErrorKind::Client(e) => match e {
-                    ClientError::TermsOfServiceNotAgreed => {}
-                    _ => unreachable!(),
-                },
-                _ => unreachable!(),
-            },
+            Err(SocialvoidError::Client(ClientError::TermsOfServiceNotAgreed)) => {}
             _ => unreachable!(),
         }
         Ok(())
@@ -314,9 +399,12 @@ mod tests {
 
         let client_info = ClientInfo::generate();
         let private_hash = client_info.private_hash.clone();
+        let server = MockServer::start().await.unwrap();
         let session = SVSessionMethods::new(
-            Arc::new(socialvoid_rawclient::new()),
-            Arc::new(socialvoid_rawclient::CdnClient::new()),
+            Arc::new(socialvoid_rawclient::with_host(server.url())),
+            Arc::new(socialvoid_rawclient::CdnClient::with_cdn_url(
+                server.url().to_string(),
+            )),
             Arc::new(Mutex::new(SessionHolder::new(Arc::new(
                 ClientInfo::generate(),
             )))),
@@ -324,8 +412,11 @@ mod tests {
         session.create().await.expect("Couldn't create the session");
         let established = session.session.lock().unwrap();
         let established = established.established.as_ref().unwrap();
-        let challenge_answer =
-            answer_challenge(private_hash.clone(), established.challenge.clone());
+        let challenge_answer = answer_challenge_at(
+            private_hash.clone(),
+            established.challenge.clone(),
+            SystemClock.now(),
+        );
 
         let output = Command::new("python3")
             .arg("src/session/test-hotp.py")
@@ -340,4 +431,130 @@ mod tests {
         );
         assert_eq!(String::from_utf8_lossy(&output.stdout), challenge_answer);
     }
+
+    /// The bodies of the requests of a session with the client and the server seeded with
+    /// `seed`, captured by a proxy between them
+    async fn seeded_requests(now: u64, seed: u64) -> Result<Vec<Vec<u8>>, SocialvoidError> {
+        use crate::environment::{Environment, ManualClock};
+        use crate::mock::MockConfig;
+        use rand::{rngs::StdRng, SeedableRng};
+        use socialvoid_rawclient::challenge::TIME_STEP;
+        use socialvoid_server::http::{self, Response};
+
+        let clock = Arc::new(ManualClock::new(now));
+        let environment = || Environment::new(Arc::clone(&clock), StdRng::seed_from_u64(seed));
+        let server = MockServer::start_with(MockConfig {
+            environment: environment(),
+            ..MockConfig::default()
+        })
+        .await?;
+        server.add_user("light", "SuperStrongPassword", "Light");
+
+        let (listener, url) = http::bind().await?;
+        let requests = Arc::new(Mutex::new(vec![]));
+        let proxy = tokio::spawn(http::serve(listener, usize::MAX, {
+            let (requests, upstream) = (Arc::clone(&requests), server.url().to_string());
+            move |request| {
+                requests.lock().unwrap().push(request.body.clone());
+                let upstream = upstream.clone();
+                async move {
+                    let response = reqwest::Client::new()
+                        .post(&upstream)
+                        .header("Content-Type", "application/json")
+                        .body(request.body)
+                        .send()
+                        .await
+                        .unwrap();
+                    let mut answer = Response::bytes(
+                        response.status().as_u16(),
+                        response.bytes().await.unwrap().to_vec(),
+                    );
+                    answer.headers =
+                        vec![("Content-Type".to_string(), "application/json".to_string())];
+                    answer
+                }
+            }
+        }));
+
+        let sv = crate::new_with_environment(
+            SessionHolder::new(Arc::new(ClientInfo::generate_with(&environment()))),
+            Some(url.clone()),
+            Some(url),
+            environment(),
+        )
+        .await?;
+        sv.session.create().await?;
+        sv.session
+            .authenticate_user("light".to_string(), "SuperStrongPassword".to_string(), None)
+            .await?;
+        sv.session.get().await?;
+        clock.advance(TIME_STEP);
+        sv.session.get().await?;
+        proxy.abort();
+
+        let requests = requests.lock().unwrap().clone();
+        Ok(requests)
+    }
+
+    #[tokio::test]
+    async fn it_should_reproduce_the_requests_with_a_seeded_environment(
+    ) -> Result<(), SocialvoidError> {
+        let now = SystemClock.now();
+        let first = seeded_requests(now, 42).await?;
+        let second = seeded_requests(now, 42).await?;
+        assert_eq!(first.len(), 4);
+        // the answer of the second session.get is the one of the next time step
+        assert_ne!(first[2], first[3]);
+        // the same ids, hashes, session and answers, byte for byte
+        assert_eq!(first, second);
+
+        let other = seeded_requests(now, 43).await?;
+        assert_ne!(first, other);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_leave_the_authentication_to_the_server() -> Result<(), SocialvoidError> {
+        use socialvoid_types::Peer;
+
+        /// `network.get_me` as defined by a user of the crate
+        #[derive(serde::Serialize)]
+        struct GetMe;
+        impl Method for GetMe {
+            const NAME: &'static str = "network.get_me";
+            type Response = Peer;
+        }
+        #[derive(serde::Serialize)]
+        struct Logout;
+        impl Method for Logout {
+            const NAME: &'static str = "session.logout";
+            type Response = bool;
+        }
+
+        let server = MockServer::start().await?;
+        server.add_user("light", "SuperStrongPassword", "Light");
+        let sv = server.client().await?;
+        sv.session.create().await?;
+        sv.session
+            .authenticate_user("light".to_string(), "SuperStrongPassword".to_string(), None)
+            .await?;
+        // e.g. a session loaded from a file saved before authenticating
+        sv.session.session.lock().unwrap().authenticated = false;
+
+        assert_eq!(sv.session.invoke(GetMe).await?.username, "light");
+        assert!(matches!(
+            sv.network.get_me().await,
+            Err(SocialvoidError::Client(ClientError::NotAuthenticated))
+        ));
+        assert!(sv.session.get().await?.authenticated);
+        assert_eq!(sv.network.get_me().await?.username, "light");
+
+        // logged out behind the back of the session
+        assert!(sv.session.invoke(Logout).await?);
+        assert!(sv.session.authenticated());
+        let refused = sv.network.get_me().await.unwrap_err();
+        assert!(refused.kind().is_some() && refused.is_auth_error());
+        assert!(!sv.session.authenticated());
+        Ok(())
+    }
 }
@@ -0,0 +1,242 @@
+//! Parameters and result types of the `timeline` methods
+
+use crate::method::{Method, PageResponse};
+use serde::Serialize;
+use socialvoid_types::{Cursor, DocumentId, Peer, Post, PostId};
+
+/// `timeline.retrieve_feed`: Returns a page of the timeline
+#[derive(Serialize, Debug)]
+pub struct RetrieveFeed {
+    pub page: Option<u32>,
+}
+
+impl Method for RetrieveFeed {
+    const NAME: &'static str = "timeline.retrieve_feed";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Vec<Post>;
+}
+
+/// `timeline.compose`: Composes a new post
+#[derive(Serialize, Debug)]
+pub struct Compose {
+    pub text: String,
+    /// IDs of the documents to attach
+    pub attachments: Vec<DocumentId>,
+}
+
+impl Method for Compose {
+    const NAME: &'static str = "timeline.compose";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Post;
+}
+
+/// `timeline.delete`: Deletes a post
+#[derive(Serialize, Debug)]
+pub struct Delete {
+    pub post: PostId,
+}
+
+impl Method for Delete {
+    const NAME: &'static str = "timeline.delete";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = bool;
+}
+
+/// `timeline.get_post`: Returns a post
+#[derive(Serialize, Debug)]
+pub struct GetPost {
+    pub post: PostId,
+}
+
+impl Method for GetPost {
+    const NAME: &'static str = "timeline.get_post";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Post;
+}
+
+/// `timeline.get_likes`: Returns a page of the peers that liked a post
+#[derive(Serialize, Debug)]
+pub struct GetLikes {
+    pub post: PostId,
+    pub page: Option<u32>,
+}
+
+impl Method for GetLikes {
+    const NAME: &'static str = "timeline.get_likes";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Vec<Peer>;
+}
+
+/// `timeline.get_replies`: Returns a page of the replies to a post
+#[derive(Serialize, Debug)]
+pub struct GetReplies {
+    pub post: PostId,
+    pub page: Option<u32>,
+}
+
+impl Method for GetReplies {
+    const NAME: &'static str = "timeline.get_replies";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Vec<Post>;
+}
+
+/// `timeline.get_quotes`: Returns a page of the quotes of a post
+#[derive(Serialize, Debug)]
+pub struct GetQuotes {
+    pub post: PostId,
+    pub page: Option<u32>,
+}
+
+impl Method for GetQuotes {
+    const NAME: &'static str = "timeline.get_quotes";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Vec<Post>;
+}
+
+/// `timeline.like`: Likes a post
+#[derive(Serialize, Debug)]
+pub struct Like {
+    pub post: PostId,
+}
+
+impl Method for Like {
+    const NAME: &'static str = "timeline.like";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = bool;
+}
+
+/// `timeline.unlike`: Unlikes a post
+#[derive(Serialize, Debug)]
+pub struct Unlike {
+    pub post: PostId,
+}
+
+impl Method for Unlike {
+    const NAME: &'static str = "timeline.unlike";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = bool;
+}
+
+/// `timeline.reply`: Replies to a post
+#[derive(Serialize, Debug)]
+pub struct Reply {
+    pub post: PostId,
+    pub text: String,
+    /// IDs of the documents to attach
+    pub attachments: Vec<DocumentId>,
+}
+
+impl Method for Reply {
+    const NAME: &'static str = "timeline.reply";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Post;
+}
+
+/// `timeline.quote`: Quotes a post
+#[derive(Serialize, Debug)]
+pub struct Quote {
+    pub post: PostId,
+    pub text: String,
+    /// IDs of the documents to attach
+    pub attachments: Vec<DocumentId>,
+}
+
+impl Method for Quote {
+    const NAME: &'static str = "timeline.quote";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Post;
+}
+
+/// `timeline.repost`: Reposts a post
+#[derive(Serialize, Debug)]
+pub struct Repost {
+    pub post: PostId,
+}
+
+impl Method for Repost {
+    const NAME: &'static str = "timeline.repost";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = bool;
+}
+
+/// `timeline.get_reposted_peers`: Returns a page of the peers that reposted a post
+#[derive(Serialize, Debug)]
+pub struct GetRepostedPeers {
+    pub post: PostId,
+    pub page: Option<u32>,
+}
+
+impl Method for GetRepostedPeers {
+    const NAME: &'static str = "timeline.get_reposted_peers";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = Vec<Peer>;
+}
+
+/// `timeline.retrieve_feed`: Returns the timeline starting at a cursor
+#[derive(Serialize, Debug)]
+pub struct RetrieveFeedPage {
+    /// `None` for the first page
+    pub cursor: Option<Cursor>,
+}
+
+impl Method for RetrieveFeedPage {
+    const NAME: &'static str = "timeline.retrieve_feed";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = PageResponse<Post>;
+}
+
+/// `timeline.get_likes`: Returns the peers that liked a post starting at a cursor
+#[derive(Serialize, Debug)]
+pub struct GetLikesPage {
+    pub post: PostId,
+    /// `None` for the first page
+    pub cursor: Option<Cursor>,
+}
+
+impl Method for GetLikesPage {
+    const NAME: &'static str = "timeline.get_likes";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = PageResponse<Peer>;
+}
+
+/// `timeline.get_replies`: Returns the replies to a post starting at a cursor
+#[derive(Serialize, Debug)]
+pub struct GetRepliesPage {
+    pub post: PostId,
+    /// `None` for the first page
+    pub cursor: Option<Cursor>,
+}
+
+impl Method for GetRepliesPage {
+    const NAME: &'static str = "timeline.get_replies";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = PageResponse<Post>;
+}
+
+/// `timeline.get_quotes`: Returns the quotes of a post starting at a cursor
+#[derive(Serialize, Debug)]
+pub struct GetQuotesPage {
+    pub post: PostId,
+    /// `None` for the first page
+    pub cursor: Option<Cursor>,
+}
+
+impl Method for GetQuotesPage {
+    const NAME: &'static str = "timeline.get_quotes";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = PageResponse<Post>;
+}
+
+/// `timeline.get_reposted_peers`: Returns the peers that reposted a post starting at a cursor
+#[derive(Serialize, Debug)]
+pub struct GetRepostedPeersPage {
+    pub post: PostId,
+    /// `None` for the first page
+    pub cursor: Option<Cursor>,
+}
+
+impl Method for GetRepostedPeersPage {
+    const NAME: &'static str = "timeline.get_reposted_peers";
+    const REQUIRES_AUTHENTICATION: bool = true;
+    type Response = PageResponse<Peer>;
+}
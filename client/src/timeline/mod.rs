@@ -1,226 +1,336 @@
+pub mod methods;
+mod thread;
+mod watcher;
+
+pub use thread::{Thread, ThreadLimits, ThreadNode};
+pub(crate) use watcher::delay;
+pub use watcher::{FeedEvent, FeedWatcher, FeedWatcherConfig};
+
+use crate::error::SocialvoidError;
+use crate::pagination::paginate;
 use crate::SVSessionMethods;
-use serde_json::json;
-use socialvoid_rawclient::Error;
+use futures::{pin_mut, Stream, TryStreamExt};
+use methods::*;
 use socialvoid_types::Peer;
 use socialvoid_types::Post;
+use socialvoid_types::{Cursor, DocumentId, Page, PostId};
+use thread::ThreadBuilder;
 
+use std::collections::{HashSet, VecDeque};
 use std::sync::Arc;
 
 /// TODO: write tests for this
-
 pub struct SVTimelineMethods {
-    client: Arc<socialvoid_rawclient::Client>,
     session: Arc<SVSessionMethods>,
 }
 
 impl SVTimelineMethods {
-    pub fn new(client: Arc<socialvoid_rawclient::Client>, session: Arc<SVSessionMethods>) -> Self {
-        Self { client, session }
+    pub fn new(session: Arc<SVSessionMethods>) -> Self {
+        Self { session }
     }
     /// Retrieve the posts from the users timeline
-    pub async fn retrieve_feed(&self, page: Option<u32>) -> Result<Vec<Post>, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "timeline.retrieve_feed",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "page": page,
-                }),
-            )
-            .await
+    pub async fn retrieve_feed(&self, page: Option<u32>) -> Result<Vec<Post>, SocialvoidError> {
+        self.session.invoke(RetrieveFeed { page }).await
     }
 
     /// Compose a new post to push to the timeline
-    pub async fn compose(&self, text: &str, attachments: Vec<String>) -> Result<Post, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "timeline.compose",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "text":text,
-                    "attachments":attachments,
-                }),
-            )
+    pub async fn compose(
+        &self,
+        text: &str,
+        attachments: Vec<DocumentId>,
+    ) -> Result<Post, SocialvoidError> {
+        self.session
+            .invoke(Compose {
+                text: text.to_string(),
+                attachments,
+            })
             .await
     }
 
     /// Delete a post from the timeline using it's ID
-    pub async fn delete(&self, post: String) -> Result<bool, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "timeline.delete",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "post":post,
-                }),
-            )
-            .await
+    pub async fn delete(&self, post: PostId) -> Result<bool, SocialvoidError> {
+        self.session.invoke(Delete { post }).await
     }
 
     /// Get post from the timeline using it's ID
-    pub async fn get_post(&self, post: String) -> Result<Post, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "timeline.get_post",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "post":post,
-                }),
-            )
-            .await
+    pub async fn get_post(&self, post: PostId) -> Result<Post, SocialvoidError> {
+        self.session.invoke(GetPost { post }).await
     }
 
     /// Get likes of a post
-    pub async fn get_likes(&self, post: String, page: Option<u32>) -> Result<Vec<Peer>, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "timeline.get_likes",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "post":post,
-                    "page":page,
-                }),
-            )
-            .await
+    pub async fn get_likes(
+        &self,
+        post: PostId,
+        page: Option<u32>,
+    ) -> Result<Vec<Peer>, SocialvoidError> {
+        self.session.invoke(GetLikes { post, page }).await
     }
 
     /// Get replies of a post
-    pub async fn get_replies(&self, post: String, page: Option<u32>) -> Result<Vec<Post>, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "timeline.get_replies",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "post":post,
-                    "page":page,
-                }),
-            )
-            .await
+    pub async fn get_replies(
+        &self,
+        post: PostId,
+        page: Option<u32>,
+    ) -> Result<Vec<Post>, SocialvoidError> {
+        self.session.invoke(GetReplies { post, page }).await
     }
 
     /// Get quotes of a post
-    pub async fn get_quotes(&self, post: String, page: Option<u32>) -> Result<Vec<Post>, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "timeline.get_quotes",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "post":post,
-                    "page":page,
-                }),
-            )
-            .await
+    pub async fn get_quotes(
+        &self,
+        post: PostId,
+        page: Option<u32>,
+    ) -> Result<Vec<Post>, SocialvoidError> {
+        self.session.invoke(GetQuotes { post, page }).await
     }
 
     /// Like a post.
-    pub async fn like(&self, post: String) -> Result<bool, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "timeline.like",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "post":post,
-                }),
-            )
-            .await
+    pub async fn like(&self, post: PostId) -> Result<bool, SocialvoidError> {
+        self.session.invoke(Like { post }).await
     }
 
     /// Unlike a post.
-    pub async fn unlike(&self, post: String) -> Result<bool, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "timeline.unlike",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "post":post,
-                }),
-            )
-            .await
+    pub async fn unlike(&self, post: PostId) -> Result<bool, SocialvoidError> {
+        self.session.invoke(Unlike { post }).await
     }
 
-    /// Compose a reply to a post.  
+    /// Compose a reply to a post and return it.  
     /// post: ID of the post to reply to.  
     /// text: The text contents of the post to compose  
     /// attachments: Vector of document IDs to send as attachment
     pub async fn reply(
         &self,
-        post: String,
+        post: PostId,
         text: String,
-        attachments: Vec<String>,
-    ) -> Result<bool, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "timeline.reply",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "post":post,
-                    "text":text,
-                    "attachments": attachments,
-                }),
-            )
+        attachments: Vec<DocumentId>,
+    ) -> Result<Post, SocialvoidError> {
+        self.session
+            .invoke(Reply {
+                post,
+                text,
+                attachments,
+            })
             .await
     }
 
-    /// Compose a new post by quoting an existing post.  
+    /// Compose a new post by quoting an existing post and return it.  
     /// post: ID of the post to quote.  
     /// text: The text contents of the post to compose  
     /// attachments: Vector of document IDs to send as attachment
     pub async fn quote(
         &self,
-        post: String,
+        post: PostId,
         text: String,
-        attachments: Vec<String>,
-    ) -> Result<bool, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "timeline.quote",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "post":post,
-                    "text":text,
-                    "attachments": attachments,
-                }),
-            )
+        attachments: Vec<DocumentId>,
+    ) -> Result<Post, SocialvoidError> {
+        self.session
+            .invoke(Quote {
+                post,
+                text,
+                attachments,
+            })
             .await
     }
 
     /// Repost a post.
-    pub async fn repost(&self, post: String) -> Result<bool, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "timeline.repost",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "post":post,
-                }),
-            )
-            .await
+    pub async fn repost(&self, post: PostId) -> Result<bool, SocialvoidError> {
+        self.session.invoke(Repost { post }).await
+    }
+
+    /// Get the peers that reposted a post
+    pub async fn get_reposted_peers(
+        &self,
+        post: PostId,
+        page: Option<u32>,
+    ) -> Result<Vec<Peer>, SocialvoidError> {
+        self.session.invoke(GetRepostedPeers { post, page }).await
+    }
+
+    /// Like `retrieve_feed` but requests the page starting at `cursor` (`None` for the first page).
+    /// The returned page carries the cursor of the next page. Fails with
+    /// `ClientError::CursorsNotSupported` if the server doesn't paginate with cursors.
+    pub async fn retrieve_feed_page(
+        &self,
+        cursor: Option<Cursor>,
+    ) -> Result<Page<Post>, SocialvoidError> {
+        self.session
+            .invoke(RetrieveFeedPage { cursor })
+            .await?
+            .into_page()
+    }
+
+    /// Like `get_likes` but requests the page starting at `cursor` (`None` for the first page).
+    /// The returned page carries the cursor of the next page. Fails with
+    /// `ClientError::CursorsNotSupported` if the server doesn't paginate with cursors.
+    pub async fn get_likes_page(
+        &self,
+        post: PostId,
+        cursor: Option<Cursor>,
+    ) -> Result<Page<Peer>, SocialvoidError> {
+        self.session
+            .invoke(GetLikesPage { post, cursor })
+            .await?
+            .into_page()
+    }
+
+    /// Like `get_replies` but requests the page starting at `cursor` (`None` for the first page).
+    /// The returned page carries the cursor of the next page. Fails with
+    /// `ClientError::CursorsNotSupported` if the server doesn't paginate with cursors.
+    pub async fn get_replies_page(
+        &self,
+        post: PostId,
+        cursor: Option<Cursor>,
+    ) -> Result<Page<Post>, SocialvoidError> {
+        self.session
+            .invoke(GetRepliesPage { post, cursor })
+            .await?
+            .into_page()
+    }
+
+    /// Like `get_quotes` but requests the page starting at `cursor` (`None` for the first page).
+    /// The returned page carries the cursor of the next page. Fails with
+    /// `ClientError::CursorsNotSupported` if the server doesn't paginate with cursors.
+    pub async fn get_quotes_page(
+        &self,
+        post: PostId,
+        cursor: Option<Cursor>,
+    ) -> Result<Page<Post>, SocialvoidError> {
+        self.session
+            .invoke(GetQuotesPage { post, cursor })
+            .await?
+            .into_page()
+    }
+
+    /// Like `get_reposted_peers` but requests the page starting at `cursor` (`None` for the first page).
+    /// The returned page carries the cursor of the next page. Fails with
+    /// `ClientError::CursorsNotSupported` if the server doesn't paginate with cursors.
+    pub async fn get_reposted_peers_page(
+        &self,
+        post: PostId,
+        cursor: Option<Cursor>,
+    ) -> Result<Page<Peer>, SocialvoidError> {
+        self.session
+            .invoke(GetRepostedPeersPage { post, cursor })
+            .await?
+            .into_page()
+    }
+
+    /// Streams every post of the timeline, requesting the pages of `retrieve_feed` as needed
+    pub fn feed(&self) -> impl Stream<Item = Result<Post, SocialvoidError>> + '_ {
+        paginate(self.session.prefetch_pages(), move |page| {
+            self.retrieve_feed(Some(page))
+        })
+    }
+
+    /// Streams every peer that liked a post, requesting the pages of `get_likes` as needed
+    pub fn likes(&self, post: PostId) -> impl Stream<Item = Result<Peer, SocialvoidError>> + '_ {
+        paginate(self.session.prefetch_pages(), move |page| {
+            self.get_likes(post.clone(), Some(page))
+        })
+    }
+
+    /// Streams every reply to a post, requesting the pages of `get_replies` as needed
+    pub fn replies(&self, post: PostId) -> impl Stream<Item = Result<Post, SocialvoidError>> + '_ {
+        paginate(self.session.prefetch_pages(), move |page| {
+            self.get_replies(post.clone(), Some(page))
+        })
+    }
+
+    /// Streams every quote of a post, requesting the pages of `get_quotes` as needed
+    pub fn quotes(&self, post: PostId) -> impl Stream<Item = Result<Post, SocialvoidError>> + '_ {
+        paginate(self.session.prefetch_pages(), move |page| {
+            self.get_quotes(post.clone(), Some(page))
+        })
     }
 
-    /// Get reposted peers
-    pub async fn get_reposted_peers(&self, post: String, page: Option<u32>) -> Result<bool, Error> {
-        let session_identification = self.session.session_identification()?;
-        self.client
-            .send_request(
-                "timeline.get_reposted_peers",
-                json!({
-                    "session_identification": serde_json::to_value(session_identification)?,
-                    "post":post,
-                    "page": page,
-                }),
-            )
+    /// Streams every peer that reposted a post, requesting the pages of `get_reposted_peers` as
+    /// needed
+    pub fn reposted_peers(
+        &self,
+        post: PostId,
+    ) -> impl Stream<Item = Result<Peer, SocialvoidError>> + '_ {
+        paginate(self.session.prefetch_pages(), move |page| {
+            self.get_reposted_peers(post.clone(), Some(page))
+        })
+    }
+
+    /// Retrieves the conversation around a post with the default `ThreadLimits`
+    pub async fn get_thread(&self, post: PostId) -> Result<Thread, SocialvoidError> {
+        self.get_thread_with_limits(post, ThreadLimits::default())
             .await
     }
+
+    /// Retrieves the conversation around a post: the posts it replies to, up to the original
+    /// thread post, and the replies to it and to its replies within `limits`
+    pub async fn get_thread_with_limits(
+        &self,
+        post: PostId,
+        limits: ThreadLimits,
+    ) -> Result<Thread, SocialvoidError> {
+        let root = self.get_post(post).await?;
+        let ancestors = self.get_ancestors(&root).await?;
+
+        let mut thread = ThreadBuilder::new(root);
+        let mut truncated = false;
+        // Breadth first, so the limits leave out the deepest replies
+        let mut queue = VecDeque::from(vec![(0, 0)]);
+        while let Some((index, depth)) = queue.pop_front() {
+            let post = thread.post(index);
+            if post.reply_count == Some(0) {
+                continue;
+            }
+            if depth == limits.max_depth {
+                truncated = true;
+                continue;
+            }
+            let replies = self.replies(post.id.clone());
+            pin_mut!(replies);
+            while let Some(reply) = replies.try_next().await? {
+                if thread.contains(&reply.id) {
+                    continue;
+                }
+                if thread.len() > limits.max_replies {
+                    truncated = true;
+                    break;
+                }
+                let reply = thread.add_reply(index, reply);
+                queue.push_back((reply, depth + 1));
+            }
+        }
+
+        Ok(Thread {
+            ancestors,
+            root: thread.build(),
+            truncated,
+        })
+    }
+
+    /// Retrieves the posts a post replies to, from the original thread post down
+    async fn get_ancestors(&self, post: &Post) -> Result<Vec<Post>, SocialvoidError> {
+        let original = post.original_thread_post.as_ref().map(|post| &post.id);
+        let mut ancestors = vec![];
+        let mut seen = HashSet::new();
+        seen.insert(post.id.clone());
+        let mut parent = post.reply_to_post.as_ref().map(|post| post.id.clone());
+        while let Some(id) = parent {
+            if !seen.insert(id.clone()) {
+                break;
+            }
+            let ancestor = match self.get_post(id.clone()).await {
+                Ok(ancestor) => ancestor,
+                Err(err) if err.is_not_found() => break,
+                Err(err) => return Err(err),
+            };
+            if ancestor.id != id && !seen.insert(ancestor.id.clone()) {
+                break;
+            }
+            parent = match original {
+                Some(original) if &ancestor.id == original => None,
+                _ => ancestor.reply_to_post.as_ref().map(|post| post.id.clone()),
+            };
+            ancestors.push(ancestor);
+        }
+        ancestors.reverse();
+        Ok(ancestors)
+    }
 }
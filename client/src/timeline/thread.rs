@@ -0,0 +1,292 @@
+use socialvoid_types::{Post, PostId};
+
+/// A conversation around a post, as returned by `timeline.get_thread`
+#[derive(Debug, Clone)]
+pub struct Thread {
+    /// The posts the post replies to, from the original thread post down to its direct parent.
+    /// If a post of the chain couldn't be retrieved (e.g. it was deleted), the chain starts at
+    /// the last post that could be.
+    pub ancestors: Vec<Post>,
+    /// The post and its replies
+    pub root: ThreadNode,
+    /// True if some replies may have been left out because of the `ThreadLimits`
+    pub truncated: bool,
+}
+
+/// A post of a thread with its replies
+#[derive(Debug, Clone)]
+pub struct ThreadNode {
+    pub post: Post,
+    pub replies: Vec<ThreadNode>,
+}
+
+/// Limits how many replies `timeline.get_thread_with_limits` retrieves
+#[derive(Debug, Clone, Copy)]
+pub struct ThreadLimits {
+    /// How many levels of replies are retrieved below the post, 0 for none
+    pub max_depth: usize,
+    /// The maximum number of replies retrieved in total
+    pub max_replies: usize,
+}
+
+impl Default for ThreadLimits {
+    fn default() -> Self {
+        ThreadLimits {
+            max_depth: 8,
+            max_replies: 200,
+        }
+    }
+}
+
+impl Thread {
+    /// The number of posts in the thread, ancestors included
+    pub fn post_count(&self) -> usize {
+        self.ancestors.len() + self.root.post_count()
+    }
+}
+
+impl ThreadNode {
+    /// The number of posts in this part of the thread
+    pub fn post_count(&self) -> usize {
+        1 + self
+            .replies
+            .iter()
+            .map(ThreadNode::post_count)
+            .sum::<usize>()
+    }
+
+    /// The deepest level of replies below this post
+    pub fn depth(&self) -> usize {
+        self.replies
+            .iter()
+            .map(|reply| reply.depth() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Finds a post in this part of the thread
+    pub fn find(&self, post: &PostId) -> Option<&ThreadNode> {
+        if &self.post.id == post {
+            return Some(self);
+        }
+        self.replies.iter().find_map(|reply| reply.find(post))
+    }
+}
+
+/// The posts of a thread while they are being retrieved.
+/// Posts are stored in the order they were retrieved, with the index of the post they reply to.
+pub(super) struct ThreadBuilder {
+    posts: Vec<(Post, Option<usize>)>,
+}
+
+impl ThreadBuilder {
+    pub(super) fn new(root: Post) -> Self {
+        ThreadBuilder {
+            posts: vec![(root, None)],
+        }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.posts.len()
+    }
+
+    pub(super) fn post(&self, index: usize) -> &Post {
+        &self.posts[index].0
+    }
+
+    pub(super) fn contains(&self, post: &PostId) -> bool {
+        self.posts.iter().any(|(existing, _)| &existing.id == post)
+    }
+
+    /// Adds a reply to the post at `parent` and returns its index
+    pub(super) fn add_reply(&mut self, parent: usize, reply: Post) -> usize {
+        self.posts.push((reply, Some(parent)));
+        self.posts.len() - 1
+    }
+
+    /// Assembles the tree, keeping the replies in the order they were added
+    pub(super) fn build(self) -> ThreadNode {
+        let mut children: Vec<Vec<usize>> = vec![vec![]; self.posts.len()];
+        for (index, (_, parent)) in self.posts.iter().enumerate() {
+            if let Some(parent) = parent {
+                children[*parent].push(index);
+            }
+        }
+        let mut posts: Vec<Option<Post>> =
+            self.posts.into_iter().map(|(post, _)| Some(post)).collect();
+        assemble(0, &children, &mut posts)
+    }
+}
+
+fn assemble(index: usize, children: &[Vec<usize>], posts: &mut [Option<Post>]) -> ThreadNode {
+    let post = posts[index].take().unwrap();
+    let replies = children[index]
+        .iter()
+        .map(|child| assemble(*child, children, posts))
+        .collect();
+    ThreadNode { post, replies }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::SocialvoidError;
+    use crate::mock::{MockConfig, MockServer};
+    use crate::Client;
+    use socialvoid_types::PostType;
+
+    fn post(id: &str) -> Post {
+        Post {
+            id: id.into(),
+            post_type: PostType::Post,
+            peer: None,
+            source: None,
+            text: Some(id.to_string()),
+            attachments: vec![],
+            entities: vec![],
+            mentioned_peers: vec![],
+            reply_to_post: None,
+            quoted_post: None,
+            reposted_post: None,
+            original_thread_post: None,
+            like_count: None,
+            repost_count: None,
+            quote_count: None,
+            reply_count: None,
+            posted_timestamp: 0,
+            flags: vec![],
+        }
+    }
+
+    #[test]
+    fn it_should_assemble_the_tree() {
+        let mut builder = ThreadBuilder::new(post("root"));
+        let a = builder.add_reply(0, post("a"));
+        let b = builder.add_reply(0, post("b"));
+        let a1 = builder.add_reply(a, post("a1"));
+        builder.add_reply(a1, post("a1x"));
+        builder.add_reply(b, post("b1"));
+        builder.add_reply(a, post("a2"));
+        assert!(builder.contains(&"a1x".into()));
+        assert!(!builder.contains(&"c".into()));
+
+        let root = builder.build();
+        assert_eq!(root.post.id, "root");
+        assert_eq!(root.post_count(), 7);
+        assert_eq!(root.depth(), 3);
+        let ids: Vec<_> = root
+            .replies
+            .iter()
+            .map(|reply| reply.post.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        let a = root.find(&"a".into()).unwrap();
+        let ids: Vec<_> = a
+            .replies
+            .iter()
+            .map(|reply| reply.post.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["a1", "a2"]);
+        assert_eq!(root.find(&"b1".into()).unwrap().depth(), 0);
+    }
+
+    /// The texts of the posts, with the replies of a post in parentheses
+    fn outline(node: &ThreadNode) -> String {
+        let text = node.post.text.clone().unwrap_or_default();
+        if node.replies.is_empty() {
+            return text;
+        }
+        let replies: Vec<_> = node.replies.iter().map(outline).collect();
+        format!("{}({})", text, replies.join(" "))
+    }
+
+    async fn sign_in(server: &MockServer, username: &str) -> Result<Client, SocialvoidError> {
+        server.add_user(username, "SuperStrongPassword", username);
+        let sv = server.client().await?;
+        sv.session.create().await?;
+        sv.session
+            .authenticate_user(
+                username.to_string(),
+                "SuperStrongPassword".to_string(),
+                None,
+            )
+            .await?;
+        Ok(sv)
+    }
+
+    async fn reply(sv: &Client, to: &PostId, text: &str) -> Result<PostId, SocialvoidError> {
+        let reply = sv.timeline.reply(to.clone(), text.to_string(), vec![]);
+        Ok(reply.await?.id)
+    }
+
+    #[tokio::test]
+    async fn it_should_retrieve_a_thread_within_the_limits() -> Result<(), SocialvoidError> {
+        // the replies to the root take two pages
+        let server = MockServer::start_with(MockConfig {
+            page_size: 2,
+            ..MockConfig::default()
+        })
+        .await?;
+        let (light, ryuk) = (
+            sign_in(&server, "light").await?,
+            sign_in(&server, "ryuk").await?,
+        );
+        let root = light.timeline.compose("r", vec![]).await?.id;
+        let a = reply(&ryuk, &root, "a").await?;
+        reply(&light, &root, "b").await?;
+        reply(&ryuk, &root, "c").await?;
+        let a1 = reply(&light, &a, "a1").await?;
+        reply(&ryuk, &a, "a2").await?;
+        let a1x = reply(&ryuk, &a1, "a1x").await?;
+
+        // the replies are in the order of `get_replies`, newest first
+        let thread = light.timeline.get_thread(root.clone()).await?;
+        assert_eq!(outline(&thread.root), "r(c b a(a2 a1(a1x)))");
+        assert!(thread.ancestors.is_empty());
+        assert_eq!(thread.root.depth(), 3);
+        assert_eq!(thread.post_count(), 7);
+        assert!(!thread.truncated);
+
+        let thread = light.timeline.get_thread(a1x).await?;
+        let ancestors: Vec<_> = thread
+            .ancestors
+            .iter()
+            .filter_map(|post| post.text.as_deref())
+            .collect();
+        assert_eq!(ancestors, ["r", "a", "a1"]);
+        assert_eq!(outline(&thread.root), "a1x");
+        assert_eq!(thread.post_count(), 4);
+
+        let limits = |max_depth, max_replies| ThreadLimits {
+            max_depth,
+            max_replies,
+        };
+        let thread = light
+            .timeline
+            .get_thread_with_limits(root.clone(), limits(1, 200))
+            .await?;
+        assert_eq!(outline(&thread.root), "r(c b a)");
+        assert!(thread.truncated);
+        let thread = light
+            .timeline
+            .get_thread_with_limits(root.clone(), limits(2, 200))
+            .await?;
+        assert_eq!(outline(&thread.root), "r(c b a(a2 a1))");
+        assert!(thread.truncated);
+        // the deepest replies are left out first
+        let thread = light
+            .timeline
+            .get_thread_with_limits(root.clone(), limits(8, 4))
+            .await?;
+        assert_eq!(outline(&thread.root), "r(c b a(a2))");
+        assert_eq!(thread.post_count(), 5);
+        assert!(thread.truncated);
+        let thread = light
+            .timeline
+            .get_thread_with_limits(root, limits(8, 6))
+            .await?;
+        assert_eq!(outline(&thread.root), "r(c b a(a2 a1(a1x)))");
+        assert!(!thread.truncated);
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+use super::SVTimelineMethods;
+use crate::error::SocialvoidError;
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use socialvoid_types::{Post, PostId, PostType};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A change of the timeline noticed by the `FeedWatcher`
+#[derive(Debug, Clone)]
+pub enum FeedEvent {
+    NewPost(Box<Post>),
+    /// A post that was seen by an earlier poll disappeared from the timeline or was deleted
+    Deleted(PostId),
+}
+
+/// Configures a `FeedWatcher`
+#[derive(Debug, Clone)]
+pub struct FeedWatcherConfig {
+    /// The time between two polls
+    pub interval: Duration,
+    /// After a failed poll the interval is doubled for every consecutive failure, up to this
+    pub max_backoff: Duration,
+    /// The maximum number of pages of the timeline requested per poll
+    pub max_pages: u32,
+    /// The file the last seen posts are saved to, so a restarted watcher doesn't yield the same
+    /// posts again. Nothing is saved if `None`.
+    pub state_file: Option<PathBuf>,
+}
+
+impl Default for FeedWatcherConfig {
+    fn default() -> Self {
+        FeedWatcherConfig {
+            interval: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(300),
+            max_pages: 3,
+            state_file: None,
+        }
+    }
+}
+
+/// Polls the timeline and streams the posts that weren't seen before.
+/// The first poll of a watcher without a saved state only records the current timeline, so
+/// only the posts published after the watcher was first started are yielded.
+pub struct FeedWatcher {
+    timeline: Arc<SVTimelineMethods>,
+    config: FeedWatcherConfig,
+    state: FeedState,
+}
+
+/// What the watcher knows about the timeline, saved to `FeedWatcherConfig::state_file`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FeedState {
+    /// The timestamp of the newest post seen, `None` before the first poll
+    newest_timestamp: Option<u64>,
+    /// The posts seen with `newest_timestamp`
+    newest_ids: HashSet<PostId>,
+    /// The posts seen by the last poll with their timestamp, to detect deletions
+    known: HashMap<PostId, u64>,
+}
+
+impl FeedWatcher {
+    /// Creates a watcher, loading the state saved by an earlier watcher if there is one
+    pub fn new(
+        timeline: Arc<SVTimelineMethods>,
+        config: FeedWatcherConfig,
+    ) -> Result<Self, SocialvoidError> {
+        let state = match &config.state_file {
+            Some(path) if path.exists() => serde_json::from_slice(&std::fs::read(path)?)?,
+            _ => FeedState::default(),
+        };
+        Ok(FeedWatcher {
+            timeline,
+            config,
+            state,
+        })
+    }
+
+    /// Polls the timeline forever, starting right away.
+    /// Errors are yielded as they happen and the watcher keeps polling with a backoff.
+    pub fn watch(self) -> impl Stream<Item = Result<FeedEvent, SocialvoidError>> {
+        stream::unfold(
+            (self, None),
+            |(mut watcher, failures): (FeedWatcher, Option<u32>)| async move {
+                if let Some(failures) = failures {
+                    tokio::time::sleep(delay(&watcher.config, failures)).await;
+                }
+                let (events, failures) = match watcher.poll().await {
+                    Ok(events) => (events.into_iter().map(Ok).collect(), 0),
+                    Err(err) => (vec![Err(err)], failures.unwrap_or(0) + 1),
+                };
+                Some((stream::iter(events), (watcher, Some(failures))))
+            },
+        )
+        .flatten()
+    }
+
+    /// Requests the timeline once and returns what changed since the last poll
+    pub async fn poll(&mut self) -> Result<Vec<FeedEvent>, SocialvoidError> {
+        let page_size = self
+            .timeline
+            .session
+            .page_size(|info| info.retrieve_feed_max_limit)
+            .await? as usize;
+        let mut posts = vec![];
+        for page in 1..=self.config.max_pages {
+            let feed = self.timeline.retrieve_feed(Some(page)).await?;
+            let last_page = feed.len() < page_size
+                || feed.iter().any(|post| {
+                    self.state
+                        .newest_timestamp
+                        .is_some_and(|newest| post.posted_timestamp < newest)
+                });
+            posts.extend(feed);
+            if last_page {
+                break;
+            }
+        }
+
+        let events = self.state.update(posts);
+        if let Some(path) = &self.config.state_file {
+            std::fs::write(path, serde_json::to_vec(&self.state)?)?;
+        }
+        Ok(events)
+    }
+}
+
+/// The time to wait before the next poll after `failures` consecutive failed polls
+pub(crate) fn delay(config: &FeedWatcherConfig, failures: u32) -> Duration {
+    if failures == 0 {
+        return config.interval;
+    }
+    config
+        .interval
+        .checked_mul(2u32.saturating_pow(failures.min(16)))
+        .map_or(config.max_backoff, |delay| delay.min(config.max_backoff))
+}
+
+impl FeedState {
+    /// Records the posts of a poll and returns the events, oldest post first
+    fn update(&mut self, posts: Vec<Post>) -> Vec<FeedEvent> {
+        let mut seen = HashSet::new();
+        let mut posts: Vec<Post> = posts
+            .into_iter()
+            .filter(|post| seen.insert(post.id.clone()))
+            .collect();
+        posts.sort_by(|a, b| (a.posted_timestamp, &a.id).cmp(&(b.posted_timestamp, &b.id)));
+
+        let mut events = vec![];
+        // Only the posts newer than the oldest one of this poll are expected to be in it
+        if let Some(oldest) = posts.first().map(|post| post.posted_timestamp) {
+            let mut deleted: Vec<(&PostId, &u64)> = self
+                .known
+                .iter()
+                .filter(|(id, timestamp)| {
+                    **timestamp > oldest
+                        && !posts.iter().any(|post| {
+                            &post.id == *id && !matches!(post.post_type, PostType::Deleted)
+                        })
+                })
+                .collect();
+            deleted.sort_by_key(|(_, timestamp)| **timestamp);
+            events.extend(
+                deleted
+                    .into_iter()
+                    .map(|(id, _)| FeedEvent::Deleted(id.clone())),
+            );
+        }
+
+        let first_poll = self.newest_timestamp.is_none();
+        let newest = self.newest_timestamp.unwrap_or(0);
+        self.known.clear();
+        for post in posts {
+            if matches!(post.post_type, PostType::Deleted) {
+                continue;
+            }
+            let timestamp = post.posted_timestamp;
+            self.known.insert(post.id.clone(), timestamp);
+            let is_new =
+                timestamp > newest || (timestamp == newest && !self.newest_ids.contains(&post.id));
+            if first_poll || !is_new {
+                continue;
+            }
+            events.push(FeedEvent::NewPost(Box::new(post)));
+        }
+
+        match self.known.values().copied().max() {
+            Some(timestamp) if timestamp > newest || first_poll => {
+                self.newest_timestamp = Some(timestamp);
+                self.newest_ids = self.ids_posted_at(timestamp);
+            }
+            Some(timestamp) if timestamp == newest => {
+                let ids = self.ids_posted_at(timestamp);
+                self.newest_ids.extend(ids);
+            }
+            _ if first_poll => self.newest_timestamp = Some(0),
+            _ => {}
+        }
+        events
+    }
+
+    fn ids_posted_at(&self, timestamp: u64) -> HashSet<PostId> {
+        self.known
+            .iter()
+            .filter(|(_, posted)| **posted == timestamp)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(id: &str, posted_timestamp: u64) -> Post {
+        Post {
+            id: id.into(),
+            post_type: PostType::Post,
+            peer: None,
+            source: None,
+            text: Some(id.to_string()),
+            attachments: vec![],
+            entities: vec![],
+            mentioned_peers: vec![],
+            reply_to_post: None,
+            quoted_post: None,
+            reposted_post: None,
+            original_thread_post: None,
+            like_count: None,
+            repost_count: None,
+            quote_count: None,
+            reply_count: None,
+            posted_timestamp,
+            flags: vec![],
+        }
+    }
+
+    fn new_posts(events: &[FeedEvent]) -> Vec<&str> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                FeedEvent::NewPost(post) => Some(post.id.as_str()),
+                FeedEvent::Deleted(_) => None,
+            })
+            .collect()
+    }
+
+    fn deleted_posts(events: &[FeedEvent]) -> Vec<&str> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                FeedEvent::Deleted(id) => Some(id.as_str()),
+                FeedEvent::NewPost(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_should_only_yield_new_posts() {
+        let mut state = FeedState::default();
+        assert!(state.update(vec![post("b", 2), post("a", 1)]).is_empty());
+
+        // pages overlap when the feed shifts
+        let events = state.update(vec![
+            post("d", 3),
+            post("c", 3),
+            post("b", 2),
+            post("b", 2),
+            post("a", 1),
+        ]);
+        assert_eq!(new_posts(&events), vec!["c", "d"]);
+
+        // a post with the same timestamp as the newest one seen
+        let events = state.update(vec![post("e", 3), post("d", 3), post("c", 3)]);
+        assert_eq!(new_posts(&events), vec!["e"]);
+        assert!(state.update(vec![post("e", 3), post("d", 3)]).is_empty());
+    }
+
+    #[test]
+    fn it_should_detect_deletions() {
+        let mut state = FeedState::default();
+        state.update(vec![post("c", 3), post("b", 2), post("a", 1)]);
+
+        let mut deleted = post("c", 3);
+        deleted.post_type = PostType::Deleted;
+        let events = state.update(vec![deleted, post("d", 4), post("a", 1)]);
+        assert_eq!(deleted_posts(&events), vec!["b", "c"]);
+        assert_eq!(new_posts(&events), vec!["d"]);
+
+        // posts older than the poll went out of the window, they weren't deleted
+        let events = state.update(vec![post("e", 5), post("d", 4)]);
+        assert_eq!(new_posts(&events), vec!["e"]);
+        assert!(deleted_posts(&events).is_empty());
+    }
+
+    #[test]
+    fn it_should_back_off_after_failures() {
+        let config = FeedWatcherConfig {
+            interval: Duration::from_secs(10),
+            max_backoff: Duration::from_secs(60),
+            ..FeedWatcherConfig::default()
+        };
+        assert_eq!(delay(&config, 0), Duration::from_secs(10));
+        assert_eq!(delay(&config, 1), Duration::from_secs(20));
+        assert_eq!(delay(&config, 2), Duration::from_secs(40));
+        assert_eq!(delay(&config, 3), Duration::from_secs(60));
+        assert_eq!(delay(&config, 100), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn it_should_not_replay_after_a_restart() -> Result<(), serde_json::Error> {
+        let mut state = FeedState::default();
+        state.update(vec![post("b", 2), post("a", 1)]);
+        let mut state: FeedState = serde_json::from_slice(&serde_json::to_vec(&state)?)?;
+
+        let events = state.update(vec![post("c", 3), post("b", 2), post("a", 1)]);
+        assert_eq!(new_posts(&events), vec!["c"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+//! Offline contract tests.
+//! Every wrapped method is called against a local stand-in server that answers with the recorded
+//! JSON result in `tests/fixtures/<method>.json`, checking that the result decodes into the
+//! return type declared by the method.
+
+use futures::TryStreamExt;
+use socialvoid::error::ClientError;
+use socialvoid::network::methods::GetMe;
+use socialvoid::notifications::{NotificationWatcher, NotificationWatcherConfig};
+use socialvoid::session::{ClientInfo, RegisterRequest, SessionHolder};
+use socialvoid::timeline::{FeedWatcher, FeedWatcherConfig};
+use socialvoid::SocialvoidError;
+use socialvoid_server::http::{self, Request, Response};
+use socialvoid_types::{DocumentId, PeerRef, PeerType, PostId, PostType, RelationshipType};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// Starts a JSON-RPC server answering every method with its fixture. The fixtures of the paginated
+/// methods are their first page, the next pages are empty.
+/// Returns the url of the server and the methods it was called with.
+async fn fixture_server() -> (String, Arc<Mutex<HashSet<String>>>) {
+    let (listener, url) = http::bind().await.unwrap();
+    let called = Arc::new(Mutex::new(HashSet::new()));
+    let methods = Arc::clone(&called);
+    tokio::spawn(http::serve(
+        listener,
+        usize::MAX,
+        move |request: Request| {
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let method = request["method"].as_str().unwrap().to_string();
+
+            let fixture = fixtures_dir().join(format!("{}.json", method));
+            let response = match std::fs::read_to_string(fixture) {
+                Ok(result) => {
+                    let mut result: serde_json::Value = serde_json::from_str(&result).unwrap();
+                    if result.is_array() && request["params"]["page"].as_u64() > Some(1) {
+                        result = serde_json::json!([]);
+                    }
+                    serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "result": result,
+                    })
+                }
+                Err(_) => serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": {"code": -32601, "message": format!("No fixture for {}", method)},
+                }),
+            };
+            methods.lock().unwrap().insert(method);
+            async move { Response::json(&response) }
+        },
+    ));
+    (url, called)
+}
+
+async fn client() -> (socialvoid::Client, Arc<Mutex<HashSet<String>>>) {
+    let (url, called) = fixture_server().await;
+    let sv = socialvoid::new(
+        SessionHolder::new(Arc::new(ClientInfo::generate())),
+        Some(url.clone()),
+        Some(url),
+    )
+    .await
+    .unwrap();
+    sv.session.create().await.unwrap();
+    sv.session
+        .authenticate_user("justanotherlight".to_string(), "password".to_string(), None)
+        .await
+        .unwrap();
+    (sv, called)
+}
+
+async fn check_help_methods(sv: &socialvoid::Client) -> Result<(), SocialvoidError> {
+    sv.help.get_community_guidelines().await?;
+    sv.help.get_privacy_policy().await?;
+    sv.help.get_terms_of_service().await?;
+    let server_info = sv.help.get_server_information().await?;
+    assert_eq!(server_info.retrieve_feed_max_limit, 100);
+    Ok(())
+}
+
+async fn check_session_methods(sv: &socialvoid::Client) -> Result<(), SocialvoidError> {
+    assert!(sv.session.get().await?.authenticated);
+    assert!(
+        sv.session
+            .authenticate_user("justanotherlight".to_string(), "password".to_string(), None)
+            .await?
+    );
+    assert!(sv.session.logout().await?);
+
+    let tos = sv.help.get_terms_of_service().await?;
+    sv.session.accept_terms_of_service(tos);
+    let peer = sv
+        .session
+        .register(RegisterRequest {
+            username: "justanotherlight".to_string(),
+            password: "SuperStrongPassword".to_string(),
+            first_name: "Light".to_string(),
+            last_name: None,
+        })
+        .await?;
+    assert_eq!(peer.username, "justanotherlight");
+    Ok(())
+}
+
+async fn check_network_methods(sv: &socialvoid::Client) -> Result<(), SocialvoidError> {
+    sv.network.get_me().await?;
+    let profile = sv.network.get_profile(None).await?;
+    assert_eq!(profile.display_picture_sizes.len(), 2);
+    let apple_bot = PeerRef::Username("apple_bot".to_string());
+    let peer = sv.network.resolve_peer(apple_bot.clone()).await?;
+    assert!(matches!(peer.peer_type, PeerType::BOT));
+    assert!(matches!(
+        sv.network.follow_peer(apple_bot.clone()).await?,
+        RelationshipType::Following
+    ));
+    assert!(matches!(
+        sv.network.unfollow_peer(apple_bot.clone()).await?,
+        RelationshipType::None
+    ));
+    assert_eq!(sv.network.get_followers(None, None).await?.len(), 1);
+    // the recorded server doesn't paginate with cursors, which must not pass for a last page
+    assert!(matches!(
+        sv.network.get_followers_page(None, None).await,
+        Err(SocialvoidError::Client(ClientError::CursorsNotSupported))
+    ));
+    assert_eq!(sv.network.get_following(None, Some(1)).await?.len(), 2);
+    Ok(())
+}
+
+async fn check_account_methods(sv: &socialvoid::Client) -> Result<(), SocialvoidError> {
+    let document_id = DocumentId::from("c8a0f1b9-05d2-4e8c-9d52-1a8e0b3b8a6f");
+    let sizes = sv.account.set_profile_picture(document_id.clone()).await?;
+    assert_eq!(sizes.len(), 2);
+    assert_eq!(sizes[0].document.id, document_id);
+    assert!(sv.account.delete_profile_picture().await?);
+    assert!(
+        sv.account
+            .update_profile_name("Light".to_string(), Some("Yagami".to_string()))
+            .await?
+    );
+    assert!(
+        sv.account
+            .update_profile_biography("Justice will prevail".to_string())
+            .await?
+    );
+    assert!(sv.account.clear_profile_biography().await?);
+    assert!(
+        sv.account
+            .update_profile_location("Tokyo".to_string())
+            .await?
+    );
+    assert!(sv.account.clear_profile_location().await?);
+    assert!(
+        sv.account
+            .update_profile_url("https://example.com".to_string())
+            .await?
+    );
+    assert!(sv.account.clear_profile_url().await?);
+    Ok(())
+}
+
+async fn check_timeline_methods(sv: &socialvoid::Client) -> Result<(), SocialvoidError> {
+    let post_id = PostId::from("0c0a1c0e-6bd1-4d3b-a5d6-6ea4f1dd0a2d");
+
+    let feed = sv.timeline.retrieve_feed(None).await?;
+    assert_eq!(feed.len(), 5);
+    // the first poll only records the timeline, which doesn't change afterwards
+    let mut watcher = FeedWatcher::new(Arc::clone(&sv.timeline), FeedWatcherConfig::default())?;
+    assert!(watcher.poll().await?.is_empty());
+    assert!(watcher.poll().await?.is_empty());
+    let mut notifications = NotificationWatcher::new(
+        Arc::clone(&sv.network),
+        Arc::clone(&sv.timeline),
+        NotificationWatcherConfig::default(),
+    )?;
+    assert!(notifications.poll().await?.is_empty());
+    assert!(notifications.poll().await?.is_empty());
+    // the second page is empty
+    let feed: Vec<_> = sv.timeline.feed().try_collect().await?;
+    assert_eq!(feed.len(), 5);
+    assert!(matches!(
+        sv.timeline.retrieve_feed_page(None).await,
+        Err(SocialvoidError::Client(ClientError::CursorsNotSupported))
+    ));
+    sv.timeline.compose("Hello, Socialvoid!", vec![]).await?;
+    assert!(sv.timeline.delete(post_id.clone()).await?);
+    sv.timeline.get_post(post_id.clone()).await?;
+    sv.timeline.get_likes(post_id.clone(), None).await?;
+    sv.timeline.get_replies(post_id.clone(), None).await?;
+    sv.timeline.get_quotes(post_id.clone(), None).await?;
+    assert!(sv.timeline.like(post_id.clone()).await?);
+    assert!(sv.timeline.unlike(post_id.clone()).await?);
+
+    let reply = sv
+        .timeline
+        .reply(post_id.clone(), "apples?".to_string(), vec![])
+        .await?;
+    assert!(matches!(reply.post_type, PostType::Reply));
+    assert_eq!(reply.reply_to_post.unwrap().id, post_id);
+
+    let quote = sv
+        .timeline
+        .quote(post_id.clone(), "Look at this".to_string(), vec![])
+        .await?;
+    assert!(matches!(quote.post_type, PostType::Quote));
+    assert_eq!(quote.quoted_post.unwrap().id, post_id);
+
+    // every post of the fixtures is the same reply, which must not loop
+    let thread = sv.timeline.get_thread(post_id.clone()).await?;
+    assert_eq!(thread.root.post.id, "a7b4d5c2-1e4f-4b8a-9c3d-2f1e0d9c8b7a");
+    assert_eq!(thread.post_count(), 1);
+
+    assert!(sv.timeline.repost(post_id.clone()).await?);
+    assert_eq!(
+        sv.timeline.get_reposted_peers(post_id, None).await?.len(),
+        2
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn help_methods_should_decode() -> Result<(), SocialvoidError> {
+    check_help_methods(&client().await.0).await
+}
+
+#[tokio::test]
+async fn session_methods_should_decode() -> Result<(), SocialvoidError> {
+    check_session_methods(&client().await.0).await
+}
+
+#[tokio::test]
+async fn network_methods_should_decode() -> Result<(), SocialvoidError> {
+    check_network_methods(&client().await.0).await
+}
+
+#[tokio::test]
+async fn account_methods_should_decode() -> Result<(), SocialvoidError> {
+    check_account_methods(&client().await.0).await
+}
+
+#[tokio::test]
+async fn timeline_methods_should_decode() -> Result<(), SocialvoidError> {
+    check_timeline_methods(&client().await.0).await
+}
+
+#[tokio::test]
+async fn invoke_should_decode_the_method_response() -> Result<(), SocialvoidError> {
+    let (sv, _) = client().await;
+    let peer = sv.invoke(GetMe).await?;
+    assert_eq!(peer.id, sv.network.get_me().await?.id);
+    Ok(())
+}
+
+#[tokio::test]
+async fn every_fixture_should_be_used() -> Result<(), SocialvoidError> {
+    let (sv, called) = client().await;
+    check_help_methods(&sv).await?;
+    check_network_methods(&sv).await?;
+    check_account_methods(&sv).await?;
+    check_timeline_methods(&sv).await?;
+    // last, as it logs the session out
+    check_session_methods(&sv).await?;
+
+    let fixtures: HashSet<String> = std::fs::read_dir(fixtures_dir())
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            path.file_stem().unwrap().to_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(*called.lock().unwrap(), fixtures);
+    Ok(())
+}
@@ -1,18 +1,38 @@
 use serde::{Deserialize, Serialize};
 
 use crate::utils::generate_id;
+use rand::RngCore;
+use std::sync::Mutex;
 
 type RawBatchRequest = Vec<RawRequest>;
 pub type BatchResponse = Vec<serde_json::Value>;
 
-/// Used to build a batch request. This object is created using the `batch_request` function of the crate
-pub struct BatchRequestBuilder {
+/// Used to build a batch request. This object is created using the `batch_request` function of the
+/// crate, or the `batch_request` method of a client to make the IDs of the requests with the
+/// generator of the client
+pub struct BatchRequestBuilder<'a> {
     requests: RawBatchRequest,
+    /// `None` for the generator of the thread
+    id_rng: Option<&'a Mutex<Box<dyn RngCore + Send>>>,
 }
 
-impl BatchRequestBuilder {
-    pub fn new() -> BatchRequestBuilder {
-        BatchRequestBuilder { requests: vec![] }
+impl BatchRequestBuilder<'static> {
+    pub fn new() -> BatchRequestBuilder<'static> {
+        BatchRequestBuilder {
+            requests: vec![],
+            id_rng: None,
+        }
+    }
+}
+
+impl<'a> BatchRequestBuilder<'a> {
+    pub(crate) fn with_id_rng(
+        id_rng: &'a Mutex<Box<dyn RngCore + Send>>,
+    ) -> BatchRequestBuilder<'a> {
+        BatchRequestBuilder {
+            requests: vec![],
+            id_rng: Some(id_rng),
+        }
     }
 
     /// Add a request to the batch request
@@ -20,12 +40,13 @@ impl BatchRequestBuilder {
         &mut self,
         method: &str,
         params: serde_json::Value,
-    ) -> &mut BatchRequestBuilder {
-        self.requests.push(RawRequest::new(
-            Some(generate_id()),
-            method.to_string(),
-            Some(params),
-        ));
+    ) -> &mut BatchRequestBuilder<'a> {
+        let id = match self.id_rng {
+            Some(id_rng) => generate_id(&mut **id_rng.lock().unwrap()),
+            None => generate_id(&mut rand::thread_rng()),
+        };
+        self.requests
+            .push(RawRequest::new(Some(id), method.to_string(), Some(params)));
         self
     }
 
@@ -34,7 +55,7 @@ impl BatchRequestBuilder {
         &mut self,
         method: &str,
         params: serde_json::Value,
-    ) -> &mut BatchRequestBuilder {
+    ) -> &mut BatchRequestBuilder<'a> {
         self.requests
             .push(RawRequest::new(None, method.to_string(), Some(params)));
         self
@@ -47,7 +68,7 @@ impl BatchRequestBuilder {
     }
 }
 
-impl Default for BatchRequestBuilder {
+impl Default for BatchRequestBuilder<'static> {
     fn default() -> Self {
         Self::new()
     }
@@ -85,4 +106,48 @@ pub struct RpcError {
     pub code: i32,
     pub message: Option<String>,
     pub data: Option<serde_json::Value>,
+    /// The underlying error if the error occurred on the client side
+    #[serde(skip)]
+    pub source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use serde_json::json;
+
+    fn batch_ids(seed: u64) -> Vec<Option<String>> {
+        let client =
+            crate::new("http://localhost/").with_id_rng(Box::new(StdRng::seed_from_u64(seed)));
+        let mut batch = client.batch_request();
+        batch
+            .add_request("help.get_server_information", json!({}))
+            .add_notification("session.logout", json!({}))
+            .add_request("help.get_terms_of_service", json!({}));
+        batch
+            .requests
+            .into_iter()
+            .map(|request| request.id)
+            .collect()
+    }
+
+    #[test]
+    fn it_should_generate_the_ids_with_the_generator_of_the_client() {
+        let ids = batch_ids(42);
+        assert_eq!(ids, batch_ids(42));
+        assert_ne!(ids, batch_ids(43));
+        assert!(ids[0].is_some() && ids[1].is_none() && ids[0] != ids[2]);
+    }
+
+    #[test]
+    fn it_should_still_build_a_batch_without_a_client() {
+        let mut batch = crate::batch_request();
+        batch
+            .add_request("help.get_server_information", json!({}))
+            .add_request("help.get_terms_of_service", json!({}));
+        let ids: Vec<_> = batch.requests.iter().map(|request| &request.id).collect();
+        assert!(ids[0].is_some() && ids[0] != ids[1]);
+        assert!(super::BatchRequestBuilder::default().requests.is_empty());
+    }
 }
@@ -1,16 +1,20 @@
 mod entities;
 mod utils;
 
-use entities::BatchRequestBuilder;
+pub use entities::BatchRequestBuilder;
 use entities::RawRequest;
 use entities::RawResponse;
 pub use entities::RpcError;
 
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::sync::Mutex;
 use utils::generate_id;
 
 pub struct Client {
     client: reqwest::Client,
     host_url: String,
+    id_rng: Mutex<Box<dyn RngCore + Send>>,
 }
 
 ///Creates a new client
@@ -19,23 +23,39 @@ pub fn new(host: &str) -> Client {
     Client {
         client,
         host_url: host.to_string(),
+        id_rng: Mutex::new(Box::new(StdRng::from_entropy())),
     }
 }
 
 /// Creates a new BatchRequestBuilder object which can be used to build a batch request which
 /// may contain both requests and notifications(i.e. requests without an id)
-pub fn batch_request() -> BatchRequestBuilder {
+pub fn batch_request() -> BatchRequestBuilder<'static> {
     BatchRequestBuilder::new()
 }
 
 impl Client {
+    /// Generates the request IDs with `rng`, e.g. a seeded one to reproduce the requests
+    pub fn with_id_rng(self, rng: Box<dyn RngCore + Send>) -> Client {
+        Client {
+            id_rng: Mutex::new(rng),
+            ..self
+        }
+    }
+
+    /// Like the `batch_request` function, with the IDs of the requests made by the generator of
+    /// the client
+    pub fn batch_request(&self) -> BatchRequestBuilder<'_> {
+        BatchRequestBuilder::with_id_rng(&self.id_rng)
+    }
+
     ///Send a request
     pub async fn send_request<T: serde::de::DeserializeOwned + std::fmt::Debug>(
         &self,
         method: &str,
         params: serde_json::Value,
     ) -> Result<T, RpcError> {
-        let request = RawRequest::new(Some(generate_id()), method.to_string(), Some(params));
+        let id = generate_id(&mut **self.id_rng.lock().unwrap());
+        let request = RawRequest::new(Some(id), method.to_string(), Some(params));
 
         println!(
             "Request: {}",
@@ -57,7 +77,7 @@ impl Client {
             method,
             serde_json::to_string_pretty(&resp).unwrap()
         );
-        let resp: RawResponse<T> = serde_json::value::from_value(resp).unwrap();
+        let resp: RawResponse<T> = serde_json::value::from_value(resp)?;
         resp.result()
     }
 
@@ -99,6 +119,7 @@ impl<T> RawResponse<T> {
                 self.id
             )),
             data: None,
+            source: None,
         })
     }
 }
@@ -115,13 +136,40 @@ impl RpcError {
     }
 }
 
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "JSON-RPC error {}: {}", self.code, self.message())
+    }
+}
+
+impl std::error::Error for RpcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|err| err as &(dyn std::error::Error + 'static))
+    }
+}
+
 impl std::convert::From<reqwest::Error> for RpcError {
     /// Convert a Reqwest error to an RPC Error with code -1
     fn from(error: reqwest::Error) -> Self {
         RpcError {
             code: -1,
-            message: Some(format!("Reqwest error: {:?}", error)),
+            message: Some(format!("Reqwest error: {}", error)),
+            data: None,
+            source: Some(Box::new(error)),
+        }
+    }
+}
+
+impl std::convert::From<serde_json::Error> for RpcError {
+    /// Convert a JSON error to an RPC Error with code -1
+    fn from(error: serde_json::Error) -> Self {
+        RpcError {
+            code: -1,
+            message: Some(format!("JSON error: {}", error)),
             data: None,
+            source: Some(Box::new(error)),
         }
     }
 }
@@ -1,10 +1,8 @@
 use rand::distributions::Alphanumeric;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
-pub fn generate_id() -> String {
-    thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(30)
-        .map(char::from)
+pub fn generate_id<R: Rng + ?Sized>(rng: &mut R) -> String {
+    (0..30)
+        .map(|_| char::from(rng.sample(Alphanumeric)))
         .collect()
 }
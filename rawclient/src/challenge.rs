@@ -1,3 +1,5 @@
+//! The answers to the session challenges, computed the same way by the clients and the servers.
+
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use crypto::digest::Digest;
 use crypto::hmac::Hmac;
@@ -6,25 +8,21 @@ use crypto::sha1;
 use pad::{Alignment, PadStr};
 
 use std::io::Cursor;
-use std::time::{SystemTime, UNIX_EPOCH};
 
-/// Returns the challenge_answer using the SessionEstablished object
-pub fn answer_challenge(client_private_hash: String, challenge: String) -> String {
+/// The number of seconds a challenge answer is valid for
+pub const TIME_STEP: u64 = 30;
+
+/// Returns the challenge_answer at the unix time `now`
+pub fn answer_challenge_at(client_private_hash: String, challenge: String, now: u64) -> String {
     let mut hasher = sha1::Sha1::new();
-    let totp_code = totp(challenge);
+    let totp_code = totp(challenge, now);
     //hashlib.sha1("{0}{1}".format(totp_code, client_private_hash).encode()).hexdigest()
     hasher.input(format!("{}{}", totp_code, client_private_hash).as_bytes());
     hasher.result_str()
 }
 
-fn totp(key: String) -> String {
-    let time_step = 30;
-    let now = SystemTime::now();
-    let counter = now
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs()
-        / time_step;
+fn totp(key: String, now: u64) -> String {
+    let counter = now / TIME_STEP;
     let digits = 6;
     hotp(key, counter, digits)
 }
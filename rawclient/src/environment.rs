@@ -0,0 +1,104 @@
+//! The clock and the random number generator of a client, or of a server.
+//! Both can be replaced, e.g. to reproduce the session and challenge flows exactly in tests:
+//! ```
+//! use rand::{rngs::StdRng, SeedableRng};
+//! use socialvoid_rawclient::environment::{Environment, ManualClock};
+//! use std::sync::Arc;
+//!
+//! let clock = Arc::new(ManualClock::new(1_600_000_000));
+//! let environment = Environment::new(Arc::clone(&clock), StdRng::seed_from_u64(42));
+//! clock.advance(30);
+//! ```
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub trait Clock: Send + Sync {
+    /// The number of seconds since the unix epoch
+    fn now(&self) -> u64;
+}
+
+/// The clock of the system
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs()
+    }
+}
+
+/// A clock that only moves when told to
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now: AtomicU64,
+}
+
+impl ManualClock {
+    pub fn new(now: u64) -> ManualClock {
+        ManualClock {
+            now: AtomicU64::new(now),
+        }
+    }
+
+    pub fn set(&self, now: u64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, seconds: u64) {
+        self.now.fetch_add(seconds, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+/// The clock and the random number generator a client is constructed with
+#[derive(Clone)]
+pub struct Environment {
+    pub clock: Arc<dyn Clock>,
+    pub rng: Arc<Mutex<dyn RngCore + Send>>,
+}
+
+impl Environment {
+    pub fn new<C: Clock + 'static, R: RngCore + Send + 'static>(
+        clock: Arc<C>,
+        rng: R,
+    ) -> Environment {
+        Environment {
+            clock,
+            rng: Arc::new(Mutex::new(rng)),
+        }
+    }
+
+    /// A random number generator seeded from this one, for the parts of the client that need
+    /// their own, like the JSON-RPC request IDs
+    pub fn fork_rng(&self) -> StdRng {
+        StdRng::from_rng(&mut *self.rng.lock().unwrap()).expect("Couldn't seed the generator")
+    }
+}
+
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Environment")
+            .field("now", &self.clock.now())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Environment {
+    /// The system clock and a generator seeded from the OS
+    fn default() -> Self {
+        Environment::new(Arc::new(SystemClock), StdRng::from_entropy())
+    }
+}
@@ -1,84 +1,210 @@
-enum_from_primitive! {
-    #[derive(Debug)]
-    pub enum RpcError {
-        ParseError = -32700,
-        InvalidRequest = -32600,
-        MethodNotFound = -32601,
-        InvalidParams = -32602,
-        InternalError = -32603,
-    }
+use super::types::ErrorCode;
+use super::types::ErrorKind;
+
+/// Generates the error code enums from the table below.
+/// For every namespace this generates an enum with its `RANGE`, the list of `ALL` its codes,
+/// conversions from and to codes and a human-readable `message` per code. It also generates the
+/// `ErrorNamespace` enum and the tests checking that every code round-trips.
+macro_rules! error_codes {
+    ($(
+        $(#[$meta:meta])*
+        $namespace:ident($display:literal, $start:literal..=$end:literal) => $name:ident {
+            $($variant:ident = $code:literal => $message:literal,)*
+        }
+    )*) => {
+        $(
+            $(#[$meta])*
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            pub enum $name {
+                $($variant = $code,)*
+            }
+
+            impl $name {
+                /// The codes reserved for this namespace
+                pub const RANGE: std::ops::RangeInclusive<ErrorCode> = $start..=$end;
+
+                /// Every known code of this namespace
+                pub const ALL: &'static [$name] = &[$($name::$variant,)*];
+
+                /// The numeric error code
+                pub fn code(&self) -> ErrorCode {
+                    *self as ErrorCode
+                }
+
+                /// Returns the error for a code, or `None` if the code isn't known
+                pub fn from_code(code: ErrorCode) -> Option<Self> {
+                    match code {
+                        $($code => Some($name::$variant),)*
+                        _ => None,
+                    }
+                }
+
+                /// A human-readable description of the error
+                pub fn message(&self) -> &'static str {
+                    match self {
+                        $($name::$variant => $message,)*
+                    }
+                }
+            }
+
+            impl std::fmt::Display for $name {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    write!(f, "{}", self.message())
+                }
+            }
+
+            impl std::error::Error for $name {}
+        )*
+
+        /// The namespace of an error code, as defined by the numeric ranges of the Socialvoid standard
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum ErrorNamespace {
+            $($namespace,)*
+            /// The code is outside every known range
+            Other,
+        }
+
+        impl ErrorNamespace {
+            /// Returns the namespace the error code belongs to
+            pub fn of(code: ErrorCode) -> Self {
+                $(
+                    if $name::RANGE.contains(&code) {
+                        return Self::$namespace;
+                    }
+                )*
+                Self::Other
+            }
+
+            /// The codes reserved for the namespace
+            pub fn range(&self) -> Option<std::ops::RangeInclusive<ErrorCode>> {
+                match self {
+                    $(Self::$namespace => Some($name::RANGE),)*
+                    Self::Other => None,
+                }
+            }
+        }
+
+        impl std::fmt::Display for ErrorNamespace {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $(Self::$namespace => write!(f, $display),)*
+                    Self::Other => write!(f, "other"),
+                }
+            }
+        }
+
+        /// Returns the kind of a known error code
+        pub(crate) fn kind_from_code(code: ErrorCode) -> Option<ErrorKind> {
+            match ErrorNamespace::of(code) {
+                $(ErrorNamespace::$namespace => $name::from_code(code).map(ErrorKind::$namespace),)*
+                ErrorNamespace::Other => None,
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            #[test]
+            fn every_code_should_round_trip() {
+                $(
+                    for error in $name::ALL {
+                        let code = error.code();
+                        assert!($name::RANGE.contains(&code), "{:?} is out of range", error);
+                        assert_eq!($name::from_code(code), Some(*error));
+                        assert_eq!(ErrorNamespace::of(code), ErrorNamespace::$namespace);
+                        match ErrorKind::from(code) {
+                            ErrorKind::$namespace(kind) => assert_eq!(kind, *error),
+                            kind => panic!("{} was parsed as {:?}", code, kind),
+                        }
+                        assert!(!error.message().is_empty());
+                    }
+                )*
+            }
+
+            #[test]
+            fn ranges_should_not_overlap() {
+                let ranges = [$($name::RANGE,)*];
+                for (i, a) in ranges.iter().enumerate() {
+                    for b in ranges.iter().skip(i + 1) {
+                        assert!(a.end() < b.start() || b.end() < a.start());
+                    }
+                }
+            }
+        }
+    };
 }
 
-enum_from_primitive! {
-    #[derive(Debug)]
-    pub enum ValidationError {
-        InvalidUsername = 8448,
-        InvalidPassword = 8449,
-        InvalidFirstName = 8450,
-        InvalidLastName = 8451,
-        InvalidBiography = 8452,
-        UsernameAlreadyExists = 8453,
-        InvalidPeerInput = 8454,
-        InvalidPostText = 8455,
-        InvalidClientPublicHash = 8456,
-        InvalidClientPrivateHash = 8457,
-        InvalidPlatform = 8458,
-        InvalidVersion = 8459,
-        InvalidClientName = 8460,
-        InvalidSessionIdentification = 8461,
-        InvalidFileForProfilePicture = 8462,
-        FileTooLarge = 8463,
-        InvalidHelpDocumentId = 8464,
-        AgreementRequired = 8465,
-        InvalidCursorValue = 8466,
-        InvalidGeoLocation = 8467,
-        InvalidUrlValue = 8468,
+error_codes! {
+    /// Errors defined by the JSON-RPC 2.0 specification
+    Rpc("JSON-RPC", -32768..=-32000) => RpcError {
+        ParseError = -32700 => "the server couldn't parse the request",
+        InvalidRequest = -32600 => "the request is not a valid JSON-RPC request",
+        MethodNotFound = -32601 => "the method doesn't exist on the server",
+        InvalidParams = -32602 => "invalid method parameters",
+        InternalError = -32603 => "internal JSON-RPC error",
     }
-}
 
-enum_from_primitive! {
-    #[derive(Debug)]
-    pub enum NetworkError {
-        PeerNotFound = 12544,
-        PostNotFound = 12545,
-        PostDeleted = 12546,
-        AlreadyReposted = 12547,
-        FileUploadError = 12548,
-        DocumentNotFound = 12549,
-        AccessDenied = 12550,
-        BlockedByPeer = 12551,
-        BlockedPeer = 12552,
-        SelfInteractionNotPermitted = 12553,
+    /// Errors raised when a parameter is invalid (0x2100 - 0x21FF)
+    Validation("validation", 8448..=8703) => ValidationError {
+        InvalidUsername = 8448 => "the username is invalid",
+        InvalidPassword = 8449 => "the password is invalid",
+        InvalidFirstName = 8450 => "the first name is invalid",
+        InvalidLastName = 8451 => "the last name is invalid",
+        InvalidBiography = 8452 => "the biography is invalid",
+        UsernameAlreadyExists = 8453 => "the username is already taken",
+        InvalidPeerInput = 8454 => "the peer is not a valid ID or @username",
+        InvalidPostText = 8455 => "the post text is invalid",
+        InvalidClientPublicHash = 8456 => "the client public hash is invalid",
+        InvalidClientPrivateHash = 8457 => "the client private hash is invalid",
+        InvalidPlatform = 8458 => "the client platform is invalid",
+        InvalidVersion = 8459 => "the client version is invalid",
+        InvalidClientName = 8460 => "the client name is invalid",
+        InvalidSessionIdentification = 8461 => "the session identification is invalid",
+        InvalidFileForProfilePicture = 8462 => "the file can't be used as a profile picture",
+        FileTooLarge = 8463 => "the file is too large",
+        InvalidHelpDocumentId = 8464 => "the help document ID is invalid",
+        AgreementRequired = 8465 => "the terms of service must be agreed to",
+        InvalidCursorValue = 8466 => "the cursor value is invalid",
+        InvalidGeoLocation = 8467 => "the location is invalid",
+        InvalidUrlValue = 8468 => "the URL is invalid",
     }
-}
 
-enum_from_primitive! {
-    #[derive(Debug)]
-    pub enum ServerError {
-        InternalServerError = 16384,
-        DocumentUpload = 16385
+    /// Errors related to sessions and authentication (0x2200 - 0x2313)
+    Authentication("authentication", 8704..=8979) => AuthenticationError {
+        IncorrectLoginCredentials = 8704 => "incorrect username or password",
+        IncorrectTwoFactorAuthenticationCode = 8705 => "incorrect two factor authentication code",
+        AuthenticationNotApplicable = 8706 => "authentication is not applicable to this account",
+        SessionNotFound = 8707 => "the session was not found",
+        NotAuthenticated = 8708 => "the session is not authenticated",
+        PrivateAccessTokenRequired = 8709 => "a private access token is required",
+        AuthenticationFailure = 8710 => "authentication failed",
+        BadSessionChallengeAnswer = 8711 => "the session challenge answer is incorrect",
+        TwoFactorAuthenticationRequired = 8712 => "two factor authentication is required",
+        AlreadyAuthenticated = 8713 => "the session is already authenticated",
+        SessionExpired = 8714 => "the session has expired",
     }
-}
 
-enum_from_primitive! {
-    #[derive(Debug)]
-    pub enum AuthenticationError {
-        IncorrectLoginCredentials = 8704,
-        IncorrectTwoFactorAuthenticationCode = 8705,
-        AuthenticationNotApplicable = 8706,
-        SessionNotFound = 8707,
-        NotAuthenticated = 8708,
-        PrivateAccessTokenRequired = 8709,
-        AuthenticationFailure = 8710,
-        BadSessionChallengeAnswer = 8711,
-        TwoFactorAuthenticationRequired = 8712,
-        AlreadyAuthenticated = 8713,
-        SessionExpired = 8714,
+    // 8980 - 12543 (0x2314 - 0x30FF) isn't reserved by the standard: these codes are
+    // `Unrecognized` errors of the `Other` namespace
+
+    /// Errors raised by the network, e.g. when a peer or post doesn't exist (0x3100 - 0x3FFF)
+    Network("network", 12544..=16383) => NetworkError {
+        PeerNotFound = 12544 => "the peer was not found",
+        PostNotFound = 12545 => "the post was not found",
+        PostDeleted = 12546 => "the post was deleted",
+        AlreadyReposted = 12547 => "the post was already reposted",
+        FileUploadError = 12548 => "the file couldn't be uploaded",
+        DocumentNotFound = 12549 => "the document was not found",
+        AccessDenied = 12550 => "access denied",
+        BlockedByPeer = 12551 => "you were blocked by the peer",
+        BlockedPeer = 12552 => "you blocked the peer",
+        SelfInteractionNotPermitted = 12553 => "you can't do this to yourself",
     }
-}
 
-#[derive(Debug)]
-pub enum ClientError {
-    TermsOfServiceNotAgreed,
-    SessionNotEstablished,
+    /// Errors raised when something went wrong on the server (0x4000 and above)
+    Server("server", 16384..=2147483647) => ServerError {
+        InternalServerError = 16384 => "internal server error",
+        DocumentUpload = 16385 => "the server couldn't process the uploaded document",
+    }
 }
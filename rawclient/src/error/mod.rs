@@ -4,13 +4,25 @@ pub mod types;
 use jsonrpc2_client::RpcError;
 use std::convert::From;
 
+pub use self::errors::ErrorNamespace;
 pub use self::types::Error;
 pub use self::types::ErrorKind;
 
 use crate::CdnResponse;
 
 impl From<RpcError> for Error {
-    fn from(error: RpcError) -> Self {
+    fn from(mut error: RpcError) -> Self {
+        // Errors raised on the client side keep the error they were caused by
+        if let Some(source) = error.source.take() {
+            let source = match source.downcast::<reqwest::Error>() {
+                Ok(source) => return Self::from(*source),
+                Err(source) => source,
+            };
+            match source.downcast::<serde_json::Error>() {
+                Ok(source) => return Self::from(*source),
+                Err(source) => error.source = Some(source),
+            }
+        }
         let code = error.code();
         let kind = ErrorKind::from(error.code());
         let description = error.message().to_string();
@@ -18,16 +30,18 @@ impl From<RpcError> for Error {
             code,
             kind,
             description,
+            data: error.data,
         }
     }
 }
 
 impl std::convert::From<serde_json::Error> for Error {
-    fn from(_error: serde_json::Error) -> Self {
+    fn from(error: serde_json::Error) -> Self {
         Self {
             code: -1, //TODO: maybe see standard error code for this?
-            kind: ErrorKind::JsonParsing,
-            description: String::from("JSON Parsing error"), //TODO: make more descriptive if possible
+            description: error.to_string(),
+            kind: ErrorKind::JsonParsing(error),
+            data: None,
         }
     }
 }
@@ -38,6 +52,7 @@ impl std::convert::From<reqwest::Error> for Error {
             code: -1, //TODO: maybe see standard error code for this?
             kind: ErrorKind::RequestError(error),
             description: String::from("Request error occurred"), //TODO: make more descriptive if possible
+            data: None,
         }
     }
 }
@@ -48,6 +63,7 @@ impl std::convert::From<std::io::Error> for Error {
             code: -1, //TODO: maybe see standard error code for this?
             kind: ErrorKind::IO(error),
             description: String::from("IO error occurred"), //TODO: make more descriptive if possible
+            data: None,
         }
     }
 }
@@ -60,12 +76,92 @@ impl<T> std::convert::TryFrom<&CdnResponse<T>> for Error {
                 code: 0,
                 kind: ErrorKind::Cdn("Unknown error. Success is true".to_string()),
                 description: String::from("Unknown CDN error"),
+                data: None,
             });
         }
         Ok(Self {
             code: resp.error_code.unwrap_or(0),
-            kind: ErrorKind::Cdn(resp.message.clone().unwrap_or_else(|| "".to_string())),
+            kind: ErrorKind::Cdn(resp.message.clone().unwrap_or_default()),
             description: String::from("CDN error occurred"), //TODO: make more descriptive if possible
+            data: None,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as StdError;
+
+    #[test]
+    fn it_should_display_the_kind_and_server_message() {
+        let error = Error::from(RpcError {
+            code: 8448,
+            message: Some("Bad username".to_string()),
+            data: None,
+            source: None,
+        });
+        assert_eq!(
+            error.to_string(),
+            "validation error: the username is invalid: Bad username"
+        );
+    }
+
+    #[test]
+    fn it_should_keep_the_underlying_error_as_source() {
+        let error = Error::from(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no such file",
+        ));
+        assert_eq!(error.to_string(), "I/O error");
+        assert_eq!(error.source().unwrap().to_string(), "no such file");
+
+        let json_error = serde_json::from_str::<u32>("nope").unwrap_err();
+        let error = Error::from(RpcError::from(json_error));
+        assert!(matches!(error.kind, ErrorKind::JsonParsing(_)));
+        assert!(error.source().unwrap().is::<serde_json::Error>());
+    }
+
+    #[test]
+    fn it_should_keep_unrecognized_codes_and_data() {
+        let error = Error::from(RpcError {
+            code: 8500,
+            message: Some("Something new".to_string()),
+            data: Some(serde_json::json!({"field": "username"})),
+            source: None,
+        });
+        match error.kind {
+            ErrorKind::Unrecognized { code, namespace } => {
+                assert_eq!(code, 8500);
+                assert_eq!(namespace, ErrorNamespace::Validation);
+            }
+            kind => panic!("Unexpected error kind: {:?}", kind),
+        }
+        assert_eq!(error.data, Some(serde_json::json!({"field": "username"})));
+        assert_eq!(ErrorNamespace::of(9000), ErrorNamespace::Other);
+        assert!(matches!(
+            ErrorKind::from(4),
+            ErrorKind::Unrecognized {
+                code: 4,
+                namespace: ErrorNamespace::Other
+            }
+        ));
+    }
+
+    #[test]
+    fn it_should_keep_the_server_codes_past_the_known_ones() {
+        for code in [24575, 24576, 30000, i32::MAX] {
+            match ErrorKind::from(code) {
+                ErrorKind::Unrecognized {
+                    code: parsed,
+                    namespace,
+                } => {
+                    assert_eq!(parsed, code);
+                    assert_eq!(namespace, ErrorNamespace::Server);
+                    assert_eq!(namespace.range().unwrap().end(), &i32::MAX);
+                }
+                kind => panic!("{} was parsed as {:?}", code, kind),
+            }
+        }
+    }
+}
@@ -1,11 +1,11 @@
 use super::errors::AuthenticationError;
-use super::errors::ClientError;
 use super::errors::NetworkError;
 use super::errors::RpcError;
 use super::errors::ServerError;
 use super::errors::ValidationError;
 
-use crate::enum_primitive::FromPrimitive;
+use super::errors::kind_from_code;
+use super::errors::ErrorNamespace;
 
 pub type ErrorCode = i32;
 
@@ -14,6 +14,8 @@ pub struct Error {
     pub kind: ErrorKind,
     pub code: ErrorCode,
     pub description: String,
+    /// The `data` field of the JSON-RPC error, if the server sent one
+    pub data: Option<serde_json::Value>,
 }
 
 #[derive(Debug)]
@@ -24,52 +26,80 @@ pub enum ErrorKind {
     Server(ServerError),
     Rpc(RpcError),
     Cdn(String),
-    JsonParsing,
+    JsonParsing(serde_json::Error),
     RequestError(reqwest::Error),
     IO(std::io::Error),
-    Client(ClientError),
+    /// An error code that this crate doesn't know about (yet)
+    Unrecognized {
+        code: ErrorCode,
+        namespace: ErrorNamespace,
+    },
     Unknown,
 }
 
-impl Error {
-    pub fn new_client_error(error_type: ClientError) -> Self {
-        Self {
-            kind: ErrorKind::Client(error_type),
-            code: -1,
-            description: String::from("There was an error on the client"), // TODO: maybe have description based on error type
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        // Only the server sends descriptions that add to the kind
+        let from_server = matches!(
+            self.kind,
+            ErrorKind::Validation(_)
+                | ErrorKind::Authentication(_)
+                | ErrorKind::Network(_)
+                | ErrorKind::Server(_)
+                | ErrorKind::Rpc(_)
+                | ErrorKind::Unrecognized { .. }
+                | ErrorKind::Unknown
+        );
+        if from_server && !self.description.is_empty() {
+            write!(f, ": {}", self.description)?;
         }
+        Ok(())
     }
 }
 
-impl From<ErrorCode> for ErrorKind {
-    fn from(code: ErrorCode) -> Self {
-        if (-32768..=-32000).contains(&code) {
-            match RpcError::from_i32(code) {
-                Some(kind) => Self::Rpc(kind),
-                None => Self::Unknown,
-            }
-        } else if (8448..=8703).contains(&code) {
-            match ValidationError::from_i32(code) {
-                Some(kind) => Self::Validation(kind),
-                None => Self::Unknown,
-            }
-        } else if (8704..=8979).contains(&code) {
-            match AuthenticationError::from_i32(code) {
-                Some(kind) => Self::Authentication(kind),
-                None => Self::Unknown,
-            }
-        } else if (12544..=16383).contains(&code) {
-            match NetworkError::from_i32(code) {
-                Some(kind) => Self::Network(kind),
-                None => Self::Unknown,
-            }
-        } else if (16384..).contains(&code) {
-            match ServerError::from_i32(code) {
-                Some(kind) => Self::Server(kind),
-                None => Self::Unknown,
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.kind.source()
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Validation(err) => write!(f, "validation error: {}", err),
+            ErrorKind::Authentication(err) => write!(f, "authentication error: {}", err),
+            ErrorKind::Network(err) => write!(f, "network error: {}", err),
+            ErrorKind::Server(err) => write!(f, "server error: {}", err),
+            ErrorKind::Rpc(err) => write!(f, "JSON-RPC error: {}", err),
+            ErrorKind::Cdn(message) => write!(f, "CDN error: {}", message),
+            ErrorKind::JsonParsing(_) => write!(f, "couldn't parse the JSON"),
+            ErrorKind::RequestError(_) => write!(f, "the request failed"),
+            ErrorKind::IO(_) => write!(f, "I/O error"),
+            ErrorKind::Unrecognized { code, namespace } => {
+                write!(f, "unrecognized {} error (code {})", namespace, code)
             }
-        } else {
-            Self::Unknown
+            ErrorKind::Unknown => write!(f, "unknown error"),
         }
     }
 }
+
+impl std::error::Error for ErrorKind {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ErrorKind::RequestError(err) => Some(err),
+            ErrorKind::IO(err) => Some(err),
+            ErrorKind::JsonParsing(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ErrorCode> for ErrorKind {
+    fn from(code: ErrorCode) -> Self {
+        kind_from_code(code).unwrap_or(Self::Unrecognized {
+            code,
+            namespace: ErrorNamespace::of(code),
+        })
+    }
+}
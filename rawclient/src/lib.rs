@@ -1,28 +1,36 @@
 /// # Raw client for SocialVoid.
 /// Makes a new client and makes JSONRPC requests. Also, useful in case we
 /// want to switch the JSONRPC client crate used in the future.
+pub mod challenge;
+pub mod environment;
 mod error;
 
-#[macro_use]
-extern crate enum_primitive;
 // use futures::stream::TryStreamExt;
+use futures::StreamExt;
 use std::convert::TryFrom;
 use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
 use tokio_util::codec::{BytesCodec, FramedRead};
 
 // use tokio::io::AsyncReadExt;
 
 pub use error::errors::AuthenticationError;
-pub use error::errors::ClientError;
+pub use error::errors::NetworkError;
+pub use error::errors::RpcError as RpcErrorCode;
+pub use error::errors::ServerError;
 pub use error::errors::ValidationError;
 pub use error::Error;
 pub use error::ErrorKind;
+pub use error::ErrorNamespace;
 
 use socialvoid_types::Document;
+use socialvoid_types::DocumentId;
 use socialvoid_types::SessionIdentification;
 
+use reqwest::header::{CONTENT_RANGE, RANGE};
 use reqwest::multipart::Part;
 use reqwest::Body;
+use reqwest::StatusCode;
 use serde::Deserialize;
 
 const HOST: &str = "http://socialvoid.qlg1.com:5601/";
@@ -41,11 +49,18 @@ pub fn new() -> Client {
 
 pub fn with_host(host: &str) -> Client {
     Client {
-        client: jsonrpc2_client::new(&host),
+        client: jsonrpc2_client::new(host),
     }
 }
 
 impl Client {
+    /// Generates the request IDs with `rng`, e.g. a seeded one to reproduce the requests
+    pub fn with_id_rng(self, rng: Box<dyn rand::RngCore + Send>) -> Client {
+        Client {
+            client: self.client.with_id_rng(rng),
+        }
+    }
+
     pub async fn send_request<T: serde::de::DeserializeOwned + std::fmt::Debug>(
         &self,
         method: &str,
@@ -94,7 +109,10 @@ impl CdnClient {
                 "client_public_hash",
                 session_identification.client_public_hash,
             )
-            .text("session_id", session_identification.session_id)
+            .text(
+                "session_id",
+                session_identification.session_id.into_string(),
+            )
             .text("challenge_answer", session_identification.challenge_answer)
             .text("action", "upload");
 
@@ -112,15 +130,18 @@ impl CdnClient {
     pub async fn download(
         &self,
         session_identification: SessionIdentification,
-        document_id: String,
+        document_id: DocumentId,
     ) -> Result<Vec<u8>, Error> {
         let form = reqwest::multipart::Form::new()
-            .text("document", document_id)
+            .text("document", document_id.into_string())
             .text(
                 "client_public_hash",
                 session_identification.client_public_hash, //remove the clonse
             )
-            .text("session_id", session_identification.session_id)
+            .text(
+                "session_id",
+                session_identification.session_id.into_string(),
+            )
             .text("challenge_answer", session_identification.challenge_answer)
             .text("action", "download");
 
@@ -130,11 +151,108 @@ impl CdnClient {
             .multipart(form)
             .send()
             .await?;
-        let content = response.text().await?;
-        Ok(content.as_bytes().to_vec())
+        let content = response.bytes().await?;
+        Ok(content.to_vec())
+    }
+
+    /// Downloads a document into `file_path`.
+    /// The content is first written to `<file_path>.part`. If a `.part` file already exists from
+    /// an earlier interrupted download, only the remaining bytes are requested using an HTTP Range
+    /// header. If the CDN doesn't honour the range, the document is downloaded again from the start.
+    /// The `.part` file is renamed to `file_path` once the download is complete.
+    pub async fn download_to_file(
+        &self,
+        session_identification: SessionIdentification,
+        document_id: DocumentId,
+        file_path: String,
+    ) -> Result<(), Error> {
+        let part_path = format!("{}.part", file_path);
+        let offset = match tokio::fs::metadata(&part_path).await {
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        };
+
+        let mut response = self
+            .send_download_request(&session_identification, &document_id, offset)
+            .await?;
+
+        let resume = offset > 0
+            && response.status() == StatusCode::PARTIAL_CONTENT
+            && content_range_start(&response) == Some(offset);
+        if offset > 0
+            && !resume
+            && matches!(
+                response.status(),
+                StatusCode::PARTIAL_CONTENT | StatusCode::RANGE_NOT_SATISFIABLE
+            )
+        {
+            // The range was rejected or doesn't start where we left off, start over
+            response = self
+                .send_download_request(&session_identification, &document_id, 0)
+                .await?;
+        }
+        if !response.status().is_success() {
+            return Err(Error {
+                kind: ErrorKind::Cdn(format!("Unexpected status: {}", response.status())),
+                code: response.status().as_u16() as i32,
+                description: String::from("CDN error occurred"),
+                data: None,
+            });
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resume)
+            .truncate(!resume)
+            .open(&part_path)
+            .await?;
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            file.write_all(&chunk?).await?;
+        }
+        file.flush().await?;
+        drop(file);
+
+        tokio::fs::rename(&part_path, &file_path).await?;
+        Ok(())
+    }
+
+    /// Sends the download request, asking for the bytes starting at `offset` if it is non zero
+    async fn send_download_request(
+        &self,
+        session_identification: &SessionIdentification,
+        document_id: &DocumentId,
+        offset: u64,
+    ) -> Result<reqwest::Response, Error> {
+        let form = reqwest::multipart::Form::new()
+            .text("document", document_id.to_string())
+            .text(
+                "client_public_hash",
+                session_identification.client_public_hash.clone(),
+            )
+            .text("session_id", session_identification.session_id.to_string())
+            .text(
+                "challenge_answer",
+                session_identification.challenge_answer.clone(),
+            )
+            .text("action", "download");
+
+        let mut request = self.client.post(&self.host_url).multipart(form);
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={}-", offset));
+        }
+        Ok(request.send().await?)
     }
 }
 
+/// Returns the first byte position of a `Content-Range: bytes <start>-<end>/<size>` header
+fn content_range_start(response: &reqwest::Response) -> Option<u64> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let range = value.trim().strip_prefix("bytes")?.trim_start();
+    range.split('-').next()?.trim().parse().ok()
+}
+
 impl Default for CdnClient {
     fn default() -> Self {
         Self::new()
@@ -161,6 +279,7 @@ impl<T> CdnResponse<T> {
                 kind: ErrorKind::Unknown,
                 code: -1,
                 description: "CDN Error: Success is true but no results found".to_string(),
+                data: None,
             }),
         }
     }
@@ -177,3 +296,135 @@ fn get_host() -> String {
 fn file_to_body(file: File) -> Body {
     Body::wrap_stream(FramedRead::new(file, BytesCodec::new()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use socialvoid_server::http::{self, Request, Response};
+    use std::sync::{Arc, Mutex};
+
+    const CONTENT: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+    /// How the stand-in CDN answers download requests
+    #[derive(Clone, Copy)]
+    enum Behaviour {
+        HonourRange,
+        IgnoreRange,
+        /// Announces the full length but hangs up after `n` bytes (ignores ranges)
+        DropAfter(usize),
+    }
+
+    /// Starts a local stand-in for the CDN and returns its url and the `Range` headers received
+    async fn stand_in_cdn(behaviour: Behaviour) -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+        let (listener, url) = http::bind().await.unwrap();
+        let ranges = Arc::new(Mutex::new(vec![]));
+        let received = Arc::clone(&ranges);
+        tokio::spawn(http::serve(
+            listener,
+            usize::MAX,
+            move |request: Request| {
+                let range = request.header("range").map(str::to_string);
+                received.lock().unwrap().push(range.clone());
+                let start = range
+                    .as_deref()
+                    .and_then(|range| range.strip_prefix("bytes="))
+                    .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok());
+                let response = match (behaviour, start) {
+                    (Behaviour::HonourRange, Some(start)) => {
+                        let mut response = Response::bytes(206, CONTENT[start..].to_vec());
+                        response.headers.push((
+                            "Content-Range".to_string(),
+                            format!("bytes {}-{}/{}", start, CONTENT.len() - 1, CONTENT.len()),
+                        ));
+                        response
+                    }
+                    (Behaviour::DropAfter(n), _) => {
+                        let mut response = Response::bytes(200, CONTENT[..n].to_vec());
+                        response
+                            .headers
+                            .push(("Content-Length".to_string(), CONTENT.len().to_string()));
+                        response.hang_up = true;
+                        response
+                    }
+                    _ => Response::bytes(200, CONTENT.to_vec()),
+                };
+                async move { response }
+            },
+        ));
+        (url, ranges)
+    }
+
+    fn session_identification() -> SessionIdentification {
+        SessionIdentification {
+            session_id: "session".into(),
+            client_public_hash: "public".to_string(),
+            challenge_answer: "answer".to_string(),
+        }
+    }
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "socialvoid-rawclient-{}-{}.test",
+            std::process::id(),
+            name
+        ));
+        path.to_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn it_should_resume_a_partial_download_when_range_is_honoured() {
+        let (url, ranges) = stand_in_cdn(Behaviour::HonourRange).await;
+        let cdn = CdnClient::with_cdn_url(url);
+        let path = temp_path("resume");
+        std::fs::write(format!("{}.part", path), &CONTENT[..10]).unwrap();
+
+        cdn.download_to_file(session_identification(), "doc".into(), path.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+        assert!(!std::path::Path::new(&format!("{}.part", path)).exists());
+        assert_eq!(*ranges.lock().unwrap(), vec![Some("bytes=10-".to_string())]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_should_download_again_when_range_is_ignored() {
+        let (url, ranges) = stand_in_cdn(Behaviour::IgnoreRange).await;
+        let cdn = CdnClient::with_cdn_url(url);
+        let path = temp_path("ignored");
+        std::fs::write(format!("{}.part", path), &CONTENT[..10]).unwrap();
+
+        cdn.download_to_file(session_identification(), "doc".into(), path.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+        assert_eq!(ranges.lock().unwrap().len(), 1);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_the_part_file_after_an_interrupted_download() {
+        let (url, _) = stand_in_cdn(Behaviour::DropAfter(20)).await;
+        let cdn = CdnClient::with_cdn_url(url);
+        let path = temp_path("interrupted");
+
+        assert!(cdn
+            .download_to_file(session_identification(), "doc".into(), path.clone())
+            .await
+            .is_err());
+        let part_path = format!("{}.part", path);
+        assert_eq!(std::fs::read(&part_path).unwrap(), &CONTENT[..20]);
+        assert!(!std::path::Path::new(&path).exists());
+
+        let (url, ranges) = stand_in_cdn(Behaviour::HonourRange).await;
+        let cdn = CdnClient::with_cdn_url(url);
+        cdn.download_to_file(session_identification(), "doc".into(), path.clone())
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+        assert_eq!(*ranges.lock().unwrap(), vec![Some("bytes=20-".to_string())]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,179 @@
+//! The CDN of the server, answering the `multipart/form-data` requests
+
+use crate::error::{Fault, Result};
+use crate::http::{self, Field, Request, Response};
+use crate::network::Network;
+use crate::storage::Storage;
+use serde_json::json;
+use socialvoid_rawclient::RpcErrorCode;
+use socialvoid_types::{DocumentId, SessionIdentification};
+
+/// The room left for the other fields when limiting the size of a request
+pub const FIELDS_SIZE: usize = 64 * 1024;
+
+pub fn handle<S: Storage>(
+    network: &mut Network<S>,
+    request: &Request,
+    content_type: &str,
+) -> Response {
+    let mut fields = match http::parse_multipart(content_type, &request.body) {
+        Some(fields) => fields,
+        None => return error(RpcErrorCode::ParseError.into()),
+    };
+    let mut text = |name: &str| {
+        fields
+            .remove(name)
+            .map(|field| String::from_utf8_lossy(&field.data).into_owned())
+    };
+    let (action, identification) = match (
+        text("action"),
+        text("session_id"),
+        text("client_public_hash"),
+        text("challenge_answer"),
+    ) {
+        (Some(action), Some(session_id), Some(client_public_hash), Some(challenge_answer)) => (
+            action,
+            SessionIdentification {
+                session_id: session_id.into(),
+                client_public_hash,
+                challenge_answer,
+            },
+        ),
+        _ => return error(RpcErrorCode::InvalidParams.into()),
+    };
+    let document = match fields.remove("document") {
+        Some(document) => document,
+        None => return error(RpcErrorCode::InvalidParams.into()),
+    };
+    match act(network, &action, &identification, document) {
+        Ok(Answer::Uploaded(document)) => {
+            Response::json(&json!({ "success": true, "results": document }))
+        }
+        Ok(Answer::Downloaded(mime, content)) => download(request.header("range"), &mime, content),
+        Err(fault) => error(fault),
+    }
+}
+
+enum Answer {
+    Uploaded(serde_json::Value),
+    Downloaded(String, Vec<u8>),
+}
+
+fn act<S: Storage>(
+    network: &mut Network<S>,
+    action: &str,
+    identification: &SessionIdentification,
+    document: Field,
+) -> Result<Answer> {
+    let me = network.authenticated(identification)?;
+    match action {
+        "upload" => {
+            let file_name = document
+                .file_name
+                .ok_or_else(|| Fault::from(RpcErrorCode::InvalidParams))?;
+            let document = network.upload(&me, &file_name, &document.data)?;
+            Ok(Answer::Uploaded(serde_json::to_value(document).unwrap()))
+        }
+        "download" => {
+            let id = DocumentId::from(String::from_utf8_lossy(&document.data).into_owned());
+            let mime = network.document(&id)?.file_mime;
+            Ok(Answer::Downloaded(mime, network.download(&id)?))
+        }
+        _ => Err(RpcErrorCode::InvalidParams.into()),
+    }
+}
+
+fn error(fault: Fault) -> Response {
+    let mut response = Response::json(&json!({
+        "success": false,
+        "error_code": fault.code,
+        "message": fault.message,
+    }));
+    response.status = 400;
+    response
+}
+
+/// Answers with the content, or the part of it starting at a `Range: bytes=N-`
+fn download(range: Option<&str>, mime: &str, content: Vec<u8>) -> Response {
+    let start = range
+        .and_then(|range| range.trim().strip_prefix("bytes="))
+        .and_then(|range| range.strip_suffix('-'))
+        .and_then(|start| start.trim().parse::<usize>().ok());
+    let (status, content_range, body) = match start {
+        None | Some(0) => (200, None, content),
+        Some(start) if start >= content.len() => {
+            (416, Some(format!("bytes */{}", content.len())), vec![])
+        }
+        Some(start) => (
+            206,
+            Some(format!(
+                "bytes {}-{}/{}",
+                start,
+                content.len() - 1,
+                content.len()
+            )),
+            content[start..].to_vec(),
+        ),
+    };
+    let mut response = Response::bytes(status, body);
+    response.headers = vec![("Content-Type".to_string(), mime.to_string())];
+    if let Some(content_range) = content_range {
+        response
+            .headers
+            .push(("Content-Range".to_string(), content_range));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Config;
+    use crate::storage::MemoryStorage;
+
+    #[test]
+    fn it_should_refuse_the_incomplete_requests() {
+        let mut network = Network::new(MemoryStorage::new(), Config::default());
+        let content_type = "multipart/form-data; boundary=XyZ";
+        let mut answer = |body: &[u8]| {
+            let request = Request {
+                body: body.to_vec(),
+                ..Request::default()
+            };
+            let response = handle(&mut network, &request, content_type);
+            let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+            (response.status, body["error_code"].as_i64())
+        };
+
+        assert_eq!(
+            answer(b"--XyZ\r\nnot a field"),
+            (400, Some(RpcErrorCode::ParseError.code() as i64))
+        );
+        // the session identification is missing
+        assert_eq!(
+            answer(b"--XyZ\r\nContent-Disposition: form-data; name=\"action\"\r\n\r\nupload\r\n--XyZ--\r\n"),
+            (400, Some(RpcErrorCode::InvalidParams.code() as i64))
+        );
+    }
+
+    #[test]
+    fn it_should_answer_with_the_requested_range() {
+        let part = |range: Option<&str>| download(range, "text/plain", b"hello, world".to_vec());
+
+        let full = part(None);
+        assert_eq!(
+            (full.status, full.body.as_slice()),
+            (200, &b"hello, world"[..])
+        );
+        let end = part(Some("bytes=7-"));
+        assert_eq!((end.status, end.body.as_slice()), (206, &b"world"[..]));
+        assert!(end
+            .headers
+            .contains(&("Content-Range".to_string(), "bytes 7-11/12".to_string())));
+        let past = part(Some("bytes=12-"));
+        assert_eq!(past.status, 416);
+        assert!(past
+            .headers
+            .contains(&("Content-Range".to_string(), "bytes */12".to_string())));
+    }
+}
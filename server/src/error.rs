@@ -0,0 +1,62 @@
+use socialvoid_rawclient::{
+    AuthenticationError, NetworkError, RpcErrorCode, ServerError, ValidationError,
+};
+
+/// An error returned to the client, with a code of the Socialvoid standard
+#[derive(Debug)]
+pub struct Fault {
+    pub code: i32,
+    pub message: String,
+}
+
+pub type Result<T> = std::result::Result<T, Fault>;
+
+macro_rules! fault_from {
+    ($($error:ty),*) => {
+        $(
+            impl From<$error> for Fault {
+                fn from(error: $error) -> Self {
+                    Fault {
+                        code: error.code(),
+                        message: error.message().to_string(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+fault_from!(
+    AuthenticationError,
+    NetworkError,
+    ValidationError,
+    ServerError,
+    RpcErrorCode
+);
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Fault {
+    fn from(error: rusqlite::Error) -> Self {
+        eprintln!("Database error: {}", error);
+        ServerError::InternalServerError.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_keep_the_code_and_the_message() {
+        let fault = Fault::from(ValidationError::FileTooLarge);
+        assert_eq!(fault.code, ValidationError::FileTooLarge.code());
+        assert_eq!(fault.message, ValidationError::FileTooLarge.message());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn it_should_hide_the_database_errors() {
+        let fault = Fault::from(rusqlite::Error::QueryReturnedNoRows);
+        assert_eq!(fault.code, ServerError::InternalServerError.code());
+    }
+}
@@ -0,0 +1,194 @@
+//! The HTTP of the servers: the network, and the test servers of the clients like the mock,
+//! the cassettes and the stand-ins of the contract tests
+
+use futures::stream::{self, StreamExt};
+use hyper::server::conn::AddrIncoming;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Server, StatusCode};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use tokio::net::TcpListener;
+
+/// The error stopping a server
+pub type Error = hyper::Error;
+
+/// A request, with its whole body
+#[derive(Debug, Clone, Default)]
+pub struct Request {
+    /// The header names are lowercase
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Hangs up after the body instead of ending the response, e.g. to interrupt a response
+    /// announcing a longer `Content-Length`
+    pub hang_up: bool,
+}
+
+impl Response {
+    pub fn json(body: &serde_json::Value) -> Self {
+        Response {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: serde_json::to_vec(body).unwrap(),
+            hang_up: false,
+        }
+    }
+
+    pub fn bytes(status: u16, body: Vec<u8>) -> Self {
+        Response {
+            status,
+            headers: vec![(
+                "Content-Type".to_string(),
+                "application/octet-stream".to_string(),
+            )],
+            body,
+            hang_up: false,
+        }
+    }
+}
+
+/// Binds a local port, returning the listener and its URL
+pub async fn bind() -> io::Result<(TcpListener, String)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/", listener.local_addr()?);
+    Ok((listener, url))
+}
+
+/// Answers the requests with `respond`. Requests with a body larger than `max_body_size` are
+/// answered with `413 Payload Too Large` without calling it.
+pub async fn serve<F, Fut>(
+    listener: TcpListener,
+    max_body_size: usize,
+    respond: F,
+) -> Result<(), Error>
+where
+    F: Fn(Request) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Response> + Send + 'static,
+{
+    let make_service = make_service_fn(move |_| {
+        let respond = respond.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let respond = respond.clone();
+                async move {
+                    let response = match read_request(request, max_body_size).await {
+                        Some(request) => respond(request).await,
+                        None => Response::bytes(413, vec![]),
+                    };
+                    Ok::<_, Infallible>(into_hyper(response))
+                }
+            }))
+        }
+    });
+    Server::builder(AddrIncoming::from_listener(listener)?)
+        .serve(make_service)
+        .await
+}
+
+/// Reads the whole request, or `None` if its body is larger than `max_body_size`
+async fn read_request(request: hyper::Request<Body>, max_body_size: usize) -> Option<Request> {
+    let headers = request
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| {
+            Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+        })
+        .collect();
+    let mut chunks = request.into_body();
+    let mut body = vec![];
+    while let Some(chunk) = chunks.next().await {
+        body.extend_from_slice(&chunk.ok()?);
+        if body.len() > max_body_size {
+            return None;
+        }
+    }
+    Some(Request { headers, body })
+}
+
+fn into_hyper(response: Response) -> hyper::Response<Body> {
+    let mut builder = hyper::Response::builder()
+        .status(StatusCode::from_u16(response.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR));
+    for (name, value) in &response.headers {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    let body = if response.hang_up {
+        // lets the client read the body before the error closes the connection
+        let aborted = async {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            Err(io::Error::from(io::ErrorKind::ConnectionAborted))
+        };
+        Body::wrap_stream(stream::once(async { Ok(response.body) }).chain(stream::once(aborted)))
+    } else {
+        Body::from(response.body)
+    };
+    builder.body(body).expect("the headers are valid")
+}
+
+/// A field of a `multipart/form-data` body
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub file_name: Option<String>,
+    pub data: Vec<u8>,
+}
+
+/// Parses a `multipart/form-data` body into its fields by name
+pub fn parse_multipart(content_type: &str, body: &[u8]) -> Option<HashMap<String, Field>> {
+    let boundary = multer::parse_boundary(content_type).ok()?;
+    let body = stream::once(async move { Ok::<_, Infallible>(body.to_vec()) });
+    let mut multipart = multer::Multipart::new(body, boundary);
+    // the body is already in memory, so the fields are read without waiting
+    futures::executor::block_on(async {
+        let mut fields = HashMap::new();
+        while let Some(field) = multipart.next_field().await.ok()? {
+            let name = field.name().unwrap_or_default().to_string();
+            let file_name = field.file_name().map(str::to_string);
+            let data = field.bytes().await.ok()?.to_vec();
+            fields.insert(name, Field { file_name, data });
+        }
+        Some(fields)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_parse_multipart_fields() {
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"action\"\r\n\r\nupload\r\n--XyZ\r\nContent-Disposition: form-data; name=\"document\"; filename=\"a.txt\"\r\nContent-Type: text/plain\r\n\r\nline 1\r\nline 2\r\n--XyZ--\r\n";
+        let fields = parse_multipart("multipart/form-data; boundary=XyZ", body).unwrap();
+        assert_eq!(fields["action"].data, b"upload");
+        assert_eq!(fields["action"].file_name, None);
+        assert_eq!(fields["document"].data, b"line 1\r\nline 2");
+        assert_eq!(fields["document"].file_name.as_deref(), Some("a.txt"));
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_the_bodies_too_large() -> io::Result<()> {
+        let (listener, url) = bind().await?;
+        tokio::spawn(serve(listener, 4, |request: Request| async move {
+            Response::bytes(200, request.body)
+        }));
+        let client = reqwest::Client::new();
+        let echo = client.post(&url).body("1234").send().await.unwrap();
+        assert_eq!(echo.status().as_u16(), 200);
+        assert_eq!(&echo.bytes().await.unwrap()[..], b"1234");
+        let large = client.post(&url).body("12345").send().await.unwrap();
+        assert_eq!(large.status().as_u16(), 413);
+        Ok(())
+    }
+}
@@ -0,0 +1,387 @@
+//! A Socialvoid server, for staging and for developing and testing the clients without the
+//! public network.
+//!
+//! The rules of the network are in `Network`, which keeps its records in a `Storage`: in memory,
+//! or in a SQLite database with the `sqlite` feature. `serve` answers both the JSON-RPC requests
+//! and the CDN requests on the same URL.
+
+mod cdn;
+pub mod error;
+pub mod http;
+mod network;
+mod rpc;
+pub mod storage;
+
+pub use network::{Config, Network, ProfileField};
+pub use rpc::TERMS_OF_SERVICE_ID;
+
+use http::{Request, Response};
+use std::sync::{Arc, Mutex};
+use storage::Storage;
+use tokio::net::TcpListener;
+
+/// A network shared by the requests
+pub type Shared<S> = Arc<Mutex<Network<S>>>;
+
+/// Answers a JSON-RPC request, or a CDN request if it's `multipart/form-data`
+pub fn respond<S: Storage>(network: &mut Network<S>, request: &Request) -> Response {
+    match request.header("content-type") {
+        Some(content_type) if content_type.starts_with("multipart/form-data") => {
+            cdn::handle(network, request, content_type)
+        }
+        _ => Response::json(&rpc::handle(network, &request.body)),
+    }
+}
+
+/// Serves the JSON-RPC methods and the CDN of the network, off the async threads
+pub async fn serve<S: Storage + 'static>(
+    listener: TcpListener,
+    network: Shared<S>,
+) -> Result<(), http::Error> {
+    let max_body_size =
+        network.lock().unwrap().config().upload_max_file_size as usize + cdn::FIELDS_SIZE;
+    http::serve(listener, max_body_size, move |request| {
+        let network = Arc::clone(&network);
+        async move {
+            tokio::task::spawn_blocking(move || respond(&mut network.lock().unwrap(), &request))
+                .await
+                .expect("the network doesn't panic")
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use socialvoid::environment::{Clock, Environment, ManualClock, SystemClock};
+    use socialvoid::error::{AuthenticationError, ErrorKind, ValidationError};
+    use socialvoid::session::{ClientInfo, RegisterRequest, SessionHolder};
+    use socialvoid::{Client, SocialvoidError};
+    use socialvoid_rawclient::challenge::TIME_STEP;
+    use socialvoid_types::SessionIdentification;
+    use storage::MemoryStorage;
+
+    /// Starts a server on a local port, returning its URL
+    async fn start<S: Storage + 'static>(network: Network<S>) -> String {
+        let (listener, url) = http::bind().await.unwrap();
+        tokio::spawn(serve(listener, Arc::new(Mutex::new(network))));
+        url
+    }
+
+    /// Starts a server in memory whose sessions follow `clock`
+    async fn start_at(clock: &Arc<ManualClock>, config: Config) -> String {
+        let environment = Environment::new(Arc::clone(clock), rand::rngs::OsRng);
+        start(Network::with_environment(
+            MemoryStorage::new(),
+            config,
+            environment,
+        ))
+        .await
+    }
+
+    async fn client(url: &str) -> Result<Client, SocialvoidError> {
+        socialvoid::new(
+            SessionHolder::new(Arc::new(ClientInfo::generate())),
+            Some(url.to_string()),
+            Some(url.to_string()),
+        )
+        .await
+    }
+
+    /// A client answering the challenges at the time of `clock`
+    async fn client_at(url: &str, clock: &Arc<ManualClock>) -> Result<Client, SocialvoidError> {
+        let environment = Environment::new(Arc::clone(clock), rand::rngs::OsRng);
+        socialvoid::new_with_environment(
+            SessionHolder::new(Arc::new(ClientInfo::generate_with(&environment))),
+            Some(url.to_string()),
+            Some(url.to_string()),
+            environment,
+        )
+        .await
+    }
+
+    /// Sends a JSON-RPC request on a session, bypassing the checks of the client.
+    /// Returns the code of the error, if any.
+    async fn call(url: &str, method: &str, identification: &SessionIdentification) -> Option<i32> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": { "session_identification": identification },
+        });
+        let response: Value = reqwest::Client::new()
+            .post(url)
+            .json(&request)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        response["error"]["code"].as_i64().map(|code| code as i32)
+    }
+
+    /// Sends a CDN request on a session, returning the response
+    async fn cdn(
+        url: &str,
+        identification: &SessionIdentification,
+        action: &str,
+        document: reqwest::multipart::Part,
+        range: Option<&str>,
+    ) -> reqwest::Response {
+        let form = reqwest::multipart::Form::new()
+            .text("action", action.to_string())
+            .text("session_id", identification.session_id.to_string())
+            .text(
+                "client_public_hash",
+                identification.client_public_hash.clone(),
+            )
+            .text("challenge_answer", identification.challenge_answer.clone())
+            .part("document", document);
+        let mut request = reqwest::Client::new().post(url).multipart(form);
+        if let Some(range) = range {
+            request = request.header("Range", range);
+        }
+        request.send().await.unwrap()
+    }
+
+    fn kind<T>(result: Result<T, SocialvoidError>) -> Option<ErrorKind> {
+        match result {
+            Err(SocialvoidError::RawClient(error)) => Some(error.kind),
+            _ => None,
+        }
+    }
+
+    async fn register(url: &str, username: &str) -> Result<Client, SocialvoidError> {
+        register_with(client(url).await?, username).await
+    }
+
+    async fn register_with(sv: Client, username: &str) -> Result<Client, SocialvoidError> {
+        sv.session.create().await?;
+        sv.session
+            .accept_terms_of_service(sv.help.get_terms_of_service().await?);
+        sv.session
+            .register(RegisterRequest {
+                username: username.to_string(),
+                password: "SuperStrongPassword".to_string(),
+                first_name: "Light".to_string(),
+                last_name: None,
+            })
+            .await?;
+        sv.session
+            .authenticate_user(
+                username.to_string(),
+                "SuperStrongPassword".to_string(),
+                None,
+            )
+            .await?;
+        Ok(sv)
+    }
+
+    #[tokio::test]
+    async fn it_should_serve_the_client() -> Result<(), SocialvoidError> {
+        let url = start(Network::new(MemoryStorage::new(), Config::default())).await;
+        let sv = register(&url, "light").await?;
+        assert_eq!(sv.network.get_me().await?.username, "light");
+
+        let file =
+            std::env::temp_dir().join(format!("socialvoid-server-{}.txt", std::process::id()));
+        std::fs::write(&file, b"hello, world").unwrap();
+        let document = sv.session.upload_file(file.to_str().unwrap()).await;
+        std::fs::remove_file(&file).unwrap();
+        let document = document?;
+        let post = sv
+            .timeline
+            .compose("hello", vec![document.id.clone()])
+            .await?;
+        assert_eq!(post.attachments[0].file_name, document.file_name);
+        assert_eq!(
+            sv.session.download_file(document.id).await?,
+            b"hello, world"
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn it_should_keep_the_network_in_the_database() -> Result<(), SocialvoidError> {
+        let path =
+            std::env::temp_dir().join(format!("socialvoid-server-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let sqlite = || storage::SqliteStorage::open(&path).unwrap();
+        let url = start(Network::new(sqlite(), Config::default())).await;
+        register(&url, "ryuk").await?.session.logout().await?;
+
+        let url = start(Network::new(sqlite(), Config::default())).await;
+        let sv = client(&url).await?;
+        sv.session.create().await?;
+        sv.session
+            .authenticate_user("ryuk".to_string(), "SuperStrongPassword".to_string(), None)
+            .await?;
+        assert_eq!(sv.network.get_me().await?.username, "ryuk");
+        std::fs::remove_file(&path).unwrap();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_expire_the_sessions() -> Result<(), SocialvoidError> {
+        let clock = Arc::new(ManualClock::new(SystemClock.now()));
+        let config = Config {
+            unauthorized_session_ttl: 60,
+            authorized_session_ttl: 120,
+            ..Config::default()
+        };
+        let url = start_at(&clock, config).await;
+        let expired = |result| {
+            matches!(
+                kind(result),
+                Some(ErrorKind::Authentication(
+                    AuthenticationError::SessionExpired
+                ))
+            )
+        };
+
+        let sv = client_at(&url, &clock).await?;
+        sv.session.create().await?;
+        clock.advance(59);
+        sv.session.get().await?;
+        clock.advance(1);
+        assert!(expired(sv.session.get().await.map(|_| ())));
+
+        // authenticating extends the session
+        let sv = register_with(client_at(&url, &clock).await?, "light").await?;
+        clock.advance(119);
+        sv.network.get_me().await?;
+        clock.advance(1);
+        assert!(expired(sv.network.get_me().await.map(|_| ())));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_the_wrong_answers() -> Result<(), SocialvoidError> {
+        let clock = Arc::new(ManualClock::new(SystemClock.now()));
+        let url = start_at(&clock, Config::default()).await;
+        let sv = client_at(&url, &clock).await?;
+        sv.session.create().await?;
+        let identification = sv.session.session_identification()?;
+        let bad_answer = Some(AuthenticationError::BadSessionChallengeAnswer.code());
+
+        assert_eq!(call(&url, "session.get", &identification).await, None);
+        // the answer of the previous time step is still accepted, not the one before
+        clock.advance(TIME_STEP);
+        assert_eq!(call(&url, "session.get", &identification).await, None);
+        clock.advance(TIME_STEP);
+        assert_eq!(call(&url, "session.get", &identification).await, bad_answer);
+        let wrong = SessionIdentification {
+            challenge_answer: "wrong".to_string(),
+            ..sv.session.session_identification()?
+        };
+        assert_eq!(call(&url, "session.get", &wrong).await, bad_answer);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_the_sessions_not_authenticated() -> Result<(), SocialvoidError> {
+        let url = start(Network::new(MemoryStorage::new(), Config::default())).await;
+        let sv = client(&url).await?;
+        sv.session.create().await?;
+        let identification = sv.session.session_identification()?;
+        let not_authenticated = AuthenticationError::NotAuthenticated.code();
+
+        assert_eq!(call(&url, "session.get", &identification).await, None);
+        assert_eq!(
+            call(&url, "network.get_me", &identification).await,
+            Some(not_authenticated)
+        );
+        let document = reqwest::multipart::Part::bytes(&b"hello"[..]).file_name("hello.txt");
+        let upload = cdn(&url, &identification, "upload", document, None).await;
+        assert_eq!(upload.status().as_u16(), 400);
+        let body: Value = upload.json().await.unwrap();
+        assert_eq!(body["error_code"], not_authenticated);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_a_username_taken() -> Result<(), SocialvoidError> {
+        let url = start(Network::new(MemoryStorage::new(), Config::default())).await;
+        register(&url, "light").await?;
+        assert!(matches!(
+            kind(register(&url, "LIGHT").await),
+            Some(ErrorKind::Validation(
+                ValidationError::UsernameAlreadyExists
+            ))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_the_documents_too_large() -> Result<(), SocialvoidError> {
+        let config = Config {
+            upload_max_file_size: 16,
+            ..Config::default()
+        };
+        let url = start(Network::new(MemoryStorage::new(), config)).await;
+        let sv = register(&url, "light").await?;
+        let identification = sv.session.session_identification()?;
+        let upload = |size| {
+            let document = reqwest::multipart::Part::bytes(vec![b'a'; size]).file_name("a.txt");
+            cdn(&url, &identification, "upload", document, None)
+        };
+
+        assert_eq!(upload(16).await.status().as_u16(), 200);
+        let too_large = upload(17).await;
+        assert_eq!(too_large.status().as_u16(), 400);
+        let body: Value = too_large.json().await.unwrap();
+        assert_eq!(body["error_code"], ValidationError::FileTooLarge.code());
+        // a request that can't hold a document small enough isn't read
+        assert_eq!(
+            upload(16 + cdn::FIELDS_SIZE + 1).await.status().as_u16(),
+            413
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_should_download_the_requested_range() -> Result<(), SocialvoidError> {
+        let url = start(Network::new(MemoryStorage::new(), Config::default())).await;
+        let sv = register(&url, "light").await?;
+        let identification = sv.session.session_identification()?;
+        let document = reqwest::multipart::Part::bytes(&b"hello, world"[..]).file_name("a.txt");
+        let uploaded: Value = cdn(&url, &identification, "upload", document, None)
+            .await
+            .json()
+            .await
+            .unwrap();
+        let id = uploaded["results"]["id"].as_str().unwrap().to_string();
+        let download = |range| {
+            let document = reqwest::multipart::Part::text(id.clone());
+            cdn(&url, &identification, "download", document, range)
+        };
+
+        let full = download(None).await;
+        assert_eq!(full.status().as_u16(), 200);
+        assert_eq!(&full.bytes().await.unwrap()[..], b"hello, world");
+        let end = download(Some("bytes=7-")).await;
+        assert_eq!(end.status().as_u16(), 206);
+        assert_eq!(end.headers()["content-range"], "bytes 7-11/12");
+        assert_eq!(&end.bytes().await.unwrap()[..], b"world");
+        let past = download(Some("bytes=12-")).await;
+        assert_eq!(past.status().as_u16(), 416);
+        assert_eq!(past.headers()["content-range"], "bytes */12");
+
+        // the client resumes an interrupted download with a range
+        let file = std::env::temp_dir().join(format!(
+            "socialvoid-server-{}-range.txt",
+            std::process::id()
+        ));
+        let file = file.to_str().unwrap();
+        std::fs::write(format!("{}.part", file), b"hello, ").unwrap();
+        sv.session.download_file_to(id.into(), file).await?;
+        let content = std::fs::read(file).unwrap();
+        std::fs::remove_file(file).unwrap();
+        assert_eq!(content, b"hello, world");
+        Ok(())
+    }
+}
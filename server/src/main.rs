@@ -0,0 +1,61 @@
+//! A Socialvoid server backed by a local SQLite database, for staging and for developing clients
+//! without the public network
+
+use socialvoid_server::storage::SqliteStorage;
+use socialvoid_server::{Config, Network};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use structopt::StructOpt;
+use tokio::net::TcpListener;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "socialvoid-server",
+    about = "A Socialvoid server for local development"
+)]
+struct Opt {
+    /// The address to listen on
+    #[structopt(short, long, default_value = "127.0.0.1:5601")]
+    listen: SocketAddr,
+    /// The SQLite database, created if it doesn't exist
+    #[structopt(short, long, parse(from_os_str), default_value = "socialvoid.db")]
+    database: PathBuf,
+    /// The URL the clients reach the server at, advertised as the CDN server
+    #[structopt(long)]
+    public_url: Option<String>,
+    /// The largest number of results of a page
+    #[structopt(long, default_value = "20")]
+    page_size: u32,
+    /// The size of the largest document that can be uploaded, in bytes
+    #[structopt(long, default_value = "8388608")]
+    upload_max_file_size: u32,
+}
+
+#[tokio::main]
+async fn main() {
+    let opt = Opt::from_args();
+    let config = Config {
+        cdn_server: opt
+            .public_url
+            .clone()
+            .unwrap_or_else(|| format!("http://{}", opt.listen)),
+        page_size: opt.page_size,
+        upload_max_file_size: opt.upload_max_file_size,
+        ..Config::default()
+    };
+    let storage = SqliteStorage::open(&opt.database).unwrap_or_else(|err| {
+        eprintln!("Couldn't open {}: {}", opt.database.display(), err);
+        std::process::exit(1)
+    });
+    let listener = TcpListener::bind(opt.listen).await.unwrap_or_else(|err| {
+        eprintln!("Couldn't listen on {}: {}", opt.listen, err);
+        std::process::exit(1)
+    });
+    println!("Listening on http://{}", opt.listen);
+    let network = Arc::new(Mutex::new(Network::new(storage, config)));
+    if let Err(err) = socialvoid_server::serve(listener, network).await {
+        eprintln!("The server stopped: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,834 @@
+//! The rules of the network: the sessions, the validation and what the API returns.
+//! The records are kept in a `Storage`.
+
+use crate::error::Result;
+use crate::storage::{PeerRecord, PostRecord, Seq, SessionRecord, Storage, Window};
+use rand::Rng;
+use serde_json::json;
+use socialvoid_rawclient::challenge::{answer_challenge_at, TIME_STEP};
+use socialvoid_rawclient::environment::Environment;
+use socialvoid_rawclient::{AuthenticationError, NetworkError, ValidationError};
+use socialvoid_types::{
+    DisplayPictureSize, Document, DocumentId, FileType, Peer, PeerId, PeerRef, PeerType, Post,
+    PostId, PostType, Profile, RelationshipType, SessionId, SessionIdentification,
+};
+
+/// The settings of the network, as advertised by `help.get_server_information`
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub network_name: String,
+    pub cdn_server: String,
+    pub page_size: u32,
+    pub unauthorized_session_ttl: u32,
+    pub authorized_session_ttl: u32,
+    pub upload_max_file_size: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            network_name: "Socialvoid Local".to_string(),
+            cdn_server: "http://127.0.0.1:5601".to_string(),
+            page_size: 20,
+            unauthorized_session_ttl: 600,
+            authorized_session_ttl: 259200,
+            upload_max_file_size: 8 * 1024 * 1024,
+        }
+    }
+}
+
+/// The editable text fields of a profile
+#[derive(Debug, Clone, Copy)]
+pub enum ProfileField {
+    Biography,
+    Location,
+    Url,
+}
+
+fn hash_password(salt: &str, password: &str) -> String {
+    sha256::digest(format!("{}{}", salt, password))
+}
+
+pub struct Network<S> {
+    storage: S,
+    config: Config,
+    environment: Environment,
+}
+
+impl<S: Storage> Network<S> {
+    pub fn new(storage: S, config: Config) -> Network<S> {
+        Network::with_environment(storage, config, Environment::default())
+    }
+
+    /// Like `new`, with the clock and the random number generator of `environment`, e.g. to
+    /// generate the same IDs and challenges every time
+    pub fn with_environment(storage: S, config: Config, environment: Environment) -> Network<S> {
+        Network {
+            storage,
+            config,
+            environment,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn now(&self) -> u64 {
+        self.environment.clock.now()
+    }
+
+    /// A random UUID v4
+    fn generate_id(&self) -> String {
+        let bytes: [u8; 16] = self.environment.rng.lock().unwrap().gen();
+        let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!(
+            "{}-{}-4{}-{:x}{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[13..16],
+            8 | (bytes[8] & 0x3),
+            &hex[17..20],
+            &hex[20..32]
+        )
+    }
+
+    pub fn create_session(
+        &mut self,
+        public_hash: &str,
+        private_hash: &str,
+    ) -> Result<(SessionId, String)> {
+        const BASE32: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+        let challenge: String = {
+            let mut rng = self.environment.rng.lock().unwrap();
+            (0..32)
+                .map(|_| BASE32[rng.gen_range(0..BASE32.len())] as char)
+                .collect()
+        };
+        let now = self.now();
+        let session = SessionRecord {
+            id: self.generate_id().into(),
+            public_hash: public_hash.to_string(),
+            private_hash: private_hash.to_string(),
+            challenge,
+            peer: None,
+            created: now,
+            expires: now + self.config.unauthorized_session_ttl as u64,
+        };
+        self.storage.insert_session(&session)?;
+        Ok((session.id, session.challenge))
+    }
+
+    /// Checks the session identification sent with a request.
+    /// The answer of the previous time step is accepted too, in case it changed in between.
+    fn session(&self, identification: &SessionIdentification) -> Result<SessionRecord> {
+        let session = self
+            .storage
+            .session(&identification.session_id)?
+            .ok_or(AuthenticationError::SessionNotFound)?;
+        let now = self.now();
+        if now >= session.expires {
+            return Err(AuthenticationError::SessionExpired.into());
+        }
+        if session.public_hash != identification.client_public_hash {
+            return Err(ValidationError::InvalidClientPublicHash.into());
+        }
+        let answered = [now, now.saturating_sub(TIME_STEP)].iter().any(|time| {
+            answer_challenge_at(
+                session.private_hash.clone(),
+                session.challenge.clone(),
+                *time,
+            ) == identification.challenge_answer
+        });
+        if !answered {
+            return Err(AuthenticationError::BadSessionChallengeAnswer.into());
+        }
+        Ok(session)
+    }
+
+    /// Checks the session, failing if it doesn't exist or its answer is wrong
+    pub fn check_session(&self, identification: &SessionIdentification) -> Result<()> {
+        self.session(identification).map(|_| ())
+    }
+
+    /// The peer of an authenticated session
+    pub fn authenticated(&self, identification: &SessionIdentification) -> Result<PeerId> {
+        self.session(identification)?
+            .peer
+            .ok_or_else(|| AuthenticationError::NotAuthenticated.into())
+    }
+
+    /// The session, as returned by `session.get`
+    pub fn session_info(
+        &self,
+        identification: &SessionIdentification,
+    ) -> Result<serde_json::Value> {
+        let session = self.session(identification)?;
+        Ok(json!({
+            "id": identification.session_id,
+            "flags": [],
+            "authenticated": session.peer.is_some(),
+            "created": session.created,
+            "expires": session.expires,
+        }))
+    }
+
+    pub fn authenticate(
+        &mut self,
+        identification: &SessionIdentification,
+        username: &str,
+        password: &str,
+    ) -> Result<()> {
+        let mut session = self.session(identification)?;
+        if session.peer.is_some() {
+            return Err(AuthenticationError::AlreadyAuthenticated.into());
+        }
+        let peer = self
+            .storage
+            .peer_by_username(username)?
+            .filter(|peer| hash_password(&peer.password_salt, password) == peer.password_hash)
+            .ok_or(AuthenticationError::IncorrectLoginCredentials)?;
+        session.peer = Some(peer.id);
+        session.expires = self.now() + self.config.authorized_session_ttl as u64;
+        self.storage.update_session(&session)
+    }
+
+    pub fn logout(&mut self, identification: &SessionIdentification) -> Result<()> {
+        self.authenticated(identification)?;
+        let mut session = self.session(identification)?;
+        session.peer = None;
+        session.expires = self.now() + self.config.unauthorized_session_ttl as u64;
+        self.storage.update_session(&session)
+    }
+
+    pub fn register(
+        &mut self,
+        username: &str,
+        password: &str,
+        first_name: &str,
+        last_name: Option<&str>,
+    ) -> Result<Peer> {
+        let valid_username = (1..=32).contains(&username.len())
+            && username
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_username {
+            return Err(ValidationError::InvalidUsername.into());
+        }
+        if password.len() < 8 {
+            return Err(ValidationError::InvalidPassword.into());
+        }
+        check_name(first_name, last_name)?;
+        if self.storage.peer_by_username(username)?.is_some() {
+            return Err(ValidationError::UsernameAlreadyExists.into());
+        }
+        let salt = self.generate_id();
+        let peer = PeerRecord {
+            id: self.generate_id().into(),
+            username: username.to_string(),
+            password_hash: hash_password(&salt, password),
+            password_salt: salt,
+            first_name: first_name.to_string(),
+            last_name: last_name.map(str::to_string),
+            biography: None,
+            location: None,
+            url: None,
+            picture: None,
+            created: self.now(),
+        };
+        self.storage.insert_peer(&peer)?;
+        self.peer(&peer.id)
+    }
+
+    fn peer_record(&self, peer: &PeerId) -> Result<PeerRecord> {
+        Ok(self.storage.peer(peer)?.ok_or(NetworkError::PeerNotFound)?)
+    }
+
+    pub fn peer(&self, peer: &PeerId) -> Result<Peer> {
+        let record = self.peer_record(peer)?;
+        Ok(Peer {
+            id: record.id,
+            peer_type: PeerType::USER,
+            name: full_name(&record.first_name, record.last_name.as_deref()),
+            username: record.username,
+            flags: vec![],
+        })
+    }
+
+    fn peers(&self, ids: Vec<PeerId>) -> Result<Vec<Peer>> {
+        ids.iter().map(|id| self.peer(id)).collect()
+    }
+
+    fn listed_peers(&self, ids: Vec<(Seq, PeerId)>) -> Result<Vec<(Seq, Peer)>> {
+        ids.iter()
+            .map(|(seq, id)| Ok((*seq, self.peer(id)?)))
+            .collect()
+    }
+
+    pub fn resolve(&self, peer: &PeerRef) -> Result<PeerId> {
+        let record = match peer {
+            PeerRef::Id(id) => self.storage.peer(id)?,
+            PeerRef::Username(username) => self.storage.peer_by_username(username)?,
+        };
+        Ok(record.ok_or(NetworkError::PeerNotFound)?.id)
+    }
+
+    pub fn profile(&self, peer: &PeerId) -> Result<Profile> {
+        let record = self.peer_record(peer)?;
+        Ok(Profile {
+            name: full_name(&record.first_name, record.last_name.as_deref()),
+            first_name: record.first_name,
+            last_name: record.last_name,
+            biography: record.biography,
+            location: record.location,
+            url: record.url,
+            followers_count: self.storage.followers_count(peer)? as u32,
+            following_count: self.storage.following_count(peer)? as u32,
+            display_picture_sizes: self.picture_sizes(record.picture)?,
+        })
+    }
+
+    fn picture_sizes(&self, picture: Option<DocumentId>) -> Result<Vec<DisplayPictureSize>> {
+        Ok(match picture {
+            Some(picture) => vec![DisplayPictureSize {
+                width: 640,
+                height: 640,
+                document: self.document(&picture)?,
+            }],
+            None => vec![],
+        })
+    }
+
+    pub fn update_name(
+        &mut self,
+        peer: &PeerId,
+        first_name: &str,
+        last_name: Option<&str>,
+    ) -> Result<()> {
+        check_name(first_name, last_name)?;
+        let mut record = self.peer_record(peer)?;
+        record.first_name = first_name.to_string();
+        record.last_name = last_name.map(str::to_string);
+        self.storage.update_peer(&record)
+    }
+
+    /// Sets a field of the profile, or clears it if `value` is `None`
+    pub fn update_profile(
+        &mut self,
+        peer: &PeerId,
+        field: ProfileField,
+        value: Option<&str>,
+    ) -> Result<()> {
+        let (max_length, error) = match field {
+            ProfileField::Biography => (255, ValidationError::InvalidBiography),
+            ProfileField::Location => (64, ValidationError::InvalidGeoLocation),
+            ProfileField::Url => (255, ValidationError::InvalidUrlValue),
+        };
+        if let Some(value) = value {
+            let invalid_url = matches!(field, ProfileField::Url)
+                && !(value.starts_with("http://") || value.starts_with("https://"));
+            if value.trim().is_empty() || value.chars().count() > max_length || invalid_url {
+                return Err(error.into());
+            }
+        }
+        let mut record = self.peer_record(peer)?;
+        let value = value.map(str::to_string);
+        match field {
+            ProfileField::Biography => record.biography = value,
+            ProfileField::Location => record.location = value,
+            ProfileField::Url => record.url = value,
+        }
+        self.storage.update_peer(&record)
+    }
+
+    pub fn set_picture(
+        &mut self,
+        peer: &PeerId,
+        document: Option<&DocumentId>,
+    ) -> Result<Vec<DisplayPictureSize>> {
+        if let Some(document) = document {
+            if !matches!(self.document(document)?.file_type, FileType::PHOTO) {
+                return Err(ValidationError::InvalidFileForProfilePicture.into());
+            }
+        }
+        let mut record = self.peer_record(peer)?;
+        record.picture = document.cloned();
+        self.storage.update_peer(&record)?;
+        self.picture_sizes(record.picture)
+    }
+
+    pub fn relationship(&self, from: &PeerId, to: &PeerId) -> Result<RelationshipType> {
+        Ok(
+            match (
+                self.storage.follows(from, to)?,
+                self.storage.follows(to, from)?,
+            ) {
+                (true, true) => RelationshipType::MutuallyFollowing,
+                (true, false) => RelationshipType::Following,
+                (false, true) => RelationshipType::FollowsYou,
+                (false, false) => RelationshipType::None,
+            },
+        )
+    }
+
+    pub fn follow(&mut self, from: &PeerId, to: &PeerId, follow: bool) -> Result<RelationshipType> {
+        if from == to {
+            return Err(NetworkError::SelfInteractionNotPermitted.into());
+        }
+        self.peer_record(to)?;
+        self.storage.set_follow(from, to, follow)?;
+        self.relationship(from, to)
+    }
+
+    /// The followers of a peer, latest first with their positions
+    pub fn followers(&self, peer: &PeerId, window: Window) -> Result<Vec<(Seq, Peer)>> {
+        self.peer_record(peer)?;
+        self.listed_peers(self.storage.followers(peer, window)?)
+    }
+
+    /// The peers a peer follows, latest first with their positions
+    pub fn following(&self, peer: &PeerId, window: Window) -> Result<Vec<(Seq, Peer)>> {
+        self.peer_record(peer)?;
+        self.listed_peers(self.storage.following(peer, window)?)
+    }
+
+    fn post_record(&self, post: &PostId) -> Result<PostRecord> {
+        Ok(self.storage.post(post)?.ok_or(NetworkError::PostNotFound)?)
+    }
+
+    /// A post that can be interacted with
+    fn live(&self, post: &PostId) -> Result<PostRecord> {
+        let record = self.post_record(post)?;
+        if matches!(record.post_type, PostType::Deleted) {
+            return Err(NetworkError::PostDeleted.into());
+        }
+        Ok(record)
+    }
+
+    pub fn publish(
+        &mut self,
+        author: &PeerId,
+        post_type: PostType,
+        text: Option<&str>,
+        attachments: &[DocumentId],
+        target: Option<&PostId>,
+    ) -> Result<PostId> {
+        if let Some(text) = text {
+            if text.trim().is_empty() || text.chars().count() > 280 {
+                return Err(ValidationError::InvalidPostText.into());
+            }
+        }
+        for document in attachments {
+            self.document(document)?;
+        }
+        let target = target.map(|target| self.live(target)).transpose()?;
+        let target_id = target.as_ref().map(|target| target.id.clone());
+        let (reply_to, quoted, reposted) = match post_type {
+            PostType::Reply => (target_id, None, None),
+            PostType::Quote => (None, target_id, None),
+            PostType::Repost => (None, None, target_id),
+            _ => (None, None, None),
+        };
+        let thread = reply_to
+            .as_ref()
+            .and(target.as_ref())
+            .map(|target| target.thread.clone().unwrap_or_else(|| target.id.clone()));
+        let post = PostRecord {
+            id: self.generate_id().into(),
+            post_type,
+            author: author.clone(),
+            text: text.map(str::to_string),
+            attachments: attachments.to_vec(),
+            mentioned: self.mentions(text.unwrap_or_default())?,
+            reply_to,
+            quoted,
+            reposted,
+            thread,
+            created: self.now(),
+        };
+        self.storage.insert_post(&post)?;
+        Ok(post.id)
+    }
+
+    /// The peers mentioned in a text, in the order they are first mentioned
+    fn mentions(&self, text: &str) -> Result<Vec<PeerId>> {
+        let mut mentioned: Vec<PeerId> = vec![];
+        for word in text.split_whitespace() {
+            let username = match word.strip_prefix('@') {
+                Some(username) => {
+                    username.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_')
+                }
+                None => continue,
+            };
+            if let Some(peer) = self.storage.peer_by_username(username)? {
+                if !mentioned.contains(&peer.id) {
+                    mentioned.push(peer.id);
+                }
+            }
+        }
+        Ok(mentioned)
+    }
+
+    pub fn repost(&mut self, peer: &PeerId, post: &PostId) -> Result<PostId> {
+        self.live(post)?;
+        let already = self
+            .storage
+            .targeting(post, &PostType::Repost, Window::all())?
+            .iter()
+            .any(|(_, repost)| &repost.author == peer);
+        if already {
+            return Err(NetworkError::AlreadyReposted.into());
+        }
+        self.publish(peer, PostType::Repost, None, &[], Some(post))
+    }
+
+    pub fn delete(&mut self, peer: &PeerId, post: &PostId) -> Result<()> {
+        let mut record = self.live(post)?;
+        if &record.author != peer {
+            return Err(NetworkError::AccessDenied.into());
+        }
+        record.post_type = PostType::Deleted;
+        record.text = None;
+        record.attachments.clear();
+        record.mentioned.clear();
+        self.storage.update_post(&record)
+    }
+
+    pub fn like(&mut self, peer: &PeerId, post: &PostId, like: bool) -> Result<()> {
+        self.live(post)?;
+        self.storage.set_like(post, peer, like)
+    }
+
+    /// The peers that liked a post, latest first with their positions
+    pub fn likes(&self, post: &PostId, window: Window) -> Result<Vec<(Seq, Peer)>> {
+        self.live(post)?;
+        self.listed_peers(self.storage.likes(post, window)?)
+    }
+
+    /// The replies to a post, latest first with their positions
+    pub fn replies(&self, post: &PostId, window: Window) -> Result<Vec<(Seq, Post)>> {
+        self.targeting(post, PostType::Reply, window)
+    }
+
+    /// The quotes of a post, latest first with their positions
+    pub fn quotes(&self, post: &PostId, window: Window) -> Result<Vec<(Seq, Post)>> {
+        self.targeting(post, PostType::Quote, window)
+    }
+
+    fn targeting(
+        &self,
+        post: &PostId,
+        post_type: PostType,
+        window: Window,
+    ) -> Result<Vec<(Seq, Post)>> {
+        self.live(post)?;
+        self.storage
+            .targeting(post, &post_type, window)?
+            .into_iter()
+            .map(|(seq, record)| Ok((seq, self.build_post(record, true)?)))
+            .collect()
+    }
+
+    /// The peers that reposted a post, latest first with the positions of their reposts
+    pub fn reposted_peers(&self, post: &PostId, window: Window) -> Result<Vec<(Seq, Peer)>> {
+        self.live(post)?;
+        let reposts = self.storage.targeting(post, &PostType::Repost, window)?;
+        self.listed_peers(
+            reposts
+                .into_iter()
+                .map(|(seq, repost)| (seq, repost.author))
+                .collect(),
+        )
+    }
+
+    /// The posts of the peer and of the peers it follows, latest first with their positions
+    pub fn feed(&self, peer: &PeerId, window: Window) -> Result<Vec<(Seq, Post)>> {
+        self.storage
+            .feed(peer, window)?
+            .iter()
+            .map(|(seq, id)| Ok((*seq, self.post(id)?)))
+            .collect()
+    }
+
+    /// A post as returned by the API, with the posts it refers to
+    pub fn post(&self, post: &PostId) -> Result<Post> {
+        self.build_post(self.post_record(post)?, true)
+    }
+
+    fn build_post(&self, record: PostRecord, nested: bool) -> Result<Post> {
+        let related = |id: Option<PostId>| -> Result<Option<Box<Post>>> {
+            match id {
+                Some(id) if nested => Ok(Some(Box::new(
+                    self.build_post(self.post_record(&id)?, false)?,
+                ))),
+                _ => Ok(None),
+            }
+        };
+        let count = |post_type: PostType| -> Result<Option<usize>> {
+            Ok(Some(self.storage.targeting_count(&record.id, &post_type)?))
+        };
+        let deleted = matches!(record.post_type, PostType::Deleted);
+        Ok(Post {
+            peer: if deleted {
+                None
+            } else {
+                Some(self.peer(&record.author)?)
+            },
+            source: if deleted {
+                None
+            } else {
+                Some(self.config.network_name.clone())
+            },
+            attachments: record
+                .attachments
+                .iter()
+                .map(|document| self.document(document))
+                .collect::<Result<_>>()?,
+            entities: vec![],
+            mentioned_peers: self.peers(record.mentioned.clone())?,
+            like_count: Some(self.storage.likes_count(&record.id)?),
+            repost_count: count(PostType::Repost)?,
+            quote_count: count(PostType::Quote)?,
+            reply_count: count(PostType::Reply)?,
+            reply_to_post: related(record.reply_to)?,
+            quoted_post: related(record.quoted)?,
+            reposted_post: related(record.reposted)?,
+            original_thread_post: related(record.thread)?,
+            posted_timestamp: record.created,
+            flags: vec![],
+            text: record.text,
+            id: record.id,
+            post_type: record.post_type,
+        })
+    }
+
+    pub fn upload(
+        &mut self,
+        uploader: &PeerId,
+        file_name: &str,
+        content: &[u8],
+    ) -> Result<Document> {
+        if content.len() as u64 > self.config.upload_max_file_size as u64 {
+            return Err(ValidationError::FileTooLarge.into());
+        }
+        let file_name = file_name
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let extension = file_name
+            .rsplit('.')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        let (file_mime, file_type) = match extension.as_str() {
+            "jpg" | "jpeg" => ("image/jpeg", FileType::PHOTO),
+            "png" => ("image/png", FileType::PHOTO),
+            "gif" => ("image/gif", FileType::PHOTO),
+            "webp" => ("image/webp", FileType::PHOTO),
+            "mp4" => ("video/mp4", FileType::VIDEO),
+            "mp3" => ("audio/mpeg", FileType::AUDIO),
+            "txt" => ("text/plain", FileType::DOCUMENT),
+            _ => ("application/octet-stream", FileType::DOCUMENT),
+        };
+        let document = Document {
+            id: self.generate_id().into(),
+            file_mime: file_mime.to_string(),
+            file_name,
+            file_size: content.len() as u32,
+            file_type,
+            flags: vec![],
+        };
+        self.storage
+            .insert_document(&document, uploader, self.now(), content)?;
+        Ok(document)
+    }
+
+    pub fn document(&self, document: &DocumentId) -> Result<Document> {
+        Ok(self
+            .storage
+            .document(document)?
+            .ok_or(NetworkError::DocumentNotFound)?)
+    }
+
+    pub fn download(&self, document: &DocumentId) -> Result<Vec<u8>> {
+        Ok(self
+            .storage
+            .content(document)?
+            .ok_or(NetworkError::DocumentNotFound)?)
+    }
+}
+
+fn check_name(first_name: &str, last_name: Option<&str>) -> Result<()> {
+    let valid = |name: &str| !name.trim().is_empty() && name.chars().count() <= 64;
+    if !valid(first_name) {
+        return Err(ValidationError::InvalidFirstName.into());
+    }
+    if !last_name.is_none_or(valid) {
+        return Err(ValidationError::InvalidLastName.into());
+    }
+    Ok(())
+}
+
+fn full_name(first_name: &str, last_name: Option<&str>) -> String {
+    match last_name {
+        Some(last_name) => format!("{} {}", first_name, last_name),
+        None => first_name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use socialvoid_rawclient::environment::{Clock, SystemClock};
+
+    fn memory() -> Network<MemoryStorage> {
+        Network::new(MemoryStorage::new(), Config::default())
+    }
+
+    #[cfg(feature = "sqlite")]
+    fn sqlite() -> Network<crate::storage::SqliteStorage> {
+        let storage = crate::storage::SqliteStorage::open_in_memory().unwrap();
+        Network::new(storage, Config::default())
+    }
+
+    #[test]
+    fn it_should_generate_uuids() {
+        let id = memory().generate_id();
+        let parts: Vec<_> = id.split('-').map(str::len).collect();
+        assert_eq!(parts, [8, 4, 4, 4, 12]);
+        assert_eq!(&id[14..15], "4");
+        assert!(["8", "9", "a", "b"].contains(&&id[19..20]));
+    }
+
+    #[test]
+    fn it_should_check_the_password() -> Result<()> {
+        check_the_password(memory())?;
+        #[cfg(feature = "sqlite")]
+        check_the_password(sqlite())?;
+        Ok(())
+    }
+
+    fn check_the_password<S: Storage>(mut network: Network<S>) -> Result<()> {
+        network.register("light", "SuperStrongPassword", "Light", Some("Yagami"))?;
+        let (id, challenge) = network.create_session("public", "private")?;
+        let identification = SessionIdentification {
+            session_id: id,
+            client_public_hash: "public".to_string(),
+            challenge_answer: answer_challenge_at(
+                "private".to_string(),
+                challenge,
+                SystemClock.now(),
+            ),
+        };
+        let wrong = network.authenticate(&identification, "light", "WrongPassword");
+        assert_eq!(
+            wrong.unwrap_err().code,
+            AuthenticationError::IncorrectLoginCredentials.code()
+        );
+        network.authenticate(&identification, "LIGHT", "SuperStrongPassword")?;
+        let me = network.authenticated(&identification)?;
+        assert_eq!(network.peer(&me)?.name, "Light Yagami");
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_build_threads_and_counts() -> Result<()> {
+        build_threads_and_counts(memory())?;
+        #[cfg(feature = "sqlite")]
+        build_threads_and_counts(sqlite())?;
+        Ok(())
+    }
+
+    fn build_threads_and_counts<S: Storage>(mut network: Network<S>) -> Result<()> {
+        let light = network
+            .register("light", "SuperStrongPassword", "Light", None)?
+            .id;
+        let ryuk = network
+            .register("ryuk", "SuperStrongPassword", "Ryuk", None)?
+            .id;
+        let root = network.publish(&light, PostType::Post, Some("hi @ryuk"), &[], None)?;
+        let reply = network.publish(&ryuk, PostType::Reply, Some("hey"), &[], Some(&root))?;
+        let nested = network.publish(&light, PostType::Reply, Some("o/"), &[], Some(&reply))?;
+        network.like(&ryuk, &root, true)?;
+        network.repost(&ryuk, &root)?;
+        assert!(network.repost(&ryuk, &root).is_err());
+
+        let post = network.post(&root)?;
+        assert_eq!(post.mentioned_peers[0].username, "ryuk");
+        assert_eq!(
+            (post.like_count, post.reply_count, post.repost_count),
+            (Some(1), Some(1), Some(1))
+        );
+        let nested = network.post(&nested)?;
+        assert_eq!(nested.original_thread_post.unwrap().id, root);
+        assert_eq!(nested.reply_to_post.unwrap().id, reply);
+
+        network.follow(&ryuk, &light, true)?;
+        // the repost, the reply of light and the reply of ryuk
+        assert_eq!(network.feed(&ryuk, Window::all())?.len(), 4);
+        network.delete(&light, &root)?;
+        assert!(network.like(&ryuk, &root, true).is_err());
+        assert!(matches!(network.post(&root)?.post_type, PostType::Deleted));
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_list_the_results_before_a_position() -> Result<()> {
+        list_the_results_before_a_position(memory())?;
+        #[cfg(feature = "sqlite")]
+        list_the_results_before_a_position(sqlite())?;
+        Ok(())
+    }
+
+    fn list_the_results_before_a_position<S: Storage>(mut network: Network<S>) -> Result<()> {
+        let mut register = |username| -> Result<PeerId> {
+            Ok(network
+                .register(username, "SuperStrongPassword", username, None)?
+                .id)
+        };
+        let (light, l, near, mello) = (
+            register("light")?,
+            register("l")?,
+            register("near")?,
+            register("mello")?,
+        );
+        let text = |post: &(Seq, Post)| post.1.text.clone().unwrap();
+        let first = Window {
+            before: None,
+            offset: 0,
+            limit: 2,
+        };
+        for text in &["1", "2", "3"] {
+            network.publish(&light, PostType::Post, Some(text), &[], None)?;
+        }
+        let page = network.feed(&light, first)?;
+        assert_eq!(page.iter().map(text).collect::<Vec<_>>(), ["3", "2"]);
+        // a post published between the pages doesn't move the next one
+        network.publish(&light, PostType::Post, Some("4"), &[], None)?;
+        let next = Window {
+            before: Some(page[1].0),
+            ..first
+        };
+        let page = network.feed(&light, next)?;
+        assert_eq!(page.iter().map(text).collect::<Vec<_>>(), ["1"]);
+
+        for follower in &[&l, &near, &mello] {
+            network.follow(follower, &light, true)?;
+        }
+        let page = network.followers(&light, first)?;
+        let ids: Vec<_> = page.iter().map(|(_, peer)| peer.id.clone()).collect();
+        assert_eq!(ids, [mello.clone(), near.clone()]);
+        // neither does a result of the previous page that is removed
+        network.follow(&mello, &light, false)?;
+        let next = Window {
+            before: Some(page[1].0),
+            ..first
+        };
+        let page = network.followers(&light, next)?;
+        let ids: Vec<_> = page.iter().map(|(_, peer)| peer.id.clone()).collect();
+        assert_eq!(ids, [l]);
+        Ok(())
+    }
+}
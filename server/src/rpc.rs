@@ -0,0 +1,470 @@
+//! The JSON-RPC methods of the server
+
+use crate::error::{Fault, Result};
+use crate::network::{Network, ProfileField};
+use crate::storage::{Seq, Storage, Window};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use socialvoid_rawclient::{RpcErrorCode, ValidationError};
+use socialvoid_types::{
+    Cursor, DocumentId, HelpDocumentId, PeerId, PeerRef, PostId, PostType, SessionIdentification,
+};
+
+pub const TERMS_OF_SERVICE_ID: &str = "terms-of-service";
+const PRIVACY_POLICY_ID: &str = "privacy-policy";
+const COMMUNITY_GUIDELINES_ID: &str = "community-guidelines";
+
+/// Answers a JSON-RPC request
+pub fn handle<S: Storage>(network: &mut Network<S>, body: &[u8]) -> Value {
+    let request: Value = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(_) => return error(Value::Null, RpcErrorCode::ParseError.into()),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    let result = match request.get("method").and_then(Value::as_str) {
+        Some(method) => call(network, method, &params),
+        None => Err(RpcErrorCode::InvalidRequest.into()),
+    };
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(fault) => error(id, fault),
+    }
+}
+
+fn error(id: Value, fault: Fault) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": fault.code, "message": fault.message },
+    })
+}
+
+/// Returns the parameter `name`, failing if it's missing or invalid
+fn param<T: DeserializeOwned>(params: &Value, name: &str) -> Result<T> {
+    serde_json::from_value(params.get(name).cloned().unwrap_or(Value::Null))
+        .map_err(|_| RpcErrorCode::InvalidParams.into())
+}
+
+fn to_value<T: serde::Serialize>(value: T) -> Result<Value> {
+    Ok(serde_json::to_value(value).expect("the results are serializable"))
+}
+
+/// The window of a paginated method, with one more result to know if there's a next page.
+/// Methods called with a `cursor` (even a null one) answer with a `Page` of the results listed
+/// before the cursor, the others with a bare list of the `page`th page.
+fn window<S: Storage>(network: &Network<S>, params: &Value) -> Result<Window> {
+    let size = network.config().page_size.max(1) as usize;
+    let mut window = Window {
+        before: None,
+        offset: 0,
+        limit: size + 1,
+    };
+    if params.get("cursor").is_some() {
+        window.before = param::<Option<Cursor>>(params, "cursor")?
+            .map(|cursor| position(&cursor))
+            .transpose()?;
+    } else {
+        let page = param::<Option<u32>>(params, "page")?.unwrap_or(1).max(1) as usize;
+        window.offset = (page - 1) * size;
+    }
+    Ok(window)
+}
+
+/// The position in its list of the last result of the page before `cursor`. Unlike an offset,
+/// it still points at the same result when results are added or removed, so the pages don't
+/// skip or repeat results.
+fn position(cursor: &Cursor) -> Result<Seq> {
+    cursor
+        .as_str()
+        .parse()
+        .map_err(|_| ValidationError::InvalidCursorValue.into())
+}
+
+fn paginate<T: serde::Serialize>(
+    params: &Value,
+    window: Window,
+    mut results: Vec<(Seq, T)>,
+) -> Result<Value> {
+    let size = window.limit - 1;
+    let more = results.len() > size;
+    results.truncate(size);
+    let next_cursor = results
+        .last()
+        .filter(|_| more)
+        .map(|(seq, _)| Cursor::from(seq.to_string()));
+    let results: Vec<T> = results.into_iter().map(|(_, result)| result).collect();
+    if params.get("cursor").is_some() {
+        return to_value(json!({ "results": results, "next_cursor": next_cursor }));
+    }
+    to_value(results)
+}
+
+fn help_document(id: &str, text: &str) -> Value {
+    json!({ "id": id, "text": text, "entities": [] })
+}
+
+/// Calls a method, returning its result
+pub fn call<S: Storage>(network: &mut Network<S>, method: &str, params: &Value) -> Result<Value> {
+    let config = network.config();
+    match method {
+        "help.get_server_information" => to_value(json!({
+            "network_name": config.network_name,
+            "protocol_version": "1.0",
+            "cdn_server": config.cdn_server,
+            "upload_max_file_size": config.upload_max_file_size,
+            "unauthorized_session_ttl": config.unauthorized_session_ttl,
+            "authorized_session_ttl": config.authorized_session_ttl,
+            "retrieve_likes_max_limit": config.page_size,
+            "retrieve_reposts_max_limit": config.page_size,
+            "retrieve_replies_max_limit": config.page_size,
+            "retrieve_quotes_max_limit": config.page_size,
+            "retrieve_followers_max_limit": config.page_size,
+            "retrieve_following_max_limit": config.page_size,
+            "retrieve_feed_max_limit": config.page_size,
+        })),
+        "help.get_terms_of_service" => Ok(help_document(
+            TERMS_OF_SERVICE_ID,
+            "This network is meant for staging and development. Anything posted here can be \
+             wiped at any time.",
+        )),
+        "help.get_privacy_policy" => Ok(help_document(
+            PRIVACY_POLICY_ID,
+            "Everything is kept by the server, readable by whoever runs it.",
+        )),
+        "help.get_community_guidelines" => Ok(help_document(
+            COMMUNITY_GUIDELINES_ID,
+            "Be nice to the other developers.",
+        )),
+        "session.create" => {
+            for name in &["name", "platform", "version"] {
+                if param::<String>(params, name)?.is_empty() {
+                    return Err(RpcErrorCode::InvalidParams.into());
+                }
+            }
+            let (id, challenge) = network.create_session(
+                &param::<String>(params, "public_hash")?,
+                &param::<String>(params, "private_hash")?,
+            )?;
+            to_value(json!({ "id": id, "challenge": challenge }))
+        }
+        _ => {
+            let identification: SessionIdentification = param(params, "session_identification")
+                .map_err(|_| Fault::from(ValidationError::InvalidSessionIdentification))?;
+            call_on_session(network, method, params, &identification)
+        }
+    }
+}
+
+/// Calls a method needing a session
+fn call_on_session<S: Storage>(
+    network: &mut Network<S>,
+    method: &str,
+    params: &Value,
+    identification: &SessionIdentification,
+) -> Result<Value> {
+    match method {
+        "session.get" => network.session_info(identification),
+        "session.authenticate_user" => {
+            let username: String = param(params, "username")?;
+            let password: String = param(params, "password")?;
+            network.authenticate(identification, &username, &password)?;
+            Ok(Value::Bool(true))
+        }
+        "session.register" => {
+            network.check_session(identification)?;
+            if !param::<bool>(params, "terms_of_service_agree")? {
+                return Err(ValidationError::AgreementRequired.into());
+            }
+            if param::<HelpDocumentId>(params, "terms_of_service_id")? != *TERMS_OF_SERVICE_ID {
+                return Err(ValidationError::InvalidHelpDocumentId.into());
+            }
+            let peer = network.register(
+                &param::<String>(params, "username")?,
+                &param::<String>(params, "password")?,
+                &param::<String>(params, "first_name")?,
+                param::<Option<String>>(params, "last_name")?.as_deref(),
+            )?;
+            to_value(peer)
+        }
+        "session.logout" => {
+            network.logout(identification)?;
+            Ok(Value::Bool(true))
+        }
+        _ => {
+            let me = network.authenticated(identification)?;
+            call_as(network, method, params, &me)
+        }
+    }
+}
+
+/// The peer of the `peer` parameter, or `me` if it's missing
+fn peer<S: Storage>(network: &Network<S>, params: &Value, me: &PeerId) -> Result<PeerId> {
+    match param::<Option<String>>(params, "peer")? {
+        Some(peer) => {
+            let peer = PeerRef::parse(&peer)
+                .map_err(|_| Fault::from(ValidationError::InvalidPeerInput))?;
+            network.resolve(&peer)
+        }
+        None => Ok(me.clone()),
+    }
+}
+
+/// Calls a method needing an authenticated session, as `me`
+fn call_as<S: Storage>(
+    network: &mut Network<S>,
+    method: &str,
+    params: &Value,
+    me: &PeerId,
+) -> Result<Value> {
+    let post = || param::<PostId>(params, "post");
+    match method {
+        "network.get_me" => to_value(network.peer(me)?),
+        "network.get_profile" => to_value(network.profile(&peer(network, params, me)?)?),
+        "network.resolve_peer" => to_value(network.peer(&peer(network, params, me)?)?),
+        "network.follow_peer" => {
+            let peer = peer(network, params, me)?;
+            to_value(network.follow(me, &peer, true)?)
+        }
+        "network.unfollow_peer" => {
+            let peer = peer(network, params, me)?;
+            to_value(network.follow(me, &peer, false)?)
+        }
+        "network.get_followers" => {
+            let window = window(network, params)?;
+            paginate(
+                params,
+                window,
+                network.followers(&peer(network, params, me)?, window)?,
+            )
+        }
+        "network.get_following" => {
+            let window = window(network, params)?;
+            paginate(
+                params,
+                window,
+                network.following(&peer(network, params, me)?, window)?,
+            )
+        }
+        "timeline.compose" => publish(network, params, me, PostType::Post, None),
+        "timeline.reply" => publish(network, params, me, PostType::Reply, Some(&post()?)),
+        "timeline.quote" => publish(network, params, me, PostType::Quote, Some(&post()?)),
+        "timeline.repost" => {
+            network.repost(me, &post()?)?;
+            Ok(Value::Bool(true))
+        }
+        "timeline.delete" => {
+            network.delete(me, &post()?)?;
+            Ok(Value::Bool(true))
+        }
+        "timeline.get_post" => to_value(network.post(&post()?)?),
+        "timeline.like" => {
+            network.like(me, &post()?, true)?;
+            Ok(Value::Bool(true))
+        }
+        "timeline.unlike" => {
+            network.like(me, &post()?, false)?;
+            Ok(Value::Bool(true))
+        }
+        "timeline.retrieve_feed" => {
+            let window = window(network, params)?;
+            paginate(params, window, network.feed(me, window)?)
+        }
+        "timeline.get_likes" => {
+            let window = window(network, params)?;
+            paginate(params, window, network.likes(&post()?, window)?)
+        }
+        "timeline.get_replies" => {
+            let window = window(network, params)?;
+            paginate(params, window, network.replies(&post()?, window)?)
+        }
+        "timeline.get_quotes" => {
+            let window = window(network, params)?;
+            paginate(params, window, network.quotes(&post()?, window)?)
+        }
+        "timeline.get_reposted_peers" => {
+            let window = window(network, params)?;
+            paginate(params, window, network.reposted_peers(&post()?, window)?)
+        }
+        "account.set_profile_picture" => {
+            let document = param::<DocumentId>(params, "document")?;
+            to_value(network.set_picture(me, Some(&document))?)
+        }
+        "account.delete_profile_picture" => {
+            network.set_picture(me, None)?;
+            Ok(Value::Bool(true))
+        }
+        "account.update_profile_name" => {
+            network.update_name(
+                me,
+                &param::<String>(params, "first_name")?,
+                param::<Option<String>>(params, "last_name")?.as_deref(),
+            )?;
+            Ok(Value::Bool(true))
+        }
+        "account.update_profile_biography" => {
+            update_profile(network, params, me, ProfileField::Biography, "biography")
+        }
+        "account.clear_profile_biography" => clear_profile(network, me, ProfileField::Biography),
+        "account.update_profile_location" => {
+            update_profile(network, params, me, ProfileField::Location, "location")
+        }
+        "account.clear_profile_location" => clear_profile(network, me, ProfileField::Location),
+        "account.update_profile_url" => {
+            update_profile(network, params, me, ProfileField::Url, "url")
+        }
+        "account.clear_profile_url" => clear_profile(network, me, ProfileField::Url),
+        _ => Err(RpcErrorCode::MethodNotFound.into()),
+    }
+}
+
+fn publish<S: Storage>(
+    network: &mut Network<S>,
+    params: &Value,
+    me: &PeerId,
+    post_type: PostType,
+    target: Option<&PostId>,
+) -> Result<Value> {
+    let text = param::<String>(params, "text")?;
+    let attachments = param::<Option<Vec<DocumentId>>>(params, "attachments")?.unwrap_or_default();
+    let id = network.publish(me, post_type, Some(&text), &attachments, target)?;
+    to_value(network.post(&id)?)
+}
+
+/// Sets a field of the profile to the parameter `name`
+fn update_profile<S: Storage>(
+    network: &mut Network<S>,
+    params: &Value,
+    me: &PeerId,
+    field: ProfileField,
+    name: &str,
+) -> Result<Value> {
+    network.update_profile(me, field, Some(&param::<String>(params, name)?))?;
+    Ok(Value::Bool(true))
+}
+
+fn clear_profile<S: Storage>(
+    network: &mut Network<S>,
+    me: &PeerId,
+    field: ProfileField,
+) -> Result<Value> {
+    network.update_profile(me, field, None)?;
+    Ok(Value::Bool(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Config;
+    use crate::storage::MemoryStorage;
+    use socialvoid_rawclient::challenge::answer_challenge_at;
+    use socialvoid_rawclient::environment::{Environment, ManualClock};
+    use std::sync::Arc;
+
+    const NOW: u64 = 1_600_000_000;
+
+    fn network(config: Config) -> Network<MemoryStorage> {
+        let environment = Environment::new(Arc::new(ManualClock::new(NOW)), rand::rngs::OsRng);
+        Network::with_environment(MemoryStorage::new(), config, environment)
+    }
+
+    /// Sends a request, returning its result or the code of its error
+    fn request(
+        network: &mut Network<MemoryStorage>,
+        method: &str,
+        params: Value,
+    ) -> std::result::Result<Value, i64> {
+        let request = json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params });
+        let response = handle(network, &serde_json::to_vec(&request).unwrap());
+        assert_eq!(response["id"], 7);
+        match response["error"]["code"].as_i64() {
+            Some(code) => Err(code),
+            None => Ok(response["result"].clone()),
+        }
+    }
+
+    /// The identification of a new session
+    fn identification(network: &mut Network<MemoryStorage>) -> SessionIdentification {
+        let (session_id, challenge) = network.create_session("public", "private").unwrap();
+        SessionIdentification {
+            session_id,
+            client_public_hash: "public".to_string(),
+            challenge_answer: answer_challenge_at("private".to_string(), challenge, NOW),
+        }
+    }
+
+    #[test]
+    fn it_should_answer_the_malformed_requests() -> Result<()> {
+        let mut network = network(Config::default());
+        let code = |error: Fault| Err(error.code as i64);
+
+        let response = handle(&mut network, b"{");
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["code"], RpcErrorCode::ParseError.code());
+        let response = handle(&mut network, br#"{"jsonrpc": "2.0", "id": 1}"#);
+        assert_eq!(
+            response["error"]["code"],
+            RpcErrorCode::InvalidRequest.code()
+        );
+        assert_eq!(
+            request(&mut network, "session.create", json!({ "name": "test" })),
+            code(RpcErrorCode::InvalidParams.into())
+        );
+        assert_eq!(
+            request(&mut network, "session.get", json!({})),
+            code(ValidationError::InvalidSessionIdentification.into())
+        );
+        network.register("light", "SuperStrongPassword", "Light", None)?;
+        let identification = identification(&mut network);
+        network.authenticate(&identification, "light", "SuperStrongPassword")?;
+        assert_eq!(
+            request(
+                &mut network,
+                "timeline.wipe",
+                json!({ "session_identification": identification })
+            ),
+            code(RpcErrorCode::MethodNotFound.into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_page_with_cursors() -> Result<()> {
+        let mut network = network(Config {
+            page_size: 2,
+            ..Config::default()
+        });
+        let light = network.register("light", "SuperStrongPassword", "Light", None)?;
+        let identification = identification(&mut network);
+        network.authenticate(&identification, "light", "SuperStrongPassword")?;
+        for text in &["1", "2", "3"] {
+            network.publish(&light.id, PostType::Post, Some(text), &[], None)?;
+        }
+        let mut feed = |params: Value| {
+            let mut params = params;
+            params["session_identification"] = json!(identification);
+            request(&mut network, "timeline.retrieve_feed", params)
+        };
+        let texts = |posts: &Value| -> Vec<String> {
+            posts
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|post| post["text"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        // without a cursor, the integer pages answer with a bare list
+        assert_eq!(texts(&feed(json!({ "page": 2 })).unwrap()), ["1"]);
+        let page = feed(json!({ "cursor": null })).unwrap();
+        assert_eq!(texts(&page["results"]), ["3", "2"]);
+        let page = feed(json!({ "cursor": page["next_cursor"] })).unwrap();
+        assert_eq!(texts(&page["results"]), ["1"]);
+        assert_eq!(page["next_cursor"], Value::Null);
+        assert_eq!(
+            feed(json!({ "cursor": "2 posts later" })),
+            Err(ValidationError::InvalidCursorValue.code() as i64)
+        );
+        Ok(())
+    }
+}
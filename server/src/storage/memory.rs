@@ -0,0 +1,239 @@
+//! A network kept in memory, forgotten when it's dropped
+
+use super::{PeerRecord, PostRecord, Seq, SessionRecord, Storage, Window};
+use crate::error::Result;
+use socialvoid_types::{Document, DocumentId, PeerId, PostId, PostType, SessionId};
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct MemoryStorage {
+    sessions: HashMap<SessionId, SessionRecord>,
+    peers: HashMap<PeerId, PeerRecord>,
+    /// In the order they were published, so the position of a post is its index plus one
+    posts: Vec<PostRecord>,
+    /// `(position, follower, followed)`, in the order they were followed
+    follows: Vec<(Seq, PeerId, PeerId)>,
+    /// `(position, post, peer)`, in the order they were liked
+    likes: Vec<(Seq, PostId, PeerId)>,
+    /// The last position given to a follow or a like
+    seq: Seq,
+    documents: HashMap<DocumentId, (Document, Vec<u8>)>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+
+    fn next_seq(&mut self) -> Seq {
+        self.seq += 1;
+        self.seq
+    }
+
+    /// The posts with their positions
+    fn posts(&self) -> impl DoubleEndedIterator<Item = (Seq, &PostRecord)> {
+        self.posts
+            .iter()
+            .enumerate()
+            .map(|(index, post)| (index as Seq + 1, post))
+    }
+}
+
+/// The items of `window`, from the latest of `items`
+fn latest<T, I>(items: I, window: Window) -> Vec<(Seq, T)>
+where
+    I: DoubleEndedIterator<Item = (Seq, T)>,
+{
+    items
+        .rev()
+        .filter(|(seq, _)| window.before.is_none_or(|before| *seq < before))
+        .skip(window.offset)
+        .take(window.limit)
+        .collect()
+}
+
+impl Storage for MemoryStorage {
+    fn insert_session(&mut self, session: &SessionRecord) -> Result<()> {
+        self.sessions.insert(session.id.clone(), session.clone());
+        Ok(())
+    }
+
+    fn session(&self, id: &SessionId) -> Result<Option<SessionRecord>> {
+        Ok(self.sessions.get(id).cloned())
+    }
+
+    fn update_session(&mut self, session: &SessionRecord) -> Result<()> {
+        self.insert_session(session)
+    }
+
+    fn insert_peer(&mut self, peer: &PeerRecord) -> Result<()> {
+        self.peers.insert(peer.id.clone(), peer.clone());
+        Ok(())
+    }
+
+    fn peer(&self, id: &PeerId) -> Result<Option<PeerRecord>> {
+        Ok(self.peers.get(id).cloned())
+    }
+
+    fn peer_by_username(&self, username: &str) -> Result<Option<PeerRecord>> {
+        Ok(self
+            .peers
+            .values()
+            .find(|peer| peer.username.eq_ignore_ascii_case(username))
+            .cloned())
+    }
+
+    fn update_peer(&mut self, peer: &PeerRecord) -> Result<()> {
+        self.insert_peer(peer)
+    }
+
+    fn follows(&self, follower: &PeerId, followed: &PeerId) -> Result<bool> {
+        Ok(self
+            .follows
+            .iter()
+            .any(|(_, from, to)| from == follower && to == followed))
+    }
+
+    fn set_follow(&mut self, follower: &PeerId, followed: &PeerId, follow: bool) -> Result<()> {
+        if !follow {
+            self.follows
+                .retain(|(_, from, to)| !(from == follower && to == followed));
+        } else if !self.follows(follower, followed)? {
+            let seq = self.next_seq();
+            self.follows.push((seq, follower.clone(), followed.clone()));
+        }
+        Ok(())
+    }
+
+    fn followers(&self, peer: &PeerId, window: Window) -> Result<Vec<(Seq, PeerId)>> {
+        Ok(latest(
+            self.follows
+                .iter()
+                .filter(|(_, _, followed)| followed == peer)
+                .map(|(seq, follower, _)| (*seq, follower.clone())),
+            window,
+        ))
+    }
+
+    fn following(&self, peer: &PeerId, window: Window) -> Result<Vec<(Seq, PeerId)>> {
+        Ok(latest(
+            self.follows
+                .iter()
+                .filter(|(_, follower, _)| follower == peer)
+                .map(|(seq, _, followed)| (*seq, followed.clone())),
+            window,
+        ))
+    }
+
+    fn followers_count(&self, peer: &PeerId) -> Result<usize> {
+        Ok(self.followers(peer, Window::all())?.len())
+    }
+
+    fn following_count(&self, peer: &PeerId) -> Result<usize> {
+        Ok(self.following(peer, Window::all())?.len())
+    }
+
+    fn insert_post(&mut self, post: &PostRecord) -> Result<()> {
+        self.posts.push(post.clone());
+        Ok(())
+    }
+
+    fn post(&self, id: &PostId) -> Result<Option<PostRecord>> {
+        Ok(self.posts.iter().find(|post| &post.id == id).cloned())
+    }
+
+    fn update_post(&mut self, post: &PostRecord) -> Result<()> {
+        if let Some(stored) = self.posts.iter_mut().find(|stored| stored.id == post.id) {
+            *stored = post.clone();
+        }
+        Ok(())
+    }
+
+    fn targeting(
+        &self,
+        post: &PostId,
+        post_type: &PostType,
+        window: Window,
+    ) -> Result<Vec<(Seq, PostRecord)>> {
+        Ok(latest(
+            self.posts()
+                .filter(|(_, stored)| stored.target(post_type) == Some(post))
+                .map(|(seq, stored)| (seq, stored.clone())),
+            window,
+        ))
+    }
+
+    fn targeting_count(&self, post: &PostId, post_type: &PostType) -> Result<usize> {
+        Ok(self
+            .posts
+            .iter()
+            .filter(|stored| stored.target(post_type) == Some(post))
+            .count())
+    }
+
+    fn feed(&self, peer: &PeerId, window: Window) -> Result<Vec<(Seq, PostId)>> {
+        let following: Vec<PeerId> = self
+            .following(peer, Window::all())?
+            .into_iter()
+            .map(|(_, followed)| followed)
+            .collect();
+        Ok(latest(
+            self.posts()
+                .filter(|(_, post)| {
+                    !matches!(post.post_type, PostType::Deleted)
+                        && (&post.author == peer || following.contains(&post.author))
+                })
+                .map(|(seq, post)| (seq, post.id.clone())),
+            window,
+        ))
+    }
+
+    fn set_like(&mut self, post: &PostId, peer: &PeerId, like: bool) -> Result<()> {
+        let liked = |(_, liked, liker): &(Seq, PostId, PeerId)| liked == post && liker == peer;
+        if !like {
+            self.likes.retain(|like| !liked(like));
+        } else if !self.likes.iter().any(liked) {
+            let seq = self.next_seq();
+            self.likes.push((seq, post.clone(), peer.clone()));
+        }
+        Ok(())
+    }
+
+    fn likes(&self, post: &PostId, window: Window) -> Result<Vec<(Seq, PeerId)>> {
+        Ok(latest(
+            self.likes
+                .iter()
+                .filter(|(_, liked, _)| liked == post)
+                .map(|(seq, _, peer)| (*seq, peer.clone())),
+            window,
+        ))
+    }
+
+    fn likes_count(&self, post: &PostId) -> Result<usize> {
+        Ok(self
+            .likes
+            .iter()
+            .filter(|(_, liked, _)| liked == post)
+            .count())
+    }
+
+    fn insert_document(
+        &mut self,
+        document: &Document,
+        _uploader: &PeerId,
+        _created: u64,
+        content: &[u8],
+    ) -> Result<()> {
+        self.documents
+            .insert(document.id.clone(), (document.clone(), content.to_vec()));
+        Ok(())
+    }
+
+    fn document(&self, id: &DocumentId) -> Result<Option<Document>> {
+        Ok(self.documents.get(id).map(|(document, _)| document.clone()))
+    }
+
+    fn content(&self, id: &DocumentId) -> Result<Option<Vec<u8>>> {
+        Ok(self.documents.get(id).map(|(_, content)| content.clone()))
+    }
+}
@@ -0,0 +1,140 @@
+//! Where the network is kept. The rules of the network are in `Network`, so a `Storage` only
+//! saves and finds the records.
+
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use memory::MemoryStorage;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStorage;
+
+use crate::error::Result;
+use socialvoid_types::{Document, DocumentId, PeerId, PostId, PostType, SessionId};
+
+/// The position of a result in its list: the order it was added in. Unlike an offset, it stays
+/// the same when results are added to or removed from the list.
+pub type Seq = u64;
+
+/// A range of the results of a list, latest first, e.g. a page
+#[derive(Debug, Clone, Copy)]
+pub struct Window {
+    /// Only the results added before the one at this position
+    pub before: Option<Seq>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Window {
+    /// Every result
+    pub fn all() -> Window {
+        Window {
+            before: None,
+            offset: 0,
+            limit: usize::MAX >> 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionRecord {
+    pub id: SessionId,
+    pub public_hash: String,
+    pub private_hash: String,
+    pub challenge: String,
+    /// The peer the session is authenticated as
+    pub peer: Option<PeerId>,
+    pub created: u64,
+    pub expires: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PeerRecord {
+    pub id: PeerId,
+    pub username: String,
+    pub password_salt: String,
+    pub password_hash: String,
+    pub first_name: String,
+    pub last_name: Option<String>,
+    pub biography: Option<String>,
+    pub location: Option<String>,
+    pub url: Option<String>,
+    pub picture: Option<DocumentId>,
+    pub created: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PostRecord {
+    pub id: PostId,
+    pub post_type: PostType,
+    pub author: PeerId,
+    pub text: Option<String>,
+    pub attachments: Vec<DocumentId>,
+    pub mentioned: Vec<PeerId>,
+    pub reply_to: Option<PostId>,
+    pub quoted: Option<PostId>,
+    pub reposted: Option<PostId>,
+    /// The first post of the thread of a reply
+    pub thread: Option<PostId>,
+    pub created: u64,
+}
+
+impl PostRecord {
+    /// The post it replies to, quotes or reposts, if it's a post of `post_type`
+    pub fn target(&self, post_type: &PostType) -> Option<&PostId> {
+        match (post_type, &self.post_type) {
+            (PostType::Reply, PostType::Reply) => self.reply_to.as_ref(),
+            (PostType::Quote, PostType::Quote) => self.quoted.as_ref(),
+            (PostType::Repost, PostType::Repost) => self.reposted.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// The records of a network. The lists are ordered latest first, each result with its position.
+pub trait Storage: Send {
+    fn insert_session(&mut self, session: &SessionRecord) -> Result<()>;
+    fn session(&self, id: &SessionId) -> Result<Option<SessionRecord>>;
+    fn update_session(&mut self, session: &SessionRecord) -> Result<()>;
+
+    fn insert_peer(&mut self, peer: &PeerRecord) -> Result<()>;
+    fn peer(&self, id: &PeerId) -> Result<Option<PeerRecord>>;
+    /// The peer with a username, ignoring the case
+    fn peer_by_username(&self, username: &str) -> Result<Option<PeerRecord>>;
+    fn update_peer(&mut self, peer: &PeerRecord) -> Result<()>;
+
+    fn follows(&self, follower: &PeerId, followed: &PeerId) -> Result<bool>;
+    fn set_follow(&mut self, follower: &PeerId, followed: &PeerId, follow: bool) -> Result<()>;
+    fn followers(&self, peer: &PeerId, window: Window) -> Result<Vec<(Seq, PeerId)>>;
+    fn following(&self, peer: &PeerId, window: Window) -> Result<Vec<(Seq, PeerId)>>;
+    fn followers_count(&self, peer: &PeerId) -> Result<usize>;
+    fn following_count(&self, peer: &PeerId) -> Result<usize>;
+
+    fn insert_post(&mut self, post: &PostRecord) -> Result<()>;
+    fn post(&self, id: &PostId) -> Result<Option<PostRecord>>;
+    fn update_post(&mut self, post: &PostRecord) -> Result<()>;
+    /// The posts of `post_type` replying to, quoting or reposting `post`
+    fn targeting(
+        &self,
+        post: &PostId,
+        post_type: &PostType,
+        window: Window,
+    ) -> Result<Vec<(Seq, PostRecord)>>;
+    fn targeting_count(&self, post: &PostId, post_type: &PostType) -> Result<usize>;
+    /// The posts that aren't deleted of the peer and of the peers it follows
+    fn feed(&self, peer: &PeerId, window: Window) -> Result<Vec<(Seq, PostId)>>;
+
+    fn set_like(&mut self, post: &PostId, peer: &PeerId, like: bool) -> Result<()>;
+    fn likes(&self, post: &PostId, window: Window) -> Result<Vec<(Seq, PeerId)>>;
+    fn likes_count(&self, post: &PostId) -> Result<usize>;
+
+    fn insert_document(
+        &mut self,
+        document: &Document,
+        uploader: &PeerId,
+        created: u64,
+        content: &[u8],
+    ) -> Result<()>;
+    fn document(&self, id: &DocumentId) -> Result<Option<Document>>;
+    fn content(&self, id: &DocumentId) -> Result<Option<Vec<u8>>>;
+}
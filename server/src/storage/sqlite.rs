@@ -0,0 +1,552 @@
+//! A network stored in a SQLite database
+
+use super::{PeerRecord, PostRecord, Seq, SessionRecord, Storage, Window};
+use crate::error::Result;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use socialvoid_types::{Document, DocumentId, FileType, PeerId, PostId, PostType, SessionId};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS peers (
+    id TEXT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    password_salt TEXT NOT NULL,
+    password_hash TEXT NOT NULL,
+    first_name TEXT NOT NULL,
+    last_name TEXT,
+    biography TEXT,
+    location TEXT,
+    url TEXT,
+    picture TEXT,
+    created INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    public_hash TEXT NOT NULL,
+    private_hash TEXT NOT NULL,
+    challenge TEXT NOT NULL,
+    peer TEXT REFERENCES peers (id),
+    created INTEGER NOT NULL,
+    expires INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS documents (
+    id TEXT PRIMARY KEY,
+    file_mime TEXT NOT NULL,
+    file_name TEXT NOT NULL,
+    file_type TEXT NOT NULL,
+    content BLOB NOT NULL,
+    uploader TEXT NOT NULL REFERENCES peers (id),
+    created INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS posts (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    id TEXT NOT NULL UNIQUE,
+    post_type TEXT NOT NULL,
+    author TEXT NOT NULL REFERENCES peers (id),
+    text TEXT,
+    reply_to TEXT,
+    quoted TEXT,
+    reposted TEXT,
+    thread TEXT,
+    created INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS attachments (
+    post TEXT NOT NULL REFERENCES posts (id),
+    position INTEGER NOT NULL,
+    document TEXT NOT NULL REFERENCES documents (id),
+    PRIMARY KEY (post, position)
+);
+CREATE TABLE IF NOT EXISTS mentions (
+    post TEXT NOT NULL REFERENCES posts (id),
+    peer TEXT NOT NULL REFERENCES peers (id),
+    PRIMARY KEY (post, peer)
+);
+CREATE TABLE IF NOT EXISTS likes (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    post TEXT NOT NULL REFERENCES posts (id),
+    peer TEXT NOT NULL REFERENCES peers (id),
+    UNIQUE (post, peer)
+);
+CREATE TABLE IF NOT EXISTS follows (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    follower TEXT NOT NULL REFERENCES peers (id),
+    followed TEXT NOT NULL REFERENCES peers (id),
+    UNIQUE (follower, followed)
+);
+";
+
+const POST_COLUMNS: &str =
+    "id, post_type, author, text, reply_to, quoted, reposted, thread, created";
+
+/// The name of a unit variant, as it is serialized by the API
+fn name_of<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => unreachable!("only unit variants are stored by name"),
+    }
+}
+
+fn from_name<T: DeserializeOwned>(name: String) -> Option<T> {
+    serde_json::from_value(serde_json::Value::String(name)).ok()
+}
+
+/// The column of the posts pointing at the target of the posts of `post_type`
+fn target_column(post_type: &PostType) -> &'static str {
+    match post_type {
+        PostType::Reply => "reply_to",
+        PostType::Quote => "quoted",
+        _ => "reposted",
+    }
+}
+
+pub struct SqliteStorage {
+    conn: Connection,
+}
+
+impl SqliteStorage {
+    /// Opens the database at `path`, creating it if needed
+    pub fn open<P: AsRef<Path>>(path: P) -> rusqlite::Result<SqliteStorage> {
+        SqliteStorage::with_connection(Connection::open(path)?)
+    }
+
+    /// A database that only lasts as long as the storage
+    pub fn open_in_memory() -> rusqlite::Result<SqliteStorage> {
+        SqliteStorage::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> rusqlite::Result<SqliteStorage> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(SqliteStorage { conn })
+    }
+
+    /// Runs a query of IDs with the parameter `?1`
+    fn ids<T: From<String>>(&self, sql: &str, param: &str) -> Result<Vec<T>> {
+        let mut statement = self.conn.prepare(sql)?;
+        let ids = statement
+            .query_map([param], |row| row.get::<_, String>(0))?
+            .map(|id| id.map(T::from))
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(ids)
+    }
+
+    /// Runs a query of the positions and IDs of a list with the parameter `?1` and a window.
+    /// `sql` ends with its `WHERE` clause, the window is added to it.
+    fn listed<T: From<String>>(
+        &self,
+        sql: &str,
+        param: &str,
+        window: Window,
+    ) -> Result<Vec<(Seq, T)>> {
+        let mut statement = self.conn.prepare(&format!(
+            "{} AND seq < ?2 ORDER BY seq DESC LIMIT ?3 OFFSET ?4",
+            sql
+        ))?;
+        let before = window.before.map_or(i64::MAX, |before| before as i64);
+        let ids = statement
+            .query_map(
+                params![param, before, window.limit as i64, window.offset as i64],
+                |row| Ok((row.get::<_, i64>(0)? as Seq, row.get::<_, String>(1)?)),
+            )?
+            .map(|row| row.map(|(seq, id)| (seq, T::from(id))))
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(ids)
+    }
+
+    fn count(&self, sql: &str, param: &str) -> Result<usize> {
+        let count: i64 = self.conn.query_row(sql, [param], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    fn peer_where(&self, condition: &str, param: &str) -> Result<Option<PeerRecord>> {
+        Ok(self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT id, username, password_salt, password_hash, first_name, last_name,
+                     biography, location, url, picture, created FROM peers WHERE {}",
+                    condition
+                ),
+                [param],
+                |row| {
+                    Ok(PeerRecord {
+                        id: row.get::<_, String>(0)?.into(),
+                        username: row.get(1)?,
+                        password_salt: row.get(2)?,
+                        password_hash: row.get(3)?,
+                        first_name: row.get(4)?,
+                        last_name: row.get(5)?,
+                        biography: row.get(6)?,
+                        location: row.get(7)?,
+                        url: row.get(8)?,
+                        picture: row.get::<_, Option<String>>(9)?.map(DocumentId::from),
+                        created: row.get::<_, i64>(10)? as u64,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// A post of a row of `POST_COLUMNS`, without its attachments and mentions
+    fn post_row(row: &Row) -> rusqlite::Result<PostRecord> {
+        let id = |index| -> rusqlite::Result<Option<PostId>> {
+            Ok(row.get::<_, Option<String>>(index)?.map(PostId::from))
+        };
+        Ok(PostRecord {
+            id: row.get::<_, String>(0)?.into(),
+            post_type: from_name(row.get(1)?).unwrap_or(PostType::Unknown),
+            author: row.get::<_, String>(2)?.into(),
+            text: row.get(3)?,
+            attachments: vec![],
+            mentioned: vec![],
+            reply_to: id(4)?,
+            quoted: id(5)?,
+            reposted: id(6)?,
+            thread: id(7)?,
+            created: row.get::<_, i64>(8)? as u64,
+        })
+    }
+
+    fn with_relations(&self, mut post: PostRecord) -> Result<PostRecord> {
+        post.attachments = self.ids(
+            "SELECT document FROM attachments WHERE post = ?1 ORDER BY position",
+            post.id.as_str(),
+        )?;
+        post.mentioned = self.ids(
+            "SELECT peer FROM mentions WHERE post = ?1",
+            post.id.as_str(),
+        )?;
+        Ok(post)
+    }
+
+    fn insert_relations(&self, post: &PostRecord) -> Result<()> {
+        for (position, document) in post.attachments.iter().enumerate() {
+            self.conn.execute(
+                "INSERT INTO attachments (post, position, document) VALUES (?1, ?2, ?3)",
+                params![post.id.as_str(), position as i64, document.as_str()],
+            )?;
+        }
+        for peer in &post.mentioned {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO mentions (post, peer) VALUES (?1, ?2)",
+                [post.id.as_str(), peer.as_str()],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn insert_session(&mut self, session: &SessionRecord) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO sessions (id, public_hash, private_hash, challenge, peer, created, expires)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                session.id.as_str(),
+                session.public_hash,
+                session.private_hash,
+                session.challenge,
+                session.peer.as_ref().map(PeerId::as_str),
+                session.created as i64,
+                session.expires as i64
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn session(&self, id: &SessionId) -> Result<Option<SessionRecord>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT public_hash, private_hash, challenge, peer, created, expires
+                 FROM sessions WHERE id = ?1",
+                [id.as_str()],
+                |row| {
+                    Ok(SessionRecord {
+                        id: id.clone(),
+                        public_hash: row.get(0)?,
+                        private_hash: row.get(1)?,
+                        challenge: row.get(2)?,
+                        peer: row.get::<_, Option<String>>(3)?.map(PeerId::from),
+                        created: row.get::<_, i64>(4)? as u64,
+                        expires: row.get::<_, i64>(5)? as u64,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    fn update_session(&mut self, session: &SessionRecord) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET peer = ?1, expires = ?2 WHERE id = ?3",
+            params![
+                session.peer.as_ref().map(PeerId::as_str),
+                session.expires as i64,
+                session.id.as_str()
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn insert_peer(&mut self, peer: &PeerRecord) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO peers (id, username, password_salt, password_hash, first_name, last_name,
+             biography, location, url, picture, created)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                peer.id.as_str(),
+                peer.username,
+                peer.password_salt,
+                peer.password_hash,
+                peer.first_name,
+                peer.last_name,
+                peer.biography,
+                peer.location,
+                peer.url,
+                peer.picture.as_ref().map(DocumentId::as_str),
+                peer.created as i64
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn peer(&self, id: &PeerId) -> Result<Option<PeerRecord>> {
+        self.peer_where("id = ?1", id.as_str())
+    }
+
+    fn peer_by_username(&self, username: &str) -> Result<Option<PeerRecord>> {
+        self.peer_where("username = ?1", username)
+    }
+
+    fn update_peer(&mut self, peer: &PeerRecord) -> Result<()> {
+        self.conn.execute(
+            "UPDATE peers SET first_name = ?1, last_name = ?2, biography = ?3, location = ?4,
+             url = ?5, picture = ?6 WHERE id = ?7",
+            params![
+                peer.first_name,
+                peer.last_name,
+                peer.biography,
+                peer.location,
+                peer.url,
+                peer.picture.as_ref().map(DocumentId::as_str),
+                peer.id.as_str()
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn follows(&self, follower: &PeerId, followed: &PeerId) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM follows WHERE follower = ?1 AND followed = ?2)",
+            [follower.as_str(), followed.as_str()],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn set_follow(&mut self, follower: &PeerId, followed: &PeerId, follow: bool) -> Result<()> {
+        let sql = if follow {
+            "INSERT OR IGNORE INTO follows (follower, followed) VALUES (?1, ?2)"
+        } else {
+            "DELETE FROM follows WHERE follower = ?1 AND followed = ?2"
+        };
+        self.conn
+            .execute(sql, [follower.as_str(), followed.as_str()])?;
+        Ok(())
+    }
+
+    fn followers(&self, peer: &PeerId, window: Window) -> Result<Vec<(Seq, PeerId)>> {
+        self.listed(
+            "SELECT seq, follower FROM follows WHERE followed = ?1",
+            peer.as_str(),
+            window,
+        )
+    }
+
+    fn following(&self, peer: &PeerId, window: Window) -> Result<Vec<(Seq, PeerId)>> {
+        self.listed(
+            "SELECT seq, followed FROM follows WHERE follower = ?1",
+            peer.as_str(),
+            window,
+        )
+    }
+
+    fn followers_count(&self, peer: &PeerId) -> Result<usize> {
+        self.count(
+            "SELECT COUNT(*) FROM follows WHERE followed = ?1",
+            peer.as_str(),
+        )
+    }
+
+    fn following_count(&self, peer: &PeerId) -> Result<usize> {
+        self.count(
+            "SELECT COUNT(*) FROM follows WHERE follower = ?1",
+            peer.as_str(),
+        )
+    }
+
+    fn insert_post(&mut self, post: &PostRecord) -> Result<()> {
+        self.conn.execute(
+            &format!(
+                "INSERT INTO posts ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                POST_COLUMNS
+            ),
+            params![
+                post.id.as_str(),
+                name_of(&post.post_type),
+                post.author.as_str(),
+                post.text,
+                post.reply_to.as_ref().map(PostId::as_str),
+                post.quoted.as_ref().map(PostId::as_str),
+                post.reposted.as_ref().map(PostId::as_str),
+                post.thread.as_ref().map(PostId::as_str),
+                post.created as i64
+            ],
+        )?;
+        self.insert_relations(post)
+    }
+
+    fn post(&self, id: &PostId) -> Result<Option<PostRecord>> {
+        let post = self
+            .conn
+            .query_row(
+                &format!("SELECT {} FROM posts WHERE id = ?1", POST_COLUMNS),
+                [id.as_str()],
+                SqliteStorage::post_row,
+            )
+            .optional()?;
+        post.map(|post| self.with_relations(post)).transpose()
+    }
+
+    fn update_post(&mut self, post: &PostRecord) -> Result<()> {
+        self.conn.execute(
+            "UPDATE posts SET post_type = ?1, text = ?2 WHERE id = ?3",
+            params![name_of(&post.post_type), post.text, post.id.as_str()],
+        )?;
+        self.conn.execute(
+            "DELETE FROM attachments WHERE post = ?1",
+            [post.id.as_str()],
+        )?;
+        self.conn
+            .execute("DELETE FROM mentions WHERE post = ?1", [post.id.as_str()])?;
+        self.insert_relations(post)
+    }
+
+    fn targeting(
+        &self,
+        post: &PostId,
+        post_type: &PostType,
+        window: Window,
+    ) -> Result<Vec<(Seq, PostRecord)>> {
+        let ids: Vec<(Seq, PostId)> = self.listed(
+            &format!(
+                "SELECT seq, id FROM posts WHERE {} = ?1 AND post_type = '{}'",
+                target_column(post_type),
+                name_of(post_type)
+            ),
+            post.as_str(),
+            window,
+        )?;
+        ids.iter()
+            .filter_map(|(seq, id)| Some(self.post(id).transpose()?.map(|post| (*seq, post))))
+            .collect()
+    }
+
+    fn targeting_count(&self, post: &PostId, post_type: &PostType) -> Result<usize> {
+        self.count(
+            &format!(
+                "SELECT COUNT(*) FROM posts WHERE {} = ?1 AND post_type = '{}'",
+                target_column(post_type),
+                name_of(post_type)
+            ),
+            post.as_str(),
+        )
+    }
+
+    fn feed(&self, peer: &PeerId, window: Window) -> Result<Vec<(Seq, PostId)>> {
+        self.listed(
+            "SELECT seq, id FROM posts WHERE post_type != 'DELETED' AND (author = ?1
+             OR author IN (SELECT followed FROM follows WHERE follower = ?1))",
+            peer.as_str(),
+            window,
+        )
+    }
+
+    fn set_like(&mut self, post: &PostId, peer: &PeerId, like: bool) -> Result<()> {
+        let sql = if like {
+            "INSERT OR IGNORE INTO likes (post, peer) VALUES (?1, ?2)"
+        } else {
+            "DELETE FROM likes WHERE post = ?1 AND peer = ?2"
+        };
+        self.conn.execute(sql, [post.as_str(), peer.as_str()])?;
+        Ok(())
+    }
+
+    fn likes(&self, post: &PostId, window: Window) -> Result<Vec<(Seq, PeerId)>> {
+        self.listed(
+            "SELECT seq, peer FROM likes WHERE post = ?1",
+            post.as_str(),
+            window,
+        )
+    }
+
+    fn likes_count(&self, post: &PostId) -> Result<usize> {
+        self.count("SELECT COUNT(*) FROM likes WHERE post = ?1", post.as_str())
+    }
+
+    fn insert_document(
+        &mut self,
+        document: &Document,
+        uploader: &PeerId,
+        created: u64,
+        content: &[u8],
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO documents (id, file_mime, file_name, file_type, content, uploader, created)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                document.id.as_str(),
+                document.file_mime,
+                document.file_name,
+                name_of(&document.file_type),
+                content,
+                uploader.as_str(),
+                created as i64
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn document(&self, id: &DocumentId) -> Result<Option<Document>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT file_mime, file_name, file_type, length(content)
+                 FROM documents WHERE id = ?1",
+                [id.as_str()],
+                |row| {
+                    Ok(Document {
+                        id: id.clone(),
+                        file_mime: row.get(0)?,
+                        file_name: row.get(1)?,
+                        file_type: from_name(row.get(2)?).unwrap_or(FileType::DOCUMENT),
+                        file_size: row.get(3)?,
+                        flags: vec![],
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    fn content(&self, id: &DocumentId) -> Result<Option<Vec<u8>>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT content FROM documents WHERE id = ?1",
+                [id.as_str()],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+}
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+/// Generates a newtype around an opaque `String`, like the ID of an object.
+/// The newtype is serialized as the bare string so it can be used in place of the `String`.
+macro_rules! id_types {
+    ($($(#[$meta:meta])* $name:ident;)*) => {
+        $(
+            $(#[$meta])*
+            #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+            #[serde(transparent)]
+            pub struct $name(String);
+
+            impl $name {
+                pub fn as_str(&self) -> &str {
+                    &self.0
+                }
+
+                pub fn into_string(self) -> String {
+                    self.0
+                }
+            }
+
+            impl From<String> for $name {
+                fn from(id: String) -> Self {
+                    $name(id)
+                }
+            }
+
+            impl From<&str> for $name {
+                fn from(id: &str) -> Self {
+                    $name(id.to_string())
+                }
+            }
+
+            impl FromStr for $name {
+                type Err = Infallible;
+
+                fn from_str(id: &str) -> Result<Self, Self::Err> {
+                    Ok($name(id.to_string()))
+                }
+            }
+
+            impl From<$name> for String {
+                fn from(id: $name) -> Self {
+                    id.0
+                }
+            }
+
+            impl AsRef<str> for $name {
+                fn as_ref(&self) -> &str {
+                    &self.0
+                }
+            }
+
+            impl PartialEq<str> for $name {
+                fn eq(&self, other: &str) -> bool {
+                    self.0 == other
+                }
+            }
+
+            impl PartialEq<&str> for $name {
+                fn eq(&self, other: &&str) -> bool {
+                    self.0 == *other
+                }
+            }
+
+            impl fmt::Display for $name {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    write!(f, "{}", self.0)
+                }
+            }
+        )*
+    };
+}
+
+id_types! {
+    /// The ID of a `Post`
+    PostId;
+    /// The ID of a `Peer`
+    PeerId;
+    /// The ID of a `Document` stored on the CDN
+    DocumentId;
+    /// The ID of a session
+    SessionId;
+    /// The ID of a `HelpDocument`
+    HelpDocumentId;
+    /// An opaque position in a paginated list, as returned in `Page::next_cursor`
+    Cursor;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_serialize_as_a_bare_string() -> Result<(), serde_json::Error> {
+        let id = PostId::from("0c0a1c0e-6bd1-4d3b-a5d6-6ea4f1dd0a2d");
+        let json = serde_json::to_string(&id)?;
+        assert_eq!(json, "\"0c0a1c0e-6bd1-4d3b-a5d6-6ea4f1dd0a2d\"");
+        assert_eq!(serde_json::from_str::<PostId>(&json)?, id);
+        assert_eq!(id, "0c0a1c0e-6bd1-4d3b-a5d6-6ea4f1dd0a2d");
+        Ok(())
+    }
+}
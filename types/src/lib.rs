@@ -1,16 +1,21 @@
+mod ids;
+mod peer_ref;
+
+pub use ids::{Cursor, DocumentId, HelpDocumentId, PeerId, PostId, SessionId};
+pub use peer_ref::{PeerRef, PeerRefError};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SessionIdentification {
-    pub session_id: String,
+    pub session_id: SessionId,
     pub client_public_hash: String,
     pub challenge_answer: String,
 }
 
 /// A Peer Object that contains information about the peer
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Peer {
-    pub id: String,
+    pub id: PeerId,
     #[serde(rename = "type")]
     pub peer_type: PeerType,
     pub name: String,
@@ -18,23 +23,23 @@ pub struct Peer {
     pub flags: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum PeerType {
     USER,
     BOT,
     PROXY,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DisplayPictureSize {
     pub width: u32,
     pub height: u32,
     pub document: Document,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Document {
-    pub id: String,
+    pub id: DocumentId,
     pub file_mime: String,
     pub file_name: String,
     pub file_size: u32,
@@ -42,7 +47,7 @@ pub struct Document {
     pub flags: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum FileType {
     DOCUMENT,
     PHOTO,
@@ -53,7 +58,7 @@ pub enum FileType {
 /// HelpDocument -> https://github.com/intellivoid/Socialvoid-Standard-Documentation/blob/master/Objects/HelpDocument.md
 #[derive(Serialize, Deserialize, Debug)]
 pub struct HelpDocument {
-    pub id: String,
+    pub id: HelpDocumentId,
     pub text: String,
     pub entities: Vec<TextEntity>,
 }
@@ -74,7 +79,7 @@ impl HelpDocument {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TextEntity {
     #[serde(rename = "type")]
     entity_type: TextEntityType,
@@ -83,7 +88,7 @@ pub struct TextEntity {
     value: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum TextEntityType {
     BOLD,
     ITALIC,
@@ -125,6 +130,20 @@ pub struct Profile {
     pub display_picture_sizes: Vec<DisplayPictureSize>,
 }
 
+impl Profile {
+    /// Returns the display picture that best fits a `size`x`size` box, i.e. the smallest one
+    /// that is at least as large in both dimensions, or the largest one if none of them are.
+    /// Returns `None` if no display picture is set.
+    pub fn best_display_picture(&self, size: u32) -> Option<&DisplayPictureSize> {
+        let area = |picture: &&DisplayPictureSize| picture.width as u64 * picture.height as u64;
+        self.display_picture_sizes
+            .iter()
+            .filter(|picture| picture.width >= size && picture.height >= size)
+            .min_by_key(area)
+            .or_else(|| self.display_picture_sizes.iter().max_by_key(area))
+    }
+}
+
 /// Relationship of a peer with another peer.
 /// https://github.com/intellivoid/Socialvoid-Standard-Documentation/blob/master/Types/RelationshipTypes.md
 #[derive(Serialize, Deserialize, Debug)]
@@ -140,9 +159,9 @@ pub enum RelationshipType {
 }
 
 /// Post
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Post {
-    pub id: String,
+    pub id: PostId,
     #[serde(rename = "type")]
     pub post_type: PostType,
     pub peer: Option<Peer>,
@@ -163,8 +182,24 @@ pub struct Post {
     pub flags: Vec<String>,
 }
 
-/// Post Type
+/// A page of the results of a paginated method requested with a cursor.
+/// `next_cursor` is the cursor of the following page, or `None` if this is the last page.
+/// Unlike integer pages, a cursor points at a fixed position so results aren't skipped or
+/// repeated when the list changes between requests.
 #[derive(Serialize, Deserialize, Debug)]
+pub struct Page<T> {
+    pub results: Vec<T>,
+    pub next_cursor: Option<Cursor>,
+}
+
+impl<T> Page<T> {
+    pub fn is_last(&self) -> bool {
+        self.next_cursor.is_none()
+    }
+}
+
+/// Post Type
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum PostType {
     Unknown,
@@ -174,3 +209,77 @@ pub enum PostType {
     Quote,
     Repost,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picture(width: u32, height: u32) -> DisplayPictureSize {
+        DisplayPictureSize {
+            width,
+            height,
+            document: Document {
+                id: format!("{}x{}", width, height).into(),
+                file_mime: "image/jpeg".to_string(),
+                file_name: format!("{}x{}.jpg", width, height),
+                file_size: width * height,
+                file_type: FileType::PHOTO,
+                flags: vec![],
+            },
+        }
+    }
+
+    fn profile(display_picture_sizes: Vec<DisplayPictureSize>) -> Profile {
+        Profile {
+            first_name: "Light".to_string(),
+            last_name: None,
+            name: "Light".to_string(),
+            biography: None,
+            location: None,
+            url: None,
+            followers_count: 0,
+            following_count: 0,
+            display_picture_sizes,
+        }
+    }
+
+    #[test]
+    fn it_should_pick_the_smallest_picture_that_fits() {
+        let profile = profile(vec![
+            picture(640, 640),
+            picture(128, 128),
+            picture(256, 256),
+            picture(512, 512),
+        ]);
+        let best = |size| {
+            profile
+                .best_display_picture(size)
+                .unwrap()
+                .document
+                .id
+                .clone()
+        };
+        assert_eq!(best(256), "256x256");
+        assert_eq!(best(200), "256x256");
+        assert_eq!(best(1), "128x128");
+        assert_eq!(best(1024), "640x640");
+    }
+
+    #[test]
+    fn it_should_return_none_without_pictures() {
+        assert!(profile(vec![]).best_display_picture(256).is_none());
+    }
+
+    #[test]
+    fn it_should_deserialize_pages() -> Result<(), serde_json::Error> {
+        let page: Page<u32> = serde_json::from_str(r#"{"results": [1, 2], "next_cursor": "c2"}"#)?;
+        assert_eq!(page.results, vec![1, 2]);
+        assert_eq!(page.next_cursor, Some(Cursor::from("c2")));
+
+        let page: Page<u32> = serde_json::from_str(r#"{"results": [3], "next_cursor": null}"#)?;
+        assert!(page.is_last());
+
+        assert!(serde_json::from_str::<Page<u32>>("[1, 2, 3]").is_err());
+        Ok(())
+    }
+}
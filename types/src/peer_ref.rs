@@ -0,0 +1,167 @@
+use crate::{Peer, PeerId};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// A reference to a peer, as accepted by the methods taking a `peer` parameter.
+/// It is written as the ID of the peer or as its username with a leading `@`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PeerRef {
+    Id(PeerId),
+    /// The username, without the leading `@`
+    Username(String),
+}
+
+/// The reason a string isn't a valid `PeerRef`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerRefError {
+    Empty,
+    /// A username can only contain letters, digits and underscores
+    InvalidUsername(String),
+    /// An ID can't contain whitespace or `@`
+    InvalidId(String),
+}
+
+impl PeerRef {
+    /// Parses an ID, or a username if the input starts with `@`
+    pub fn parse(input: &str) -> Result<Self, PeerRefError> {
+        match input.strip_prefix('@') {
+            Some(username) => Self::username(username),
+            None => Self::id(input),
+        }
+    }
+
+    /// A reference to the peer with this ID
+    pub fn id(id: &str) -> Result<Self, PeerRefError> {
+        if id.is_empty() {
+            return Err(PeerRefError::Empty);
+        }
+        if id.chars().any(|c| c.is_whitespace() || c == '@') {
+            return Err(PeerRefError::InvalidId(id.to_string()));
+        }
+        Ok(PeerRef::Id(id.into()))
+    }
+
+    /// A reference to the peer with this username (without the leading `@`)
+    pub fn username(username: &str) -> Result<Self, PeerRefError> {
+        if username.is_empty() {
+            return Err(PeerRefError::Empty);
+        }
+        if !username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(PeerRefError::InvalidUsername(username.to_string()));
+        }
+        Ok(PeerRef::Username(username.to_string()))
+    }
+}
+
+impl fmt::Display for PeerRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeerRef::Id(id) => write!(f, "{}", id),
+            PeerRef::Username(username) => write!(f, "@{}", username),
+        }
+    }
+}
+
+impl FromStr for PeerRef {
+    type Err = PeerRefError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        PeerRef::parse(input)
+    }
+}
+
+impl From<&Peer> for PeerRef {
+    fn from(peer: &Peer) -> Self {
+        PeerRef::Id(peer.id.clone())
+    }
+}
+
+impl From<PeerId> for PeerRef {
+    fn from(id: PeerId) -> Self {
+        PeerRef::Id(id)
+    }
+}
+
+impl Serialize for PeerRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PeerRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let input = String::deserialize(deserializer)?;
+        PeerRef::parse(&input).map_err(D::Error::custom)
+    }
+}
+
+impl fmt::Display for PeerRefError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeerRefError::Empty => write!(f, "the peer is empty"),
+            PeerRefError::InvalidUsername(username) => write!(
+                f,
+                "`@{}` is not a valid username, usernames can only contain letters, digits and underscores",
+                username
+            ),
+            PeerRefError::InvalidId(id) => write!(
+                f,
+                "`{}` is not a valid peer ID. Did you forget to prefix with `@` in case of a username?",
+                id
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PeerRefError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PeerType;
+
+    #[test]
+    fn it_should_parse_ids_and_usernames() {
+        assert_eq!(
+            "@apple_bot".parse(),
+            Ok(PeerRef::Username("apple_bot".to_string()))
+        );
+        assert_eq!(
+            "b8a4e4c2-1f1f-4c8e-9d1a-4ab6e1f5d0c3".parse(),
+            Ok(PeerRef::Id("b8a4e4c2-1f1f-4c8e-9d1a-4ab6e1f5d0c3".into()))
+        );
+        assert_eq!("".parse::<PeerRef>(), Err(PeerRefError::Empty));
+        assert_eq!("@".parse::<PeerRef>(), Err(PeerRefError::Empty));
+        assert!(matches!(
+            "@apple bot".parse::<PeerRef>(),
+            Err(PeerRefError::InvalidUsername(_))
+        ));
+        assert!(matches!(
+            "apple@bot".parse::<PeerRef>(),
+            Err(PeerRefError::InvalidId(_))
+        ));
+    }
+
+    #[test]
+    fn it_should_serialize_as_a_string() -> Result<(), serde_json::Error> {
+        let username = PeerRef::Username("apple_bot".to_string());
+        assert_eq!(serde_json::to_string(&username)?, "\"@apple_bot\"");
+        assert_eq!(serde_json::from_str::<PeerRef>("\"@apple_bot\"")?, username);
+        assert!(serde_json::from_str::<PeerRef>("\"@\"").is_err());
+
+        let peer = Peer {
+            id: "42".into(),
+            peer_type: PeerType::USER,
+            name: "Light".to_string(),
+            username: "justanotherlight".to_string(),
+            flags: vec![],
+        };
+        assert_eq!(serde_json::to_string(&PeerRef::from(&peer))?, "\"42\"");
+        Ok(())
+    }
+}